@@ -0,0 +1,33 @@
+# input: \x02\x0c\xff\xfd\x01\x61\xfb\xe3\xd2\x80
+# output: 2: 2\n12: 2 2 3\n255: 3 5 17\n253: 11 23\n1:\n97: 97\n251: 251\n227: 227\n210: 2 3 5 7\n128: 2 2 2 2 2 2 2\n
+[ factor
+  Prints the prime factors of each input byte by trial division
+  Cells  r d t x y rem q else 0 f 0 p c with the decimal printer using p and the cells after it
+]
+
+read the next number n into r at cell 0 and loop until the end of input
+,[
+
+print n in decimal followed by a colon
+[->>>>>>>>>>>+<<<<<<<<<+<<]>>[-<<+>>]>>>>>>>>>>>++++++++++<<[->+>-[>+>>]>[+[-<+>]>+>>]<<<<<<]>>[-]>>>++++++++++<[->-[>+>>]>[+[-<+>]>+>>]<<<<<]>[-]>>[>++++++[-<++++++++>]<.<<+>+>[-]]<[<[->-<]++++++[->++++++++<]>.[-]]<<++++++[-<++++++++>]<.[-]<<[-<+>]<[-]>++++++++++++++++++++++++++++++++++++++++++++++++++++++++++.[-]
+
+start with the divisor d equal to 2 in cell 1
+<<<<<<<<<<<++
+
+loop while r is not 1 with the flag f in cell 9
+<[->>>>>>>>>+<<<<<<<+<<]>>[-<<+>>]>>>>>>>-[
+
+divide a copy of r by a copy of d with the remainder in cell 5 and the quotient in cell 6
+<<<<<<<<<[->>>+<+<<]>>[-<<+>>]<[->>>+<<+<]>[-<+>]>[->-[>+>>]>[+[-<+>]>+>>]<<<<<]>[-]
+
+if the remainder is not 0 then try the next divisor
+>>>+<<[[-]<<<<+>>>>>>-<<]
+
+else print a space and d and continue with the quotient as r
+>>[->>>>>++++++++++++++++++++++++++++++++.[-]<<<<<<<<<<<[->>>>>>>>>>+<<<<<<<<<+<]>[-<+>]>>>>>>>>>>>++++++++++<<[->+>-[>+>>]>[+[-<+>]>+>>]<<<<<<]>>[-]>>>++++++++++<[->-[>+>>]>[+[-<+>]>+>>]<<<<<]>[-]>>[>++++++[-<++++++++>]<.<<+>+>[-]]<[<[->-<]++++++[->++++++++<]>.[-]]<<++++++[-<++++++++>]<.[-]<<[-<+>]<[-]<<<<<<<<<<<[-]>>>>>>[-<<<<<<+>>>>>>]>]<[-]
+
+update the flag f from r
+>>>[-]<<<<<<<<<[->>>>>>>>>+<<<<<<<+<<]>>[-<<+>>]>>>>>>>-]
+
+print a newline and clear r and d before reading the next number
+>>>++++++++++.[-]<<<<<<<<<<<<[-]>[-]<,]
@@ -0,0 +1,83 @@
+[ hanoi
+  Solves the towers of Hanoi with 7 disks from peg A to peg C
+  Move m moves the disk of the lowest set bit low of m
+  from peg (m minus low) mod 3 to peg (m plus low) mod 3
+  Compiled by bf-interp optimize -O0 from the structured language program
+
+  var m = 1;
+  while m < 128 {
+      var low = 1;
+      var disk = '1';
+      while m / low % 2 == 0 {
+          low = low * 2;
+          disk = disk + 1;
+      }
+      print disk, ": ", 'A' + (m - low) % 3, " -> ", 'A' + (m + low) % 3, "\n";
+      m = m + 1;
+  }
+]
+>>>+<<<[-]>>>[-<<<+>>>]<<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+++++++++++++++++++++++++
+++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++
++++++++++++++++++++++++[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[
+-]>>>]<<<]<[-]>>[-<<+>>]<<[>+<<<[-]>>>[-<<<+>>>]++++++++++++++++++++++++++++++++
++++++++++++++++++<<[-]>>[-<<+>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>
++>+<<<<<]>>>>>[-<<<<<+>>>>>]<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<
+<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>>>
+]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+<<
+]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<[[-]<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]<[-<
+<<<->>>>]<<+<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[
+->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>
+-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->
+[[-]>+<]<]>[-]>[-<<+>>]<<]<<[-]<[-]>>[-<<+>>]<++[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>
+>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<
+<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<
+]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<[[-]<<[->>>+>+<<<<]>
+>>>[-<<<<+>>>>]<[-<<<<->>>>]<<+<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>
+[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>
+>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>
++<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<]<<[-]>[-]<[-<->]>+<<[[-]>>-<<]>>[-<
+<+>>]<<[<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]++<[->>+<<]>>[-<[-<+>>>+<<]>>[-<<+>
+>]<]<[-]<<<<<[-]>>>>[-<<<<+>>>>]<<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+[-<+>]<<<<[-]>>
+>[-<<<+>>>]<[-]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<
+<<<<+>>>>>]<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[-
+>>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-
+<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->[
+[-]>+<]<]>[-]>[-<<+>>]<<[[-]<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]<[-<<<<->>>>]<<+<[->>
++>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>
+>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]
+<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-
+<<+>>]<<]<<[-]<[-]>>[-<<+>>]<++[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[
+-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>
+>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+
+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<[[-]<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]<[
+-<<<<->>>>]<<+<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<
+<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->
+>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[
+->[[-]>+<]<]>[-]>[-<<+>>]<<]<<[-]>[-]<[-<->]>+<<[[-]>>-<<]>>[-<<+>>]<<]<<[->>+>+
+<<<]>>>[-<<<+>>>]<.[-]++++++++++++++++++++++++++++++++++++++++++++++++++++++++++
+.--------------------------.--------------------------------++++++++++++++++++++
++++++++++++++++++++++++++++++++++++++++++++++<<<<[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<
++>>>>>>]<<<<<[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<[-<->]+++[->>+>+<<<]>>>[-<<
+<+>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>
+>]<[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<
++>>]<+<[[-]>-<]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<[[-]<<
+[->>>+>+<<<<]>>>>[-<<<<+>>>>]<[-<<<<->>>>]<<+<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>
++>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<
+<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>
+[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<]<<[-]>[-]<<[-<+>]<.[-
+]++++++++++++++++++++++++++++++++.+++++++++++++.+++++++++++++++++.--------------
+----------------.--------------------------------+++++++++++++++++++++++++++++++
+++++++++++++++++++++++++++++++++++<<<<[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<<<
+<<[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<[-<+>]+++[->>+>+<<<]>>>[-<<<+>>>]<<<<[
+->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+
+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]
+>-<]>[-<+>]<<[[-]>>+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<[[-]<<[->>>+>+<<<
+<]>>>>[-<<<<+>>>>]<[-<<<<->>>>]<<+<[->>+>+<<<]>>>[-<<<+>>>]<<<<[->>>>+>+<<<<<]>>
+>>>[-<<<<<+>>>>>]<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]<[<[->>>+>+<<<<]>>>>[-<<<
+<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<<+>>]<+<[[-]>-<]>[-<+>]<<[[-
+]>>+<<]>>[-<<+>>]<<[->[[-]>+<]<]>[-]>[-<<+>>]<<]<<[-]>[-]<<[-<+>]<.[-]++++++++++
+.----------<<<<[->>>>+>+<<<<<]>>>>>[-<<<<<+>>>>>]+[-<+>]<<<<<[-]>>>>[-<<<<+>>>>]
+<[-]<<<[->>>+>+<<<<]>>>>[-<<<<+>>>>]++++++++++++++++++++++++++++++++++++++++++++
+++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++
+++++[<[->>>+>+<<<<]>>>>[-<<<<+>>>>]+<[[-]<<<->->>>-<]>[-<<+<[-]>>>]<<<]<[-]>>[-<
+<+>>]<<]
@@ -0,0 +1 @@
+output-file: hanoi.out
@@ -0,0 +1,13 @@
+# output: \x72\x72
+[ loops
+  Four nested loops with 45 and 46 and 47 and 37 iterations
+  The innermost loop increments two cells 3599730 times
+]
++++++++++++++++++++++++++++++++++++++++++++++[>
+  ++++++++++++++++++++++++++++++++++++++++++++++[>
+    +++++++++++++++++++++++++++++++++++++++++++++++[>
+      +++++++++++++++++++++++++++++++++++++[>+>+<<-]
+    <-]
+  <-]
+<-]
+>>>>.>.
@@ -0,0 +1,17 @@
+# output: A
+[ sweep
+  Fills 250 cells with ones and sweeps the data pointer
+  over them 250 times in each direction with scan loops
+  Cells  counter sentinel ones
+]
+set the sweep counter
++++++ +++++ [>+++++ +++++ +++++ +++++ +++++<-]>[<+++++ ++++>-]<++++++++++
+fill cells 2 to 251 with ones
+>>+++++ +++++ [>+++++ +++++ +++++ +++++ +++++<-]>[<+++++ ++++>-]<++++++++++
+[[->+<]+>-]
+return to the counter
+<[<]<
+sweep right and back
+[>>[>]<[<]<-]
+print the first one as A
+>>++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++.
@@ -0,0 +1,140 @@
+//! Benchmark harness running the corpus on all engines and optimization levels.
+//!
+//! Run it with `cargo bench --bench engines -- [OPTIONS] [FILTER...]`:
+//! * `--samples N` timed runs per engine and optimization level (default: 3),
+//! * `--save-baseline FILE` saves the measurements as baseline,
+//! * `--baseline FILE` fails on regressions against the baseline,
+//! * `--tolerance PERCENT` accepted slowdown against the baseline (default: 20),
+//! * `FILTER` only runs the benchmarks whose name contains one of the filters.
+//!
+//! The corpus consists of `examples/mandelbrot.bf` and the programs in
+//! `benches/corpus`, which carry specs like the tests of `bf-interp test`.
+//! Further `.bf` files in `benches/corpus` are picked up automatically.
+
+extern crate bf_interp;
+
+use bf_interp::bench::{self, Options};
+use bf_interp::runner::{self, Spec, TestCase};
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// The parsed command line.
+struct Args {
+    options: Options,
+    baseline: Option<String>,
+    save_baseline: Option<String>,
+    tolerance: f64,
+    filters: Vec<String>,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut parsed = Args {
+        options: Options::default(),
+        baseline: None,
+        save_baseline: None,
+        tolerance: 20.0,
+        filters: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| format!("{:} expects a value", flag))
+        };
+        match arg.as_str() {
+            // passed by `cargo bench`
+            "--bench" => (),
+            "--samples" => {
+                parsed.options.samples = value("--samples")?
+                    .parse()
+                    .map_err(|_| "Invalid --samples".to_string())?
+            }
+            "--tolerance" => {
+                parsed.tolerance = value("--tolerance")?
+                    .parse()
+                    .map_err(|_| "Invalid --tolerance".to_string())?
+            }
+            "--baseline" => parsed.baseline = Some(value("--baseline")?),
+            "--save-baseline" => parsed.save_baseline = Some(value("--save-baseline")?),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {:?}", arg)),
+            _ => parsed.filters.push(arg),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Returns the benchmarks of the corpus with their names.
+fn corpus() -> Result<Vec<(String, TestCase)>, String> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = vec![root.join("examples/mandelbrot.bf")];
+    files.extend(runner::discover(&root.join("benches/corpus"))
+                     .map_err(|err| format!("Cannot read the corpus because {:}", err))?);
+    let mut cases = Vec::new();
+    for file in files {
+        let name = file.file_stem()
+            .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let case = match runner::load(&file)? {
+            Some(case) => case,
+            None => without_spec(file)?,
+        };
+        cases.push((name, case));
+    }
+    Ok(cases)
+}
+
+/// Returns the program in `path` with the default spec, the output is not checked.
+fn without_spec(path: PathBuf) -> Result<TestCase, String> {
+    let mut contents = Vec::new();
+    File::open(&path)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .map_err(|err| format!("Cannot read {:} because {:}", path.display(), err))?;
+    Ok(TestCase {
+           source: String::from_utf8_lossy(&contents).into_owned(),
+           path,
+           spec: Spec::default(),
+       })
+}
+
+fn execute(args: &Args) -> Result<(), String> {
+    let mut measurements = Vec::new();
+    for (name, case) in corpus()? {
+        if !args.filters.is_empty() && !args.filters.iter().any(|f| name.contains(f.as_str())) {
+            continue;
+        }
+        eprintln!("measuring {:}", name);
+        measurements.extend(bench::measure(&name, &case, &args.options)?);
+    }
+    print!("{:}", bench::report(&measurements));
+
+    if let Some(ref fname) = args.save_baseline {
+        File::create(fname)
+            .and_then(|mut file| file.write_all(bench::to_baseline(&measurements).as_bytes()))
+            .map_err(|err| format!("Cannot write {:} because {:}", fname, err))?;
+    }
+    if let Some(ref fname) = args.baseline {
+        let mut baseline = String::new();
+        File::open(fname)
+            .and_then(|mut file| file.read_to_string(&mut baseline))
+            .map_err(|err| format!("Cannot read {:} because {:}", fname, err))?;
+        let regressions = bench::compare(&baseline, &measurements, args.tolerance)
+            .map_err(|err| format!("{:}:{:}", fname, err))?;
+        for regression in &regressions {
+            println!("regression: {:}", regression);
+        }
+        if !regressions.is_empty() {
+            return Err(format!("{:} regressions against {:}", regressions.len(), fname));
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|args| execute(&args));
+    if let Err(msg) = result {
+        eprintln!("{:}", msg);
+        process::exit(1);
+    }
+}
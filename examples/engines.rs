@@ -0,0 +1,28 @@
+//! Example that benchmarks the interpreter engines on mandelbrot.bf
+
+extern crate bf_interp;
+
+use bf_interp::{ast, parser, opt, interp};
+use bf_interp::interp::Engine;
+
+use std::fs::File;
+use std::io::Result;
+use std::time::Instant;
+
+fn load_program(fname: String) -> Result<ast::Program> {
+    parser::parse_reader(File::open(fname)?)
+}
+
+fn main() {
+    match load_program("examples/mandelbrot.bf".to_string()) {
+        Ok(ref p) => {
+            let p = opt::optimize(p);
+            for engine in &[Engine::Match, Engine::Threaded, Engine::Bytecode] {
+                let start = Instant::now();
+                interp::run(*engine, &p, &mut std::io::empty(), &mut std::io::sink());
+                println!("{:?}: {:?}", engine, start.elapsed());
+            }
+        }
+        Err(err) => panic!("Cannot read file because {:?}", err),
+    }
+}
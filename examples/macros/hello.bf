@@ -0,0 +1,10 @@
+the letters of hi and the newline
+#include "letters.inc"
+#define NEWLINE 10
+PRINT(72) PRINT(105) PRINT(NEWLINE)
+the alphabet from a first letter
+#macro ALPHABET(first)
+>+*first. <+*25 [>+.<-] print the others
+#end
+ALPHABET(ASCII_A) >CLEAR<
++*NEWLINE.
@@ -0,0 +1,2 @@
+macros: true
+output: Hi\nABCDEFGHIJKLMNOPQRSTUVWXYZ\n
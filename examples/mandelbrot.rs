@@ -5,15 +5,10 @@ extern crate bf_interp;
 use bf_interp::{ast, parser, opt, interp};
 
 use std::fs::File;
-use std::io::{Read, Result};
+use std::io::Result;
 
 fn load_program(fname: String) -> Result<ast::Program> {
-    let mut file = File::open(fname)?;
-    let mut contents: Vec<u8> = Vec::new();
-    file.read_to_end(&mut contents)?;
-    let text = String::from_utf8(contents).unwrap();
-
-    Ok(parser::parse(text))
+    parser::parse_reader(File::open(fname)?)
 }
 
 fn main() {
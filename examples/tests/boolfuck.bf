@@ -0,0 +1,4 @@
+output the bits of H least significant bit first: 0 0 0 1 0 0 1 0
+;;;+;+;;+;+;
+output the bits of i: 1 0 0 1 0 1 1 0
++;+;;+;+;+;;+;
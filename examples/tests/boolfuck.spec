@@ -0,0 +1,3 @@
+dialect: boolfuck
+engines: match
+output: Hi
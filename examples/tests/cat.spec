@@ -0,0 +1,2 @@
+input: one\ttwo\nthree\n
+output: one\ttwo\nthree\n
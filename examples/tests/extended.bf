@@ -0,0 +1,12 @@
+cell 0 = 65
+++++++++[>++++++++<-]>+
+print A and keep it in the storage register
+.$
+shift it right to 32 and print a space
+>!}.
+or it with 65 and print a
+|.
+end the program
+@
+never printed
+.
@@ -0,0 +1,2 @@
+dialect: extended
+output: A a
@@ -0,0 +1,10 @@
+fork the thread
+the parent continues at cell 0 = 0
+the child continues at cell 1 = 1
+Y
+each thread adds its cell to the cell two to the right
+[>>+<<-]
+and adds 65 there with a counter four to the right
+>>>>++++++++[<<++++++++>>-]<<+
+the parent prints A before the child prints B
+.
@@ -0,0 +1,3 @@
+dialect: brainfork
+engines: match
+output: AB
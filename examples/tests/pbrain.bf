@@ -0,0 +1,9 @@
+procedure 0 prints cell 1 and increments it
+(>.+<)
+cell 1 = 65
+>>++++++++[<++++++++>-]<+<
+call procedure 0 three times
+:::
+procedure 1 calls procedure 0 twice
++(-::+)
+:
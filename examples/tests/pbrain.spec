@@ -0,0 +1,2 @@
+dialect: pbrain
+output: ABCDE
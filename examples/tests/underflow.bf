@@ -0,0 +1,3 @@
+# output: !
+# exit: error
++++++[>++++++<-]>+++.<<
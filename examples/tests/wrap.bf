@@ -0,0 +1,6 @@
+# cell-width: 16
+# eof: unchanged
+# opt-level: 0
+# input: A
+# output: \xff\x01AA
+-.>+.>,.,.
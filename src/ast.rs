@@ -1,12 +1,20 @@
 //! The ast-module defines the data-structures for a brainfuck
 //! program.
 
+#[cfg(feature = "serde")]
+use schema::ProgramSchema;
+
 /// A single brainfuck instruction for normal brainfuck code:
 /// `+`, `-`, `<`, `>`, `.`, `,`, `[` and `]`.
 ///
 /// It also supports optimized instructions like
 /// increment by 4 `Inc(4)`.
+///
+/// With the `serde` feature, instructions serialize externally tagged,
+/// e.g. `{"Inc": 4}` or `"Output"`.
+/// Variants are never renamed or removed, so older dumps stay loadable.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Instruction {
     /// Move data-pointer to left by the given offset.
     /// `<` is `MoveLeft(1)`.
@@ -42,11 +50,68 @@ pub enum Instruction {
     /// This implements `]` with the index being the position of the corresponding `[`.
     /// The index avoids looking up the index to jump to at runtime.
     LoopExit(usize),
+    /// Stops the debugger (see `debugger::Debugger`), the engines skip it.
+    /// This implements the debug character `#`,
+    /// which is only parsed with `parser::Options::debug`.
+    Breakpoint,
+    /// Defines the procedure numbered by the memory cell at the current
+    /// data-pointer, whose body follows up to the given index of the
+    /// corresponding `ProcedureEnd`, and jumps behind it.
+    /// This implements pbrain's `(`, which is only parsed with `parser::Options::pbrain`.
+    ProcedureStart(usize),
+    /// Returns from the current procedure call.
+    /// This implements pbrain's `)` with the index being the position of the
+    /// corresponding `(`.
+    ProcedureEnd(usize),
+    /// Calls the procedure numbered by the memory cell at the current data-pointer.
+    /// Calling an undefined procedure panics.
+    /// This implements pbrain's `:`.
+    Call,
+    /// Forks the current thread: the current thread sets the memory cell at
+    /// its data-pointer to `0`, the new thread moves its data-pointer one to
+    /// the right and sets that cell to `1`. Both continue behind the fork.
+    /// This implements Brainfork's `Y`, which is only parsed with
+    /// `parser::Options::fork` and only runs on the scheduler `fork::run_with`.
+    Fork,
+    /// Ends the program.
+    /// This implements Extended Brainfuck's `@`, like all following
+    /// instructions only parsed with `parser::Options::extended`.
+    End,
+    /// Copies the memory cell at the current data-pointer into the storage
+    /// register (see `tape::Tape::storage`). This implements `$`.
+    Store,
+    /// Copies the storage register into the memory cell at the current
+    /// data-pointer. This implements `!`.
+    Load,
+    /// Shifts the memory cell at the current data-pointer one bit to the right.
+    /// This implements `}`.
+    ShiftRight,
+    /// Shifts the memory cell at the current data-pointer one bit to the left,
+    /// bits shifted out of the cell width are lost. This implements `{`.
+    ShiftLeft,
+    /// Inverts the bits of the memory cell at the current data-pointer.
+    /// This implements `~`.
+    Not,
+    /// Sets the memory cell at the current data-pointer to its bitwise xor
+    /// with the storage register. This implements `^`.
+    Xor,
+    /// Sets the memory cell at the current data-pointer to its bitwise and
+    /// with the storage register. This implements `&`.
+    And,
+    /// Sets the memory cell at the current data-pointer to its bitwise or
+    /// with the storage register. This implements `|`.
+    Or,
 }
 
 /// A program is just an array (`vec`) of `Instruction`s.
 /// For loop-instructions, the offset is just an index into this array.
-#[derive(Debug, PartialEq)]
+///
+/// With the `serde` feature, a program serializes as `schema::ProgramSchema`,
+/// i.e. together with the schema version.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde",
+           derive(Serialize, Deserialize),
+           serde(into = "ProgramSchema", try_from = "ProgramSchema"))]
 pub struct Program {
     /// The array of instructions.
     /// Each instruction has a defined index index in this array.
@@ -0,0 +1,296 @@
+//! Benchmarks `bench::measure` of the engines and optimization levels.
+//!
+//! A benchmark is a brainfuck program with a spec like a test of
+//! `runner`: the spec provides the input, the tape configuration and the
+//! expected output, which is checked before measuring. `measure` runs the
+//! program on each engine and optimization level and records the number of
+//! executed instructions and the run times. The instruction counts are
+//! deterministic and catch regressions of the optimizer, the run times catch
+//! regressions of the engines.
+//!
+//! Measurements are saved as a baseline, one line
+//! `NAME ENGINE OPT_LEVEL INSTRUCTIONS NANOSECONDS` per measurement,
+//! and later measurements are compared against it with `compare`.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ast::Program;
+use fork;
+use interp::{self, Engine};
+use profile;
+use runner::{self, TestCase};
+use tape::Tape;
+
+/// What to measure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub engines: Vec<Engine>,
+    /// Optimization levels, `0` disables the optimizer.
+    pub opt_levels: Vec<u32>,
+    /// Number of timed runs per engine and optimization level.
+    pub samples: usize,
+}
+
+impl Default for Options {
+    /// All engines, optimization levels `0` and `1`, 3 samples.
+    fn default() -> Options {
+        Options {
+            engines: runner::ENGINES.to_vec(),
+            opt_levels: vec![0, 1],
+            samples: 3,
+        }
+    }
+}
+
+/// The measurement of one benchmark on one engine and optimization level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub name: String,
+    pub engine: Engine,
+    pub opt_level: u32,
+    /// Number of executed instructions of the (optimized) program.
+    pub instructions: u64,
+    /// The run time of each sample.
+    pub times: Vec<Duration>,
+}
+
+impl Measurement {
+    /// Returns the median run time, zero without samples.
+    pub fn median(&self) -> Duration {
+        let mut times = self.times.clone();
+        times.sort();
+        times.get(times.len() / 2).cloned().unwrap_or_default()
+    }
+
+    /// Returns the executed instructions per second of the median run time.
+    pub fn instructions_per_second(&self) -> f64 {
+        let secs = self.median().as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.instructions as f64 / secs
+        }
+    }
+}
+
+/// Parses `case` and optimizes it for `opt_level`.
+fn compile(case: &TestCase, opt_level: u32) -> Result<Program, String> {
+    case.compile(opt_level)
+        .map_err(|err| format!("{:}:{:}", case.path.display(), err))
+}
+
+/// Measures `case` named `name` on all engines and optimization levels
+/// of `options`. Fails if the program does not parse, if it forks, which
+/// only the Brainfork scheduler runs, or if its output differs from the
+/// expected output of its spec.
+///
+/// # Panics
+///
+/// Panics if the program aborts, e.g. by moving left of the tape.
+pub fn measure(name: &str, case: &TestCase, options: &Options) -> Result<Vec<Measurement>, String> {
+    let spec = &case.spec;
+    let mut measurements = Vec::new();
+    for &opt_level in &options.opt_levels {
+        let program = compile(case, opt_level)?;
+        if fork::forks(&program) {
+            return Err(format!("{:}: programs which fork do not run on the engines", name));
+        }
+        let mut output = Vec::new();
+        let instructions = profile::profile_with(&program,
+                                                 &mut Tape::new(&spec.tape),
+                                                 &mut spec.input.as_slice(),
+                                                 &mut output)
+                .total();
+        match spec.output {
+            Some(ref expected) if *expected != output => {
+                return Err(format!("{:}: output differs at -O{:}\n{:}",
+                                   name,
+                                   opt_level,
+                                   runner::diff(expected, &output)));
+            }
+            _ => (),
+        }
+        for &engine in &options.engines {
+            let times = (0..options.samples)
+                .map(|_| {
+                    let mut tape = Tape::new(&spec.tape);
+                    let start = Instant::now();
+                    interp::run_with(engine,
+                                     &program,
+                                     &mut tape,
+                                     &mut spec.input.as_slice(),
+                                     &mut io::sink());
+                    start.elapsed()
+                })
+                .collect();
+            measurements.push(Measurement {
+                                  name: name.to_string(),
+                                  engine,
+                                  opt_level,
+                                  instructions,
+                                  times,
+                              });
+        }
+    }
+    Ok(measurements)
+}
+
+/// Returns the measurements as a table.
+pub fn report(measurements: &[Measurement]) -> String {
+    let mut report = format!("{:<16} {:<9} {:>3} {:>14} {:>12} {:>10}\n",
+                             "benchmark",
+                             "engine",
+                             "-O",
+                             "instructions",
+                             "median",
+                             "Minstr/s");
+    for m in measurements {
+        report.push_str(&format!("{:<16} {:<9} {:>3} {:>14} {:>12} {:>10.1}\n",
+                                 m.name,
+                                 m.engine.to_string(),
+                                 m.opt_level,
+                                 m.instructions,
+                                 format!("{:.3?}", m.median()),
+                                 m.instructions_per_second() / 1e6));
+    }
+    report
+}
+
+/// Returns the measurements in the baseline format,
+/// see the module documentation.
+pub fn to_baseline(measurements: &[Measurement]) -> String {
+    measurements
+        .iter()
+        .map(|m| {
+                 format!("{:} {:} {:} {:} {:}\n",
+                         m.name,
+                         m.engine,
+                         m.opt_level,
+                         m.instructions,
+                         m.median().as_nanos())
+             })
+        .collect()
+}
+
+/// The key and values of one line of a baseline.
+type BaselineEntry = ((String, Engine, u32), (u64, Duration));
+
+fn parse_baseline_line(line: &str) -> Result<BaselineEntry, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err("expected NAME ENGINE OPT_LEVEL INSTRUCTIONS NANOSECONDS".to_string());
+    }
+    fn number<T: FromStr>(field: &str) -> Result<T, String> {
+        field
+            .parse()
+            .map_err(|_| format!("invalid number {:?}", field))
+    }
+    Ok(((fields[0].to_string(), fields[1].parse()?, number(fields[2])?),
+        (number(fields[3])?, Duration::from_nanos(number(fields[4])?))))
+}
+
+/// A measurement worse than its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Regression {
+    /// More instructions were executed.
+    Instructions {
+        name: String,
+        engine: Engine,
+        opt_level: u32,
+        before: u64,
+        after: u64,
+    },
+    /// The median run time exceeds the tolerance.
+    Time {
+        name: String,
+        engine: Engine,
+        opt_level: u32,
+        before: Duration,
+        after: Duration,
+    },
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Regression::Instructions {
+                ref name,
+                engine,
+                opt_level,
+                before,
+                after,
+            } => {
+                write!(f,
+                       "{:} ({:}, -O{:}): {:} instructions executed, {:} before",
+                       name,
+                       engine,
+                       opt_level,
+                       after,
+                       before)
+            }
+            Regression::Time {
+                ref name,
+                engine,
+                opt_level,
+                before,
+                after,
+            } => {
+                write!(f,
+                       "{:} ({:}, -O{:}): {:.3?}, {:.3?} before",
+                       name,
+                       engine,
+                       opt_level,
+                       after,
+                       before)
+            }
+        }
+    }
+}
+
+/// Compares `measurements` against the `baseline` text.
+/// A run time is a regression if its median exceeds the baseline by more
+/// than `tolerance` percent. Measurements missing in the baseline are
+/// ignored, so new benchmarks can be added.
+pub fn compare(baseline: &str,
+               measurements: &[Measurement],
+               tolerance: f64)
+               -> Result<Vec<Regression>, String> {
+    let mut entries = Vec::new();
+    for (idx, line) in baseline.lines().enumerate() {
+        if !line.trim().is_empty() {
+            let entry = parse_baseline_line(line).map_err(|err| format!("{:}: {:}", idx + 1, err))?;
+            entries.push(entry);
+        }
+    }
+    let mut regressions = Vec::new();
+    for m in measurements {
+        let key = (m.name.clone(), m.engine, m.opt_level);
+        let (instructions, time) = match entries.iter().find(|entry| entry.0 == key) {
+            Some(&(_, values)) => values,
+            None => continue,
+        };
+        if m.instructions > instructions {
+            regressions.push(Regression::Instructions {
+                                 name: m.name.clone(),
+                                 engine: m.engine,
+                                 opt_level: m.opt_level,
+                                 before: instructions,
+                                 after: m.instructions,
+                             });
+        }
+        let limit = time.as_secs_f64() * (1.0 + tolerance / 100.0);
+        if m.median().as_secs_f64() > limit {
+            regressions.push(Regression::Time {
+                                 name: m.name.clone(),
+                                 engine: m.engine,
+                                 opt_level: m.opt_level,
+                                 before: time,
+                                 after: m.median(),
+                             });
+        }
+    }
+    Ok(regressions)
+}
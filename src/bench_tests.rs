@@ -0,0 +1,124 @@
+//! Tests for the benchmarks `bench::measure`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bench::*;
+use interp::Engine;
+use runner::{self, Spec, TestCase};
+
+fn case(source: &str, spec: &str) -> TestCase {
+    TestCase {
+        path: PathBuf::from("bench.bf"),
+        source: source.to_string(),
+        spec: Spec::parse(spec, Path::new("")).unwrap(),
+    }
+}
+
+fn measurement(engine: Engine, instructions: u64, millis: &[u64]) -> Measurement {
+    Measurement {
+        name: "loops".to_string(),
+        engine,
+        opt_level: 1,
+        instructions,
+        times: millis.iter().map(|ms| Duration::from_millis(*ms)).collect(),
+    }
+}
+
+#[test]
+fn measure_engines_and_levels() {
+    let options = Options {
+        samples: 2,
+        ..Options::default()
+    };
+    let measurements = measure("count", &case("+++[>++<-]>.", "output: \\x06"), &options).unwrap();
+    assert_eq!(6, measurements.len());
+    let summary: Vec<(Engine, u32, u64, usize)> = measurements
+        .iter()
+        .map(|m| (m.engine, m.opt_level, m.instructions, m.times.len()))
+        .collect();
+    assert_eq!(vec![(Engine::Match, 0, 24, 2),
+                    (Engine::Threaded, 0, 24, 2),
+                    (Engine::Bytecode, 0, 24, 2),
+                    (Engine::Match, 1, 19, 2),
+                    (Engine::Threaded, 1, 19, 2),
+                    (Engine::Bytecode, 1, 19, 2)],
+               summary);
+    assert!(measurements.iter().all(|m| m.name == "count"));
+}
+
+#[test]
+fn measure_checks_output() {
+    let err = measure("wrong", &case("+.", "output: \\x02"), &Options::default()).unwrap_err();
+    assert!(err.starts_with("wrong: output differs at -O0\n"), "{:}", err);
+    assert!(measure("syntax", &case("+]", ""), &Options::default()).is_err());
+}
+
+#[test]
+fn measure_rejects_fork() {
+    let err = measure("fork", &case("Y", "dialect: brainfork"), &Options::default()).unwrap_err();
+    assert_eq!("fork: programs which fork do not run on the engines", err);
+}
+
+#[test]
+fn median_and_rate() {
+    let m = measurement(Engine::Match, 3_000_000, &[300, 100, 200]);
+    assert_eq!(Duration::from_millis(200), m.median());
+    assert_eq!(15_000_000.0, m.instructions_per_second());
+    assert_eq!(0.0, measurement(Engine::Match, 1, &[]).instructions_per_second());
+}
+
+#[test]
+fn report_table() {
+    let report = report(&[measurement(Engine::Bytecode, 3_000_000, &[200])]);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(2, lines.len());
+    assert_eq!(vec!["loops", "bytecode", "1", "3000000", "200.000ms", "15.0"],
+               lines[1].split_whitespace().collect::<Vec<_>>());
+}
+
+#[test]
+fn compare_with_baseline() {
+    let baseline = to_baseline(&[measurement(Engine::Match, 100, &[100]),
+                                 measurement(Engine::Threaded, 100, &[100])]);
+    assert_eq!("loops match 1 100 100000000\nloops threaded 1 100 100000000\n",
+               baseline);
+
+    let unchanged = [measurement(Engine::Match, 100, &[105]),
+                     measurement(Engine::Threaded, 90, &[50]),
+                     measurement(Engine::Bytecode, 200, &[500])];
+    assert_eq!(Ok(vec![]), compare(&baseline, &unchanged, 10.0));
+
+    let regressed = [measurement(Engine::Match, 101, &[100]),
+                     measurement(Engine::Threaded, 100, &[111])];
+    let regressions = compare(&baseline, &regressed, 10.0).unwrap();
+    assert_eq!(vec!["loops (match, -O1): 101 instructions executed, 100 before",
+                    "loops (threaded, -O1): 111.000ms, 100.000ms before"],
+               regressions
+                   .iter()
+                   .map(|r| r.to_string())
+                   .collect::<Vec<_>>());
+
+    assert_eq!(Err("2: invalid number \"x\"".to_string()),
+               compare("\nloops match x 1 1", &[], 10.0));
+    assert_eq!(Err("1: invalid number \"4294967297\"".to_string()),
+               compare("loops match 4294967297 1 1", &[], 10.0));
+    assert!(compare("loops jit 1 1 1", &[], 10.0).is_err());
+}
+
+#[test]
+fn corpus_outputs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/corpus");
+    let files = runner::discover(&dir).unwrap();
+    assert!(files.len() >= 4);
+    let options = Options {
+        engines: vec![Engine::Bytecode],
+        opt_levels: vec![1],
+        samples: 1,
+    };
+    for file in files {
+        let case = runner::load(&file).unwrap().expect("benchmark has a spec");
+        assert!(measure("corpus", &case, &options).is_ok(), "{:?}", file);
+    }
+}
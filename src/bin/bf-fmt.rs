@@ -0,0 +1,72 @@
+//! `bf-fmt` reformats or minifies brainfuck source.
+//!
+//! Usage: `bf-fmt [--minify] [--indent N] [--width N] [FILE]`
+//!
+//! Reads `FILE` (or stdin) and writes the result to stdout.
+
+extern crate bf_interp;
+
+use bf_interp::format;
+use bf_interp::printer::Options;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "Usage: bf-fmt [--minify] [--indent N] [--width N] [FILE]";
+
+fn exit_with(msg: &str) -> ! {
+    eprintln!("{:}", msg);
+    process::exit(2);
+}
+
+fn number(arg: Option<String>, flag: &str) -> usize {
+    match arg.map(|n| n.parse()) {
+        Some(Ok(n)) => n,
+        _ => exit_with(&format!("{:} expects a number\n{:}", flag, USAGE)),
+    }
+}
+
+fn main() {
+    let mut minify = false;
+    let mut options = Options {
+        width: 80,
+        indent: 2,
+    };
+    let mut fname = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--minify" => minify = true,
+            "--indent" => options.indent = number(args.next(), "--indent"),
+            "--width" => options.width = number(args.next(), "--width"),
+            "-h" | "--help" => {
+                println!("{:}", USAGE);
+                return;
+            }
+            _ if fname.is_none() && !arg.starts_with("--") => fname = Some(arg),
+            _ => exit_with(USAGE),
+        }
+    }
+
+    let mut contents = Vec::new();
+    let read = match fname {
+        Some(ref fname) => File::open(fname).and_then(|mut file| file.read_to_end(&mut contents)),
+        None => io::stdin().read_to_end(&mut contents),
+    };
+    if let Err(err) = read {
+        exit_with(&format!("Cannot read input because {:}", err));
+    }
+    let text = String::from_utf8_lossy(&contents);
+
+    let out = if minify {
+        format::minify(&text) + "\n"
+    } else {
+        format::format(&text, &options)
+    };
+    io::stdout()
+        .write_all(out.as_bytes())
+        .expect("Output error");
+}
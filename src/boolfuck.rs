@@ -0,0 +1,250 @@
+//! Boolfuck, brainfuck on a tape of single bits, see `dialect::Boolfuck`.
+//!
+//! `run_with` executes Boolfuck programs parsed with `dialect::Boolfuck`
+//! (and optionally optimized by `opt`) on a bit-packed `BitTape`: `+`
+//! (`Inc(1)`) flips the current bit, `;` (`Output`) outputs it and `,`
+//! (`Input`) reads a bit. Bits are read and written least significant bit
+//! first: each byte of the input provides eight bits, every eighth output bit
+//! completes a byte. At the end of the program an incomplete byte is padded
+//! with zeros. At the end of input `,` reads `0` bits.
+//!
+//! `translate` translates brainfuck programs to Boolfuck. Each brainfuck
+//! cell becomes nine bits: a bit used while carrying and borrowing, followed
+//! by the eight bits of the cell, least significant bit first.
+
+use std::io::{Read, Write};
+
+use ast::Program;
+use ast::Instruction::*;
+use dialect;
+use parser;
+
+/// The number of bits of a brainfuck cell translated by `translate`.
+pub const CELL_BITS: usize = 9;
+
+/// The Boolfuck code of each brainfuck command translated by `translate`.
+const INC: &str = ">[>]+<[+<]>>>>>>>>>[+]<<<<<<<<<";
+const DEC: &str = ">>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+]<<<<<<<<<";
+const MOVE_LEFT: &str = "<<<<<<<<<";
+const MOVE_RIGHT: &str = ">>>>>>>>>";
+const INPUT: &str = ">,>,>,>,>,>,>,>,<<<<<<<<";
+const OUTPUT: &str = ">;>;>;>;>;>;>;>;<<<<<<<<";
+const LOOP_ENTRY: &str = ">>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+<<<<<<<<[>]+<[+<]";
+const LOOP_EXIT: &str = ">>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>]<[+<]";
+
+/// A tape of bits, packed into words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitTape {
+    words: Vec<u64>,
+    len: usize,
+    dataptr: usize,
+}
+
+impl BitTape {
+    /// Creates a tape of `len` bits, all `0`, with the data-pointer at `0`.
+    pub fn new(len: usize) -> BitTape {
+        BitTape {
+            words: vec![0; len.div_ceil(64)],
+            len,
+            dataptr: 0,
+        }
+    }
+
+    /// Returns the number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the tape has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the data-pointer.
+    pub fn dataptr(&self) -> usize {
+        self.dataptr
+    }
+
+    /// Returns the bit at `idx`.
+    pub fn bit(&self, idx: usize) -> bool {
+        assert!(idx < self.len, "Data-pointer moved right of the tape");
+        self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Returns the bit at the data-pointer.
+    #[inline]
+    pub fn get(&self) -> bool {
+        self.bit(self.dataptr)
+    }
+
+    /// Sets the bit at the data-pointer to `value`.
+    #[inline]
+    pub fn set(&mut self, value: bool) {
+        if self.get() != value {
+            self.flip();
+        }
+    }
+
+    /// Flips the bit at the data-pointer.
+    #[inline]
+    pub fn flip(&mut self) {
+        assert!(self.dataptr < self.len, "Data-pointer moved right of the tape");
+        self.words[self.dataptr / 64] ^= 1 << (self.dataptr % 64);
+    }
+
+    /// Moves the data-pointer left by `offset`.
+    /// Moving left of the tape panics.
+    #[inline]
+    pub fn move_left(&mut self, offset: usize) {
+        self.dataptr = self.dataptr
+            .checked_sub(offset)
+            .expect("Data-pointer moved left of the tape");
+    }
+
+    /// Moves the data-pointer right by `offset`.
+    /// Moving behind the end of the tape panics on the next bit access.
+    #[inline]
+    pub fn move_right(&mut self, offset: usize) {
+        self.dataptr += offset;
+    }
+}
+
+/// Reads bits from the bytes of `inner`, least significant bit first.
+struct BitReader<'a> {
+    inner: &'a mut dyn Read,
+    byte: u8,
+    /// The number of bits of `byte` not read yet.
+    bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn read(&mut self) -> bool {
+        if self.bits == 0 {
+            let mut buf = [0u8; 1];
+            match self.inner.read(&mut buf) {
+                Err(_) => panic!("Cannot read from stdin"),
+                Ok(0) => return false,
+                Ok(_) => {
+                    self.byte = buf[0];
+                    self.bits = 8;
+                }
+            }
+        }
+        let bit = self.byte & 1 != 0;
+        self.byte >>= 1;
+        self.bits -= 1;
+        bit
+    }
+}
+
+/// Writes bits as bytes to `inner`, least significant bit first.
+struct BitWriter<'a> {
+    inner: &'a mut dyn Write,
+    byte: u8,
+    /// The number of bits in `byte`.
+    bits: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    fn write(&mut self, bit: bool) {
+        self.byte |= (bit as u8) << self.bits;
+        self.bits += 1;
+        if self.bits == 8 {
+            self.flush();
+        }
+    }
+
+    /// Writes the incomplete byte padded with zeros.
+    fn flush(&mut self) {
+        if self.bits > 0 {
+            self.inner.write_all(&[self.byte]).expect("Output error");
+            self.byte = 0;
+            self.bits = 0;
+        }
+    }
+}
+
+/// Runs the Boolfuck `program` on a tape of `len` bits.
+/// See `interp::interp` for the meaning of `input` and `output`.
+pub fn run(program: &Program, len: usize, input: &mut dyn Read, output: &mut dyn Write) {
+    run_with(program, &mut BitTape::new(len), input, output);
+}
+
+/// Runs the Boolfuck `program` on `tape`, see the module documentation.
+/// `Inc` and `Dec` flip the current bit as often as given.
+///
+/// # Panics
+///
+/// Panics if the data-pointer leaves the tape and on instructions
+/// Boolfuck does not have, like `Call`.
+pub fn run_with(program: &Program, tape: &mut BitTape, input: &mut dyn Read, output: &mut dyn Write) {
+    let mut reader = BitReader {
+        inner: input,
+        byte: 0,
+        bits: 0,
+    };
+    let mut writer = BitWriter {
+        inner: output,
+        byte: 0,
+        bits: 0,
+    };
+    let mut pc = 0;
+    while pc < program.instructions.len() {
+        match program.instructions[pc] {
+            MoveLeft(offset) => tape.move_left(offset),
+            MoveRight(offset) => tape.move_right(offset),
+            Inc(count) | Dec(count) => {
+                if count % 2 == 1 {
+                    tape.flip();
+                }
+            }
+            Output => writer.write(tape.get()),
+            Input => {
+                let bit = reader.read();
+                tape.set(bit);
+            }
+            LoopEntry(target) => {
+                if !tape.get() {
+                    pc = target;
+                }
+            }
+            LoopExit(target) => {
+                if tape.get() {
+                    pc = target;
+                }
+            }
+            Breakpoint => (),
+            instr => panic!("{:?} at pc={:} is not a Boolfuck instruction", instr, pc),
+        }
+        pc += 1;
+    }
+    writer.flush();
+}
+
+/// Translates the brainfuck `program` to a Boolfuck program computing the
+/// same output from the same input, see the module documentation.
+/// The Boolfuck program needs `CELL_BITS` bits per brainfuck cell and
+/// outputs whole bytes, so its output is never padded.
+///
+/// Fails on instructions beyond brainfuck, like `Call`.
+pub fn translate(program: &Program) -> Result<Program, String> {
+    let mut text = String::new();
+    for (pc, &instr) in program.instructions.iter().enumerate() {
+        let (code, count) = match instr {
+            MoveLeft(offset) => (MOVE_LEFT, offset),
+            MoveRight(offset) => (MOVE_RIGHT, offset),
+            Inc(increment) => (INC, increment as usize),
+            Dec(decrement) => (DEC, decrement as usize),
+            Input => (INPUT, 1),
+            Output => (OUTPUT, 1),
+            LoopEntry(_) => (LOOP_ENTRY, 1),
+            LoopExit(_) => (LOOP_EXIT, 1),
+            Breakpoint => continue,
+            _ => return Err(format!("Cannot translate {:?} at pc={:} to Boolfuck", instr, pc)),
+        };
+        for _ in 0..count {
+            text.push_str(code);
+        }
+    }
+    Ok(parser::parse_dialect(&text, &dialect::Boolfuck).0)
+}
@@ -0,0 +1,111 @@
+//! Tests for Boolfuck, `boolfuck::run_with` and `boolfuck::translate`.
+
+use ast::Program;
+use ast::Instruction::*;
+use boolfuck::*;
+use buffer::*;
+use dialect::Boolfuck;
+use interp;
+use opt;
+use parser;
+
+fn parse_boolfuck(text: &str) -> Program {
+    parser::parse_dialect(text, &Boolfuck).0
+}
+
+fn execute_and_check(program: &Program, input: &str, exp_output: &[u8]) {
+    for p in &[opt::optimize(program), program.clone()] {
+        let mut output = Vec::new();
+        run(p, 1024, &mut IBuffer::from_str(input), &mut output);
+        assert_eq!(exp_output, output.as_slice());
+    }
+}
+
+/// Checks that the Boolfuck translation of `text` outputs like `text`.
+fn translate_and_check(text: &str, input: &str) {
+    let program = parser::parse(text.to_string());
+    let mut expected = Vec::new();
+    interp::interp(&program, &mut IBuffer::from_str(input), &mut expected);
+    let translated = translate(&opt::optimize(&program)).unwrap();
+    let mut output = Vec::new();
+    run(&translated,
+        CELL_BITS * 100,
+        &mut IBuffer::from_str(input),
+        &mut output);
+    assert_eq!(expected, output);
+}
+
+#[test]
+fn bit_tape() {
+    let mut tape = BitTape::new(100);
+    tape.move_right(70);
+    tape.flip();
+    assert!(tape.get());
+    tape.set(true);
+    assert!(tape.bit(70));
+    assert!(!tape.bit(69) && !tape.bit(6));
+    tape.move_left(70);
+    tape.set(true);
+    assert_eq!((0, 100), (tape.dataptr(), tape.len()));
+    assert!(tape.bit(0));
+}
+
+#[test]
+#[should_panic(expected = "Data-pointer moved right of the tape")]
+fn move_right_of_bit_tape() {
+    run(&parse_boolfuck("+[>+]"),
+        100,
+        &mut IBuffer::from_str(""),
+        &mut Vec::new());
+}
+
+#[test]
+fn tokenize_boolfuck() {
+    assert_eq!(vec![Inc(1), Output, Input, LoopEntry(5), MoveRight(1), LoopExit(3)],
+               parse_boolfuck("+;,-.[>]").instructions);
+}
+
+#[test]
+fn output_bits() {
+    // bits are written least significant bit first, the last byte is padded
+    execute_and_check(&parse_boolfuck(";+;;;;;;;+;"), "", &[0xfe, 0x00]);
+    execute_and_check(&parse_boolfuck("+;"), "", &[0x01]);
+    execute_and_check(&parse_boolfuck(""), "", &[]);
+}
+
+#[test]
+fn input_bits() {
+    let echo = ",;,;,;,;,;,;,;,;";
+    execute_and_check(&parse_boolfuck(&echo.repeat(2)), "AB", b"AB");
+    // the end of input reads zeros
+    execute_and_check(&parse_boolfuck(&echo.repeat(2)), "A", b"A\0");
+}
+
+#[test]
+fn translate_commands() {
+    translate_and_check("-.+.", "");
+    translate_and_check("++++++[>++++++++<-]>+.>>+++[<<.>>-]", "");
+    translate_and_check(",[.,]", "Boolfuck");
+}
+
+#[test]
+fn translate_hello_world() {
+    let text = String::from_utf8(::std::fs::read("examples/tests/hello.bf").unwrap()).unwrap();
+    translate_and_check(&text, "");
+}
+
+#[test]
+fn translate_beyond_brainfuck() {
+    let program = Program { instructions: vec![Inc(1), Call] };
+    assert_eq!(Err("Cannot translate Call at pc=1 to Boolfuck".to_string()),
+               translate(&program));
+}
+
+#[test]
+#[should_panic(expected = "Call at pc=0 is not a Boolfuck instruction")]
+fn run_beyond_boolfuck() {
+    run(&Program { instructions: vec![Call] },
+        8,
+        &mut IBuffer::from_str(""),
+        &mut Vec::new());
+}
@@ -0,0 +1,539 @@
+//! Compact bytecode encoding of a brainfuck `Program` and an
+//! interpreter running directly on it.
+//!
+//! Each instruction is encoded as one opcode byte followed by its operand:
+//! * `MoveLeft` and `MoveRight` take the offset as unsigned LEB128 varint,
+//! * `Inc` and `Dec` take the value as a single byte,
+//! * `LoopEntry` and `LoopExit` take the jump distance in bytes as varint.
+//!   The distance is relative to the end of the jump instruction itself,
+//!   forward for `LoopEntry` and backward for `LoopExit`,
+//! * `ProcedureStart` and `ProcedureEnd` take the distance between the
+//!   procedure instructions like the loop instructions,
+//! * `Output`, `Input`, `Breakpoint`, `Call`, `Fork` and the Extended
+//!   Brainfuck instructions have no operand.
+//!
+//! pbrain procedures are defined and called by their byte offsets.
+//!
+//! `Bytecode::write_to` and `Bytecode::read_from` implement the `.bfc` file format:
+//!
+//! | bytes | content                                |
+//! |-------|----------------------------------------|
+//! | 4     | magic `BFC\0`                          |
+//! | 2     | format version (little endian)         |
+//! | 4     | length of the code (little endian)     |
+//! | 4     | Adler-32 checksum of the code (little endian) |
+//! | n     | the code                               |
+
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+use loop_helper::LoopHelper;
+use tape::{Config, Tape};
+
+/// Magic bytes at the beginning of each `.bfc` file.
+pub const MAGIC: [u8; 4] = *b"BFC\0";
+
+/// Version of the `.bfc` format written by `Bytecode::write_to`.
+/// `Bytecode::read_from` also reads all older versions.
+///
+/// History:
+/// * `1`: opcodes `0x01` to `0x08`.
+/// * `2`: `Breakpoint` (`0x09`).
+/// * `3`: pbrain procedures (`0x0a` to `0x0c`).
+/// * `4`: Brainfork's `Fork` (`0x0d`).
+/// * `5`: Extended Brainfuck (`0x0e` to `0x16`).
+pub const VERSION: u16 = 5;
+
+const OP_MOVE_LEFT: u8 = 0x01;
+const OP_MOVE_RIGHT: u8 = 0x02;
+const OP_INC: u8 = 0x03;
+const OP_DEC: u8 = 0x04;
+const OP_OUTPUT: u8 = 0x05;
+const OP_INPUT: u8 = 0x06;
+const OP_LOOP_ENTRY: u8 = 0x07;
+const OP_LOOP_EXIT: u8 = 0x08;
+const OP_BREAKPOINT: u8 = 0x09;
+const OP_PROCEDURE_START: u8 = 0x0a;
+const OP_PROCEDURE_END: u8 = 0x0b;
+const OP_CALL: u8 = 0x0c;
+const OP_FORK: u8 = 0x0d;
+const OP_END: u8 = 0x0e;
+const OP_STORE: u8 = 0x0f;
+const OP_LOAD: u8 = 0x10;
+const OP_SHIFT_RIGHT: u8 = 0x11;
+const OP_SHIFT_LEFT: u8 = 0x12;
+const OP_NOT: u8 = 0x13;
+const OP_XOR: u8 = 0x14;
+const OP_AND: u8 = 0x15;
+const OP_OR: u8 = 0x16;
+
+/// Returns the format version which added the opcode `op`, see `VERSION`.
+fn opcode_version(op: u8) -> u16 {
+    match op {
+        OP_MOVE_LEFT..=OP_LOOP_EXIT => 1,
+        OP_BREAKPOINT => 2,
+        OP_PROCEDURE_START..=OP_CALL => 3,
+        OP_FORK => 4,
+        _ => 5,
+    }
+}
+
+/// A brainfuck program in its bytecode encoding (see `encode`).
+/// A `Bytecode` is always well-formed: it is either encoded from a
+/// `Program` or validated while being read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bytecode {
+    code: Vec<u8>,
+}
+
+/// Returns the number of bytes of `value` as LEB128 varint.
+fn varint_len(mut value: usize) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+pub(crate) fn write_varint(code: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        code.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    code.push(value as u8);
+}
+
+/// Reads a varint at `*pc` and advances `*pc` behind it.
+/// Returns `None` if the code ends within the varint or it overflows `usize`.
+#[inline]
+pub(crate) fn read_varint(code: &[u8], pc: &mut usize) -> Option<usize> {
+    // fast path: most operands fit into one byte
+    if let Some(&byte) = code.get(*pc) {
+        if byte < 0x80 {
+            *pc += 1;
+            return Some(byte as usize);
+        }
+    }
+    let mut value: usize = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *code.get(*pc)?;
+        *pc += 1;
+        if shift >= 64 {
+            return None;
+        }
+        value |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Returns the opcode of `instr` and its operand: the offset, the value
+/// or the loop target index (`0` for instructions without operand).
+/// Used by other binary formats to share the opcodes (see `trace`).
+pub(crate) fn opcode(instr: Instruction) -> (u8, usize) {
+    match instr {
+        MoveLeft(offset) => (OP_MOVE_LEFT, offset),
+        MoveRight(offset) => (OP_MOVE_RIGHT, offset),
+        Inc(val) => (OP_INC, val as usize),
+        Dec(val) => (OP_DEC, val as usize),
+        Output => (OP_OUTPUT, 0),
+        Input => (OP_INPUT, 0),
+        LoopEntry(target) => (OP_LOOP_ENTRY, target),
+        LoopExit(target) => (OP_LOOP_EXIT, target),
+        Breakpoint => (OP_BREAKPOINT, 0),
+        ProcedureStart(target) => (OP_PROCEDURE_START, target),
+        ProcedureEnd(target) => (OP_PROCEDURE_END, target),
+        Call => (OP_CALL, 0),
+        Fork => (OP_FORK, 0),
+        End => (OP_END, 0),
+        Store => (OP_STORE, 0),
+        Load => (OP_LOAD, 0),
+        ShiftRight => (OP_SHIFT_RIGHT, 0),
+        ShiftLeft => (OP_SHIFT_LEFT, 0),
+        Not => (OP_NOT, 0),
+        Xor => (OP_XOR, 0),
+        And => (OP_AND, 0),
+        Or => (OP_OR, 0),
+    }
+}
+
+/// Returns the instruction of `op` and `operand` as returned by `opcode`
+/// or `None` for an unknown opcode or an operand out of range.
+pub(crate) fn from_opcode(op: u8, operand: usize) -> Option<Instruction> {
+    let byte = || u8::try_from(operand).ok();
+    match op {
+        OP_MOVE_LEFT => Some(MoveLeft(operand)),
+        OP_MOVE_RIGHT => Some(MoveRight(operand)),
+        OP_INC => byte().map(Inc),
+        OP_DEC => byte().map(Dec),
+        OP_OUTPUT => Some(Output),
+        OP_INPUT => Some(Input),
+        OP_LOOP_ENTRY => Some(LoopEntry(operand)),
+        OP_LOOP_EXIT => Some(LoopExit(operand)),
+        OP_BREAKPOINT => Some(Breakpoint),
+        OP_PROCEDURE_START => Some(ProcedureStart(operand)),
+        OP_PROCEDURE_END => Some(ProcedureEnd(operand)),
+        OP_CALL => Some(Call),
+        OP_FORK => Some(Fork),
+        OP_END => Some(End),
+        OP_STORE => Some(Store),
+        OP_LOAD => Some(Load),
+        OP_SHIFT_RIGHT => Some(ShiftRight),
+        OP_SHIFT_LEFT => Some(ShiftLeft),
+        OP_NOT => Some(Not),
+        OP_XOR => Some(Xor),
+        OP_AND => Some(And),
+        OP_OR => Some(Or),
+        _ => None,
+    }
+}
+
+/// Computes the Adler-32 checksum of `data`.
+fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Returns the operand of a jump instruction at index `idx` given the
+/// end offsets of all instructions.
+fn jump_distance(instr: Instruction, idx: usize, ends: &[usize]) -> usize {
+    match instr {
+        LoopEntry(target) | ProcedureStart(target) => ends[target] - ends[idx],
+        LoopExit(target) | ProcedureEnd(target) => ends[idx] - ends[target],
+        _ => 0,
+    }
+}
+
+/// Encodes `program` into bytecode.
+///
+/// # Panics
+///
+/// Panics if loop or procedure targets in `program` point behind its end.
+pub fn encode(program: &Program) -> Bytecode {
+    let instructions = &program.instructions;
+    // Jump distances depend on the length of the varints in between.
+    // Start with the shortest encoding and grow jumps until nothing changes.
+    let mut lens: Vec<usize> = instructions
+        .iter()
+        .map(|instr| match *instr {
+                 MoveLeft(offset) | MoveRight(offset) => 1 + varint_len(offset),
+                 Inc(_) | Dec(_) => 2,
+                 Output | Input | Breakpoint | Call | Fork => 1,
+                 End | Store | Load | ShiftRight | ShiftLeft | Not | Xor | And | Or => 1,
+                 LoopEntry(_) | LoopExit(_) | ProcedureStart(_) | ProcedureEnd(_) => 2,
+             })
+        .collect();
+    let mut ends: Vec<usize> = Vec::with_capacity(lens.len());
+    loop {
+        ends.clear();
+        let mut end = 0;
+        for len in &lens {
+            end += *len;
+            ends.push(end);
+        }
+        let mut changed = false;
+        for (idx, instr) in instructions.iter().enumerate() {
+            if let LoopEntry(_) | LoopExit(_) | ProcedureStart(_) | ProcedureEnd(_) = *instr {
+                let len = 1 + varint_len(jump_distance(*instr, idx, &ends));
+                if len > lens[idx] {
+                    lens[idx] = len;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut code = Vec::with_capacity(ends.last().cloned().unwrap_or(0));
+    for (idx, instr) in instructions.iter().enumerate() {
+        let start = code.len();
+        match *instr {
+            MoveLeft(offset) => {
+                code.push(OP_MOVE_LEFT);
+                write_varint(&mut code, offset);
+            }
+            MoveRight(offset) => {
+                code.push(OP_MOVE_RIGHT);
+                write_varint(&mut code, offset);
+            }
+            Inc(val) => code.extend_from_slice(&[OP_INC, val]),
+            Dec(val) => code.extend_from_slice(&[OP_DEC, val]),
+            Output => code.push(OP_OUTPUT),
+            Input => code.push(OP_INPUT),
+            Breakpoint => code.push(OP_BREAKPOINT),
+            Call => code.push(OP_CALL),
+            Fork => code.push(OP_FORK),
+            End | Store | Load | ShiftRight | ShiftLeft | Not | Xor | And | Or => code.push(opcode(*instr).0),
+            LoopEntry(_) => {
+                code.push(OP_LOOP_ENTRY);
+                write_varint(&mut code, jump_distance(*instr, idx, &ends));
+            }
+            LoopExit(_) => {
+                code.push(OP_LOOP_EXIT);
+                write_varint(&mut code, jump_distance(*instr, idx, &ends));
+            }
+            ProcedureStart(_) => {
+                code.push(OP_PROCEDURE_START);
+                write_varint(&mut code, jump_distance(*instr, idx, &ends));
+            }
+            ProcedureEnd(_) => {
+                code.push(OP_PROCEDURE_END);
+                write_varint(&mut code, jump_distance(*instr, idx, &ends));
+            }
+        }
+        debug_assert_eq!(lens[idx], code.len() - start);
+    }
+    Bytecode { code }
+}
+
+/// Walks over `code` and calls `visit` with the offset and the decoded
+/// instruction of each instruction.
+/// Jump instructions are passed with the target *offset* (the offset
+/// behind the corresponding loop or procedure instruction) instead of an index.
+/// Returns `None` if `code` is malformed.
+fn walk<F: FnMut(usize, Instruction)>(code: &[u8], mut visit: F) -> Option<()> {
+    let mut pc = 0;
+    while pc < code.len() {
+        let start = pc;
+        let op = code[pc];
+        pc += 1;
+        let instr = match op {
+            OP_MOVE_LEFT => MoveLeft(read_varint(code, &mut pc)?),
+            OP_MOVE_RIGHT => MoveRight(read_varint(code, &mut pc)?),
+            OP_INC | OP_DEC => {
+                let val = *code.get(pc)?;
+                pc += 1;
+                if op == OP_INC { Inc(val) } else { Dec(val) }
+            }
+            OP_OUTPUT => Output,
+            OP_INPUT => Input,
+            OP_BREAKPOINT => Breakpoint,
+            OP_CALL => Call,
+            OP_FORK => Fork,
+            OP_END | OP_STORE | OP_LOAD | OP_SHIFT_RIGHT | OP_SHIFT_LEFT | OP_NOT | OP_XOR | OP_AND |
+            OP_OR => from_opcode(op, 0)?,
+            OP_LOOP_ENTRY => {
+                let distance = read_varint(code, &mut pc)?;
+                LoopEntry(pc.checked_add(distance)?)
+            }
+            OP_LOOP_EXIT => {
+                let distance = read_varint(code, &mut pc)?;
+                LoopExit(pc.checked_sub(distance)?)
+            }
+            OP_PROCEDURE_START => {
+                let distance = read_varint(code, &mut pc)?;
+                ProcedureStart(pc.checked_add(distance)?)
+            }
+            OP_PROCEDURE_END => {
+                let distance = read_varint(code, &mut pc)?;
+                ProcedureEnd(pc.checked_sub(distance)?)
+            }
+            _ => return None,
+        };
+        visit(start, instr);
+    }
+    Some(())
+}
+
+/// Checks that `code` consists of complete instructions and that all
+/// jumps target the end of their corresponding loop or procedure instruction.
+fn validate(code: &[u8]) -> bool {
+    let mut instructions = Vec::new();
+    if walk(code, |start, instr| instructions.push((start, instr))).is_none() {
+        return false;
+    }
+    let end = |idx: usize| {
+        instructions
+            .get(idx + 1)
+            .map_or(code.len(), |&(start, _)| start)
+    };
+    let mut loop_stack = Vec::new();
+    for (idx, &(_, instr)) in instructions.iter().enumerate() {
+        let expected = match instr {
+            LoopEntry(_) | ProcedureStart(_) => {
+                loop_stack.push(idx);
+                continue;
+            }
+            LoopExit(target) => (target, LoopEntry(end(idx))),
+            ProcedureEnd(target) => (target, ProcedureStart(end(idx))),
+            _ => continue,
+        };
+        match loop_stack.pop() {
+            Some(entry) => {
+                if expected != (end(entry), instructions[entry].1) {
+                    return false;
+                }
+            }
+            None => return false,
+        }
+    }
+    loop_stack.is_empty()
+}
+
+impl Bytecode {
+    /// Returns the encoded bytes.
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    /// Decodes the bytecode back into a `Program`.
+    pub fn to_program(&self) -> Program {
+        let mut loop_helper = LoopHelper::new();
+        let mut instructions = Vec::new();
+        walk(&self.code, |_, instr| {
+            let idx = instructions.len();
+            instructions.push(match instr {
+                                  LoopEntry(_) => loop_helper.loop_entry(idx),
+                                  LoopExit(_) => loop_helper.loop_exit(idx),
+                                  ProcedureStart(_) => loop_helper.procedure_start(idx),
+                                  ProcedureEnd(_) => loop_helper.procedure_end(idx),
+                                  instr => instr,
+                              });
+        })
+                .expect("Bytecode is well-formed");
+        loop_helper.relocate(&mut instructions);
+        Program { instructions }
+    }
+
+    /// Interprets the bytecode directly.
+    /// Behaves exactly like `interp::interp` on the encoded `Program`.
+    pub fn run(&self, input: &mut dyn Read, output: &mut dyn Write) {
+        self.run_with(&mut Tape::new(&Config::default()), input, output);
+    }
+
+    /// Interprets the bytecode directly on the given `tape`
+    /// (see `interp::interp_with`).
+    pub fn run_with(&self, tape: &mut Tape, input: &mut dyn Read, output: &mut dyn Write) {
+        let code = &self.code[..];
+        let mut pc: usize = 0;
+
+        // `self.code` is well-formed, so operands are always present
+        while pc < code.len() {
+            let op = code[pc];
+            pc += 1;
+            match op {
+                OP_MOVE_LEFT => tape.move_left(read_varint(code, &mut pc).unwrap()),
+                OP_MOVE_RIGHT => tape.move_right(read_varint(code, &mut pc).unwrap()),
+                OP_INC => {
+                    tape.inc(code[pc]);
+                    pc += 1;
+                }
+                OP_DEC => {
+                    tape.dec(code[pc]);
+                    pc += 1;
+                }
+                OP_OUTPUT => tape.write(output),
+                OP_INPUT => tape.read(input),
+                OP_BREAKPOINT => (),
+                OP_LOOP_ENTRY => {
+                    let distance = read_varint(code, &mut pc).unwrap();
+                    if tape.is_zero() {
+                        pc += distance;
+                    }
+                }
+                OP_LOOP_EXIT => {
+                    let distance = read_varint(code, &mut pc).unwrap();
+                    if !tape.is_zero() {
+                        pc -= distance;
+                    }
+                }
+                OP_PROCEDURE_START => {
+                    let distance = read_varint(code, &mut pc).unwrap();
+                    tape.define(pc);
+                    pc += distance;
+                }
+                OP_PROCEDURE_END => {
+                    read_varint(code, &mut pc).unwrap();
+                    pc = tape.ret();
+                }
+                OP_CALL => pc = tape.call(pc),
+                OP_END => pc = code.len(),
+                OP_STORE => tape.store(),
+                OP_LOAD => tape.load(),
+                OP_SHIFT_RIGHT => tape.shift_right(),
+                OP_SHIFT_LEFT => tape.shift_left(),
+                OP_NOT => tape.not(),
+                OP_XOR => tape.xor(),
+                OP_AND => tape.and(),
+                OP_OR => tape.or(),
+                OP_FORK => {
+                    panic!("Fork at pc={:} needs the brainfork scheduler `fork::run_with`",
+                           pc - 1)
+                }
+                op => panic!("Unknown opcode {:?} at pc={:}", op, pc - 1),
+            }
+        }
+    }
+
+    /// Writes the bytecode in the `.bfc` file format to `output`.
+    pub fn write_to(&self, output: &mut dyn Write) -> Result<()> {
+        let len = self.code.len() as u32;
+        output.write_all(&MAGIC)?;
+        output.write_all(&VERSION.to_le_bytes())?;
+        output.write_all(&len.to_le_bytes())?;
+        output.write_all(&adler32(&self.code).to_le_bytes())?;
+        output.write_all(&self.code)
+    }
+
+    /// Reads bytecode in the `.bfc` file format from `input`.
+    ///
+    /// Fails with `ErrorKind::InvalidData` if the magic bytes, the version
+    /// or the checksum do not match, if the code is malformed or if it
+    /// contains opcodes newer than its version.
+    pub fn read_from(input: &mut dyn Read) -> Result<Bytecode> {
+        fn invalid(msg: &str) -> Error {
+            Error::new(ErrorKind::InvalidData, msg)
+        }
+
+        let mut header = [0u8; 14];
+        input.read_exact(&mut header)?;
+        if header[0..4] != MAGIC {
+            return Err(invalid("not a bfc file"));
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version == 0 || version > VERSION {
+            return Err(invalid(&format!("unsupported bfc version {:}", version)));
+        }
+        let len = u32::from_le_bytes([header[6], header[7], header[8], header[9]]);
+        let checksum = u32::from_le_bytes([header[10], header[11], header[12], header[13]]);
+        // the length is untrusted, so it only limits the read
+        let mut code = Vec::new();
+        input.take(len as u64).read_to_end(&mut code)?;
+        if code.len() != len as usize {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated bfc code"));
+        }
+        if adler32(&code) != checksum {
+            return Err(invalid("bfc checksum mismatch"));
+        }
+        if !validate(&code) {
+            return Err(invalid("malformed bfc code"));
+        }
+        let mut newer = None;
+        walk(&code, |start, instr| {
+            let op = opcode(instr).0;
+            if newer.is_none() && opcode_version(op) > version {
+                newer = Some((op, start));
+            }
+        });
+        if let Some((op, start)) = newer {
+            return Err(invalid(&format!("opcode {:#04x} at offset {:} needs bfc version {:}",
+                                        op,
+                                        start,
+                                        opcode_version(op))));
+        }
+        Ok(Bytecode { code })
+    }
+}
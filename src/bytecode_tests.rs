@@ -0,0 +1,221 @@
+//! Tests for the bytecode encoding `bytecode::encode` and the `.bfc` format.
+
+use std::io::ErrorKind;
+
+use ast::Program;
+use ast::Instruction::*;
+use buffer::*;
+use bytecode::{encode, Bytecode};
+use parser::parse;
+
+fn execute_and_check(program: &Program, input: &str, exp_output: &[u8]) {
+    let mut input = IBuffer::from_str(input);
+    let mut output = Vec::new();
+    encode(program).run(&mut input, &mut output);
+    assert_eq!(exp_output, output.as_slice());
+}
+
+/// A loop whose body needs more than 127 bytes, so both jumps need 2-byte varints.
+fn long_loop() -> Program {
+    let mut instructions = vec![Inc(3), LoopEntry(0)];
+    for _ in 0..100 {
+        instructions.push(MoveRight(1));
+        instructions.push(MoveLeft(1));
+    }
+    instructions.push(Dec(1));
+    instructions.push(LoopExit(1));
+    instructions.push(Output);
+    let exit = instructions.len() - 2;
+    instructions[1] = LoopEntry(exit);
+    Program { instructions }
+}
+
+#[test]
+fn encode_single_byte_operands() {
+    let p = Program { instructions: vec![Inc(3), MoveRight(2), Dec(1), MoveLeft(2), Output, Input] };
+    assert_eq!(&[0x03, 3, 0x02, 2, 0x04, 1, 0x01, 2, 0x05, 0x06],
+               encode(&p).code());
+}
+
+#[test]
+fn encode_varint_offset() {
+    let p = Program { instructions: vec![MoveRight(300)] };
+    assert_eq!(&[0x02, 0xac, 0x02], encode(&p).code());
+}
+
+#[test]
+fn encode_loop_distances() {
+    let p = parse("[-]".to_string());
+    assert_eq!(&[0x07, 4, 0x04, 1, 0x08, 4], encode(&p).code());
+}
+
+#[test]
+fn round_trip_program() {
+    let p = parse(",+>+[-]<.[>[<]]".to_string());
+    assert_eq!(p, encode(&p).to_program());
+}
+
+#[test]
+fn round_trip_breakpoint() {
+    let p = Program { instructions: vec![Inc(1), Breakpoint, LoopEntry(4), Dec(1), LoopExit(2)] };
+    assert_eq!(&[0x03, 1, 0x09, 0x07, 4, 0x04, 1, 0x08, 4], encode(&p).code());
+    assert_eq!(p, encode(&p).to_program());
+}
+
+#[test]
+fn round_trip_procedures() {
+    let p = Program {
+        instructions: vec![ProcedureStart(4),
+                           LoopEntry(3),
+                           Call,
+                           LoopExit(1),
+                           ProcedureEnd(0),
+                           Call],
+    };
+    assert_eq!(&[0x0a, 7, 0x07, 3, 0x0c, 0x08, 3, 0x0b, 7, 0x0c], encode(&p).code());
+    assert_eq!(p, encode(&p).to_program());
+}
+
+#[test]
+fn round_trip_fork() {
+    let p = Program { instructions: vec![Fork, Output] };
+    assert_eq!(&[0x0d, 0x05], encode(&p).code());
+    assert_eq!(p, encode(&p).to_program());
+}
+
+#[test]
+fn round_trip_extended() {
+    let p = Program { instructions: vec![Store, Load, ShiftRight, ShiftLeft, Not, Xor, And, Or, End] };
+    assert_eq!(&[0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x0e], encode(&p).code());
+    assert_eq!(p, encode(&p).to_program());
+}
+
+#[test]
+fn round_trip_long_loop() {
+    let p = long_loop();
+    assert_eq!(p, encode(&p).to_program());
+}
+
+#[test]
+fn run_long_loop() {
+    execute_and_check(&long_loop(), "", &[0]);
+}
+
+#[test]
+fn run_input_output() {
+    execute_and_check(&parse(",+.,.".to_string()), "a1", b"b1");
+}
+
+#[test]
+fn run_loop_move_value() {
+    execute_and_check(&parse("++++[->+++<]>.".to_string()), "", &[12]);
+}
+
+#[test]
+fn smaller_than_instructions() {
+    let p = long_loop();
+    assert!(encode(&p).code().len() < p.instructions.len() * 4);
+}
+
+#[test]
+fn bfc_round_trip() {
+    let bytecode = encode(&long_loop());
+    let mut file = Vec::new();
+    bytecode.write_to(&mut file).expect("write must be ok");
+    assert_eq!(b"BFC\0\x05\x00", &file[0..6]);
+    let read = Bytecode::read_from(&mut file.as_slice()).expect("read must be ok");
+    assert_eq!(bytecode, read);
+}
+
+fn bfc_file(program: &str) -> Vec<u8> {
+    let mut file = Vec::new();
+    encode(&parse(program.to_string()))
+        .write_to(&mut file)
+        .expect("write must be ok");
+    file
+}
+
+fn read_error(file: &[u8]) -> ErrorKind {
+    Bytecode::read_from(&mut &file[..])
+        .expect_err("read must fail")
+        .kind()
+}
+
+#[test]
+fn bfc_read_version_1() {
+    let mut file = bfc_file("+[-].");
+    file[4] = 1;
+    let read = Bytecode::read_from(&mut file.as_slice()).expect("read must be ok");
+    assert_eq!(parse("+[-].".to_string()), read.to_program());
+}
+
+#[test]
+fn bfc_opcode_newer_than_version() {
+    let mut file = Vec::new();
+    encode(&Program { instructions: vec![Inc(1), Fork, Output] })
+        .write_to(&mut file)
+        .expect("write must be ok");
+    file[4] = 4;
+    assert!(Bytecode::read_from(&mut file.as_slice()).is_ok());
+    file[4] = 3;
+    let err = Bytecode::read_from(&mut file.as_slice()).expect_err("read must fail");
+    assert_eq!(ErrorKind::InvalidData, err.kind());
+    assert_eq!("opcode 0x0d at offset 2 needs bfc version 4", err.to_string());
+}
+
+#[test]
+fn bfc_bad_magic() {
+    let mut file = bfc_file("+.");
+    file[0] = b'X';
+    assert_eq!(ErrorKind::InvalidData, read_error(&file));
+}
+
+#[test]
+fn bfc_unsupported_version() {
+    let mut file = bfc_file("+.");
+    file[4] = 6;
+    assert_eq!(ErrorKind::InvalidData, read_error(&file));
+}
+
+#[test]
+fn bfc_checksum_mismatch() {
+    let mut file = bfc_file("+++.");
+    let last = file.len() - 1;
+    file[last] = 0x06;
+    assert_eq!(ErrorKind::InvalidData, read_error(&file));
+}
+
+#[test]
+fn bfc_truncated() {
+    let file = bfc_file("+++.");
+    assert_eq!(ErrorKind::UnexpectedEof, read_error(&file[..file.len() - 1]));
+}
+
+#[test]
+fn bfc_huge_length() {
+    // the length of the header must not be allocated before reading the code
+    let file = b"BFC\0\x05\x00\xff\xff\xff\xff\x00\x00\x00\x00+.";
+    assert_eq!(ErrorKind::UnexpectedEof, read_error(file));
+}
+
+#[test]
+fn bfc_mismatched_procedure() {
+    // a valid checksum over a loop closed by `)`
+    let mut file = Vec::new();
+    let code = [0x07u8, 2, 0x0b, 2];
+    file.extend_from_slice(b"BFC\0\x03\x00\x04\x00\x00\x00");
+    file.extend_from_slice(&[0x17, 0x00, 0x3e, 0x00]);
+    file.extend_from_slice(&code);
+    assert_eq!(ErrorKind::InvalidData, read_error(&file));
+}
+
+#[test]
+fn bfc_malformed_jump() {
+    // a valid checksum over an unbalanced loop
+    let mut file = Vec::new();
+    let code = [0x07u8, 0];
+    file.extend_from_slice(b"BFC\0\x01\x00\x02\x00\x00\x00");
+    file.extend_from_slice(&[0x08, 0x00, 0x10, 0x00]);
+    file.extend_from_slice(&code);
+    assert_eq!(ErrorKind::InvalidData, read_error(&file));
+}
@@ -0,0 +1,250 @@
+//! Code coverage `coverage::record` with lcov reports.
+//!
+//! `record` runs a program like `interp::interp_with` and returns the
+//! `FileCoverage` of its source: how often each source line with commands
+//! was executed and, for each loop, how often its body ran and how often
+//! it was skipped entirely. `Coverage` merges the results of several runs
+//! and files and converts from and to the lcov tracefile format, where
+//! * `DA:<line>,<hits>` counts the executions of the line's most executed command,
+//! * `BRDA:<line>,<column>,0,<count>` counts the iterations of the loop at
+//!   `line:column` and `BRDA:<line>,<column>,1,<count>` how often it was
+//!   skipped, `-` if the loop was never reached.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as FmtWrite;
+use std::io::{Read, Write};
+
+use ast::Program;
+use ast::Instruction::*;
+use interp;
+use span::SourceMap;
+use tape::Tape;
+
+/// Coverage of one loop.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LoopCoverage {
+    /// Whether the loop was reached at all.
+    pub reached: bool,
+    /// How often the loop body ran.
+    pub iterations: u64,
+    /// How often the loop was skipped, because the cell was zero at `[`.
+    pub skipped: u64,
+}
+
+impl LoopCoverage {
+    fn merge(&mut self, other: &LoopCoverage) {
+        self.reached |= other.reached;
+        self.iterations += other.iterations;
+        self.skipped += other.skipped;
+    }
+}
+
+/// Coverage of one source file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileCoverage {
+    /// The hits of each line with commands.
+    pub lines: BTreeMap<usize, u64>,
+    /// The coverage of each loop by `line` and `column` of its `[`.
+    pub loops: BTreeMap<(usize, usize), LoopCoverage>,
+}
+
+impl FileCoverage {
+    /// Adds the counts of `other`.
+    pub fn merge(&mut self, other: &FileCoverage) {
+        for (line, hits) in &other.lines {
+            *self.lines.entry(*line).or_default() += *hits;
+        }
+        for (position, other_loop) in &other.loops {
+            self.loops
+                .entry(*position)
+                .or_default()
+                .merge(other_loop);
+        }
+    }
+
+    /// Returns the number of lines with commands and how many of them were hit.
+    pub fn line_summary(&self) -> (usize, usize) {
+        (self.lines.len(), self.lines.values().filter(|hits| **hits > 0).count())
+    }
+
+    /// Returns the number of loop branches (body and skip of each loop)
+    /// and how many of them were taken.
+    pub fn loop_summary(&self) -> (usize, usize) {
+        let taken = self.loops
+            .values()
+            .map(|l| (l.iterations > 0) as usize + (l.skipped > 0) as usize)
+            .sum();
+        (2 * self.loops.len(), taken)
+    }
+}
+
+/// Runs `program` like `interp::interp_with` on `tape` and returns the
+/// coverage of its source. `source_map` maps the instructions of `program`
+/// to the source, so the program may be optimized. `commands` is the source
+/// map of the unoptimized program, whose lines with commands are covered,
+/// i.e. `source_map` again for an unoptimized program.
+///
+/// # Panics
+///
+/// Panics like `interp::interp_with`, the coverage is lost then.
+pub fn record(program: &Program,
+              source_map: &SourceMap,
+              commands: &SourceMap,
+              tape: &mut Tape,
+              input: &mut dyn Read,
+              output: &mut dyn Write)
+              -> FileCoverage {
+    let len = program.instructions.len();
+    let mut counts = vec![0u64; len];
+    let mut skipped = vec![0u64; len];
+    let mut pc: usize = 0;
+
+    while pc < len {
+        counts[pc] += 1;
+        let next = interp::step(program, pc, tape, input, output);
+        if let LoopEntry(_) = program.instructions[pc] {
+            if next != pc + 1 {
+                skipped[pc] += 1;
+            }
+        }
+        pc = next;
+    }
+
+    let command_lines: BTreeSet<usize> =
+        commands.spans().iter().map(|span| span.start.line).collect();
+    let mut coverage = FileCoverage::default();
+    for (pc, instr) in program.instructions.iter().enumerate() {
+        let span = match source_map.span(pc) {
+            Some(span) => span,
+            None => continue,
+        };
+        // a merged instruction may span lines, count it for each of its lines with commands
+        for line in command_lines.range(span.start.line..span.end.line + 1) {
+            let hits = coverage.lines.entry(*line).or_insert(0);
+            *hits = (*hits).max(counts[pc]);
+        }
+        if let LoopEntry(exit) = *instr {
+            coverage.loops.insert((span.start.line, span.start.column),
+                                  LoopCoverage {
+                                      reached: counts[pc] > 0,
+                                      // each iteration ends at the loop-exit
+                                      iterations: counts[exit],
+                                      skipped: skipped[pc],
+                                  });
+        }
+    }
+    coverage
+}
+
+/// Coverage of several source files, merged over several runs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Coverage {
+    /// The coverage of each file by its path.
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+impl Coverage {
+    /// Adds the coverage of a run of the program in `path`.
+    pub fn add(&mut self, path: &str, coverage: &FileCoverage) {
+        self.files
+            .entry(path.to_string())
+            .or_default()
+            .merge(coverage);
+    }
+
+    /// Adds the counts of all files of `other`.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (path, coverage) in &other.files {
+            self.add(path, coverage);
+        }
+    }
+
+    /// Returns the report in the lcov tracefile format.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, file) in &self.files {
+            let _ = writeln!(lcov, "TN:\nSF:{:}", path);
+            for (&(line, column), l) in &file.loops {
+                for (branch, count) in [l.iterations, l.skipped].iter().enumerate() {
+                    if l.reached {
+                        let _ = writeln!(lcov, "BRDA:{:},{:},{:},{:}", line, column, branch, count);
+                    } else {
+                        let _ = writeln!(lcov, "BRDA:{:},{:},{:},-", line, column, branch);
+                    }
+                }
+            }
+            let (branches, taken) = file.loop_summary();
+            let _ = writeln!(lcov, "BRF:{:}\nBRH:{:}", branches, taken);
+            for (line, hits) in &file.lines {
+                let _ = writeln!(lcov, "DA:{:},{:}", line, hits);
+            }
+            let (lines, hit) = file.line_summary();
+            let _ = writeln!(lcov, "LF:{:}\nLH:{:}\nend_of_record", lines, hit);
+        }
+        lcov
+    }
+
+    /// Parses a report in the lcov tracefile format as written by `to_lcov`.
+    /// Records other than `SF`, `DA`, `BRDA` and `end_of_record` are ignored.
+    pub fn from_lcov(text: &str) -> Result<Coverage, String> {
+        fn numbers(line_no: usize, values: &str, count: usize) -> Result<Vec<&str>, String> {
+            let values: Vec<&str> = values.split(',').collect();
+            if values.len() < count {
+                return Err(format!("{:}: expected {:} values", line_no, count));
+            }
+            Ok(values)
+        }
+        fn number(line_no: usize, value: &str) -> Result<u64, String> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{:}: invalid number {:?}", line_no, value))
+        }
+
+        let mut coverage = Coverage::default();
+        let mut current: Option<(String, FileCoverage)> = None;
+        for (idx, line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.trim();
+            let (record, values) = match line.find(':') {
+                Some(colon) => (&line[..colon], &line[colon + 1..]),
+                None => (line, ""),
+            };
+            match (record, current.as_mut()) {
+                ("SF", None) => current = Some((values.to_string(), FileCoverage::default())),
+                ("SF", Some(_)) => return Err(format!("{:}: missing end_of_record", line_no)),
+                ("end_of_record", Some(_)) => {
+                    let (path, file) = current.take().expect("current file");
+                    coverage.add(&path, &file);
+                }
+                ("DA", Some(&mut (_, ref mut file))) => {
+                    let values = numbers(line_no, values, 2)?;
+                    let line = number(line_no, values[0])? as usize;
+                    *file.lines.entry(line).or_default() += number(line_no, values[1])?;
+                }
+                ("BRDA", Some(&mut (_, ref mut file))) => {
+                    let values = numbers(line_no, values, 4)?;
+                    let position = (number(line_no, values[0])? as usize,
+                                    number(line_no, values[1])? as usize);
+                    let l = file.loops.entry(position).or_default();
+                    if values[3].trim() != "-" {
+                        let count = number(line_no, values[3])?;
+                        l.reached = true;
+                        match values[2].trim() {
+                            "0" => l.iterations += count,
+                            _ => l.skipped += count,
+                        }
+                    }
+                }
+                ("DA", None) | ("BRDA", None) | ("end_of_record", None) => {
+                    return Err(format!("{:}: {:} outside of a file record", line_no, record));
+                }
+                _ => (),
+            }
+        }
+        match current {
+            Some(_) => Err("missing end_of_record at the end".to_string()),
+            None => Ok(coverage),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Tests for the coverage `coverage::record`.
+
+use std::collections::BTreeMap;
+
+use buffer::*;
+use coverage::{record, Coverage, FileCoverage, LoopCoverage};
+use opt::optimize_with;
+use parser::{parse_with, Options};
+use tape::{Config, Tape};
+
+fn coverage(text: &str, input: &str, optimized: bool) -> FileCoverage {
+    let (program, commands) = parse_with(text, &Options::default());
+    let (program, source_map) = if optimized {
+        optimize_with(&program, &commands)
+    } else {
+        (program, commands.clone())
+    };
+    record(&program,
+           &source_map,
+           &commands,
+           &mut Tape::new(&Config::default()),
+           &mut IBuffer::from_str(input),
+           &mut Vec::new())
+}
+
+fn lines(hits: &[(usize, u64)]) -> BTreeMap<usize, u64> {
+    hits.iter().cloned().collect()
+}
+
+#[test]
+fn record_lines_and_loops() {
+    let c = coverage(",[\n.,]\ncomment\n+[-]\n[>]", "", false);
+    assert_eq!(lines(&[(1, 1), (2, 0), (4, 1), (5, 1)]), c.lines);
+    assert_eq!(Some(&LoopCoverage {
+                   reached: true,
+                   iterations: 0,
+                   skipped: 1,
+               }),
+               c.loops.get(&(1, 2)));
+    assert_eq!(Some(&LoopCoverage {
+                   reached: true,
+                   iterations: 1,
+                   skipped: 0,
+               }),
+               c.loops.get(&(4, 2)));
+    assert_eq!((4, 3), c.line_summary());
+    assert_eq!((6, 3), c.loop_summary());
+}
+
+#[test]
+fn record_unreached_loop() {
+    let c = coverage("[\n[-]\n]", "", false);
+    assert_eq!(Some(&LoopCoverage::default()), c.loops.get(&(2, 1)));
+    assert_eq!(lines(&[(1, 1), (2, 0), (3, 0)]), c.lines);
+}
+
+#[test]
+fn record_optimized_program() {
+    let text = "+++\n[->++\n+<]";
+    assert_eq!(coverage(text, "", false).loops, coverage(text, "", true).loops);
+    assert_eq!(lines(&[(1, 1), (2, 3), (3, 3)]),
+               coverage(text, "", true).lines);
+}
+
+#[test]
+fn record_run_merged_across_lines() {
+    let text = "+\n+\ncomment\n+\n.";
+    let optimized = coverage(text, "", true);
+    assert_eq!(lines(&[(1, 1), (2, 1), (4, 1), (5, 1)]), optimized.lines);
+    assert_eq!(coverage(text, "", false).lines, optimized.lines);
+}
+
+#[test]
+fn merge_runs() {
+    let text = ",[.,]";
+    let mut report = Coverage::default();
+    report.add("a.bf", &coverage(text, "", false));
+    report.add("a.bf", &coverage(text, "xy", false));
+    let file = &report.files["a.bf"];
+    assert_eq!(lines(&[(1, 3)]), file.lines);
+    assert_eq!(Some(&LoopCoverage {
+                   reached: true,
+                   iterations: 2,
+                   skipped: 1,
+               }),
+               file.loops.get(&(1, 2)));
+}
+
+#[test]
+fn lcov_report() {
+    let mut report = Coverage::default();
+    report.add("a.bf", &coverage("+[-]\n[\n.]", "", false));
+    assert_eq!("\
+TN:
+SF:a.bf
+BRDA:1,2,0,1
+BRDA:1,2,1,0
+BRDA:2,1,0,0
+BRDA:2,1,1,1
+BRF:4
+BRH:2
+DA:1,1
+DA:2,1
+DA:3,0
+LF:3
+LH:2
+end_of_record
+",
+               report.to_lcov());
+}
+
+#[test]
+fn lcov_round_trip() {
+    let mut report = Coverage::default();
+    report.add("a.bf", &coverage(",[.,]\n[[-]]", "ab", false));
+    report.add("b.bf", &coverage("+\n[-]", "", true));
+    assert_eq!(Ok(report.clone()), Coverage::from_lcov(&report.to_lcov()));
+}
+
+#[test]
+fn lcov_merge_with_report() {
+    let mut report = Coverage::default();
+    report.add("a.bf", &coverage(",[.,]", "a", false));
+    let mut merged = Coverage::from_lcov(&report.to_lcov()).expect("lcov must parse");
+    merged.merge(&report);
+    assert_eq!(lines(&[(1, 2)]), merged.files["a.bf"].lines);
+}
+
+#[test]
+fn lcov_reject_malformed() {
+    assert!(Coverage::from_lcov("DA:1,1\n").is_err());
+    assert!(Coverage::from_lcov("SF:a.bf\nDA:1\nend_of_record\n").is_err());
+    assert!(Coverage::from_lcov("SF:a.bf\nDA:1,x\nend_of_record\n").is_err());
+    assert!(Coverage::from_lcov("SF:a.bf\nDA:1,1\n").is_err());
+}
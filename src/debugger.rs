@@ -0,0 +1,464 @@
+//! Source-level debugger `debugger::Debugger`.
+//!
+//! The debugger steps through the *unoptimized* program,
+//! so each instruction maps to exactly one command in the source text.
+//! The debug character `#` in the source is a built-in breakpoint.
+//! All steps are recorded in a `history::History`, so the debugger can
+//! also step back, continue backwards and rewind to earlier steps.
+
+use std::collections::BTreeSet;
+use std::fmt::Write as FmtWrite;
+use std::io::{Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+use ast::Instruction::*;
+use ast::Program;
+use history::{History, LastWrite, Limits};
+use parser::{self, Options, ParseError};
+use repl::{tape_view, TAPE_VIEW_RADIUS};
+use span::{Position, SourceMap};
+use tape::{Config, Tape};
+
+const HELP: &str = "\
+Commands:
+  break LINE[:COLUMN]    sets a breakpoint at the first command at or behind the position
+  delete LINE[:COLUMN]   removes the breakpoint set with the same position
+  watch CELL             stops after the value of CELL changed
+  unwatch CELL           removes the watchpoint of CELL
+  step                   executes one instruction, steps into loops
+  next                   executes one instruction, steps over whole loops
+  continue               runs until the next breakpoint or watchpoint
+  back                   undoes the last instruction (output is not undone)
+  reverse-continue       runs backwards until the previous breakpoint or watchpoint
+  rewind STEP            returns to the state after STEP executed instructions
+  last-write CELL        shows the instruction which wrote CELL last
+  where                  shows the current position
+  tape                   shows the cells around the data-pointer
+  info                   lists the breakpoints and watchpoints
+  restart                restarts the program with an empty tape
+  help                   shows this help
+  quit                   ends the debugger";
+
+/// Why the debugger stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    /// Stopped before the instruction at the given index because of a
+    /// breakpoint or the debug character `#`.
+    Breakpoint(usize),
+    /// Stopped after the value of the watched `cell` changed.
+    Watchpoint { cell: usize, old: u32, new: u32 },
+    /// Stopped after a step.
+    Step,
+    /// The program has finished.
+    Finished,
+    /// Stepping back reached the beginning of the recorded history.
+    Beginning,
+}
+
+/// The debugger for one program.
+pub struct Debugger {
+    program: Program,
+    source_map: SourceMap,
+    lines: Vec<String>,
+    config: Config,
+    tape: Tape,
+    pc: usize,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    history: History,
+}
+
+impl Debugger {
+    /// Creates a debugger for the brainfuck source `text`, which runs
+    /// on a tape with configuration `config`.
+    pub fn new(text: &str, config: &Config) -> Result<Debugger, ParseError> {
+        parser::check(text)?;
+        let options = Options {
+            debug: true,
+            ..Options::default()
+        };
+        let (program, source_map) = parser::parse_with(text, &options);
+        Ok(Debugger::with_program(program, source_map, text, config))
+    }
+
+    /// Creates a debugger for the unoptimized `program` parsed from `text`
+    /// with the debug character, e.g. by `preprocess::Expansion::parse_dialect`.
+    /// `source_map` refers to `text`.
+    pub fn with_program(program: Program,
+                        source_map: SourceMap,
+                        text: &str,
+                        config: &Config)
+                        -> Debugger {
+        Debugger {
+            program,
+            source_map,
+            lines: text.lines().map(str::to_string).collect(),
+            config: *config,
+            tape: Tape::new(config),
+            pc: 0,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            history: History::default(),
+        }
+    }
+
+    /// Returns the debugged (unoptimized) program.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Returns the source positions of the instructions of `program()`.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Returns the tape.
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    /// Returns the index of the next instruction to execute.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Returns whether the program has finished.
+    pub fn is_finished(&self) -> bool {
+        self.pc >= self.program.instructions.len()
+    }
+
+    /// Returns the number of executed instructions.
+    pub fn steps(&self) -> u64 {
+        self.history.steps()
+    }
+
+    /// Restarts the program with an empty tape.
+    /// Breakpoints and watchpoints are kept, the history is cleared.
+    pub fn restart(&mut self) {
+        self.tape = Tape::new(&self.config);
+        self.pc = 0;
+        self.history.clear();
+    }
+
+    /// Sets the memory limits of the history and clears it.
+    pub fn set_history_limits(&mut self, limits: Limits) {
+        self.history = History::new(limits);
+    }
+
+    /// Sets a breakpoint at the first instruction at or behind `position`.
+    /// Returns the index of that instruction or `None` if there is none.
+    pub fn set_breakpoint(&mut self, position: Position) -> Option<usize> {
+        let pc = self.source_map.find(position)?;
+        self.breakpoints.insert(pc);
+        Some(pc)
+    }
+
+    /// Removes the breakpoint set with `set_breakpoint(position)`.
+    /// Returns the index of its instruction or `None` if there was none.
+    pub fn remove_breakpoint(&mut self, position: Position) -> Option<usize> {
+        let pc = self.source_map.find(position)?;
+        if self.breakpoints.remove(&pc) {
+            Some(pc)
+        } else {
+            None
+        }
+    }
+
+    /// Stops execution after the value of `cell` changed.
+    pub fn set_watchpoint(&mut self, cell: usize) {
+        self.watchpoints.insert(cell);
+    }
+
+    /// Removes the watchpoint of `cell`.
+    /// Returns whether there was one.
+    pub fn remove_watchpoint(&mut self, cell: usize) -> bool {
+        self.watchpoints.remove(&cell)
+    }
+
+    fn is_breakpoint(&self, pc: usize) -> bool {
+        self.breakpoints.contains(&pc) || self.program.instructions.get(pc) == Some(&Breakpoint)
+    }
+
+    /// Executes one instruction.
+    /// Returns the `Stop::Watchpoint` if it changed a watched cell.
+    fn execute(&mut self, input: &mut dyn Read, output: &mut dyn Write) -> Option<Stop> {
+        let cell = self.tape.dataptr();
+        let old = self.tape.cells().get(cell).cloned();
+        self.pc = self.history
+            .step(&self.program, self.pc, &mut self.tape, input, output);
+        let new = self.tape.cells().get(cell).cloned();
+        match (old, new) {
+            (Some(old), Some(new)) if old != new && self.watchpoints.contains(&cell) => {
+                Some(Stop::Watchpoint { cell, old, new })
+            }
+            _ => None,
+        }
+    }
+
+    /// Executes the next instruction, loops are entered (step into).
+    pub fn step(&mut self, input: &mut dyn Read, output: &mut dyn Write) -> Stop {
+        if self.is_finished() {
+            return Stop::Finished;
+        }
+        match self.execute(input, output) {
+            Some(stop) => stop,
+            None if self.is_finished() => Stop::Finished,
+            None => Stop::Step,
+        }
+    }
+
+    /// Executes the next instruction, a loop is executed completely (step over).
+    /// Breakpoints and watchpoints within the loop still stop.
+    pub fn step_over(&mut self, input: &mut dyn Read, output: &mut dyn Write) -> Stop {
+        match self.program.instructions.get(self.pc) {
+            Some(&LoopEntry(target)) => self.run(Some(target + 1), input, output),
+            _ => self.step(input, output),
+        }
+    }
+
+    /// Runs until the next breakpoint, watchpoint or the end of the program.
+    /// The next instruction is executed even if it has a breakpoint,
+    /// so continuing after a breakpoint does not stop at it again.
+    pub fn resume(&mut self, input: &mut dyn Read, output: &mut dyn Write) -> Stop {
+        self.run(None, input, output)
+    }
+
+    /// Undoes the last executed instruction.
+    /// Returns the `Stop::Watchpoint` if it changed a watched cell
+    /// and `Stop::Beginning` if there is nothing to undo.
+    fn unexecute(&mut self) -> Option<Stop> {
+        let undone = match self.history.undo(&self.program, &mut self.tape) {
+            Some(undone) => undone,
+            None => return Some(Stop::Beginning),
+        };
+        self.pc = undone.pc;
+        if undone.before != undone.after && self.watchpoints.contains(&undone.cell) {
+            Some(Stop::Watchpoint {
+                     cell: undone.cell,
+                     old: undone.before,
+                     new: undone.after,
+                 })
+        } else {
+            None
+        }
+    }
+
+    /// Undoes the last executed instruction (step back).
+    /// The output of the instruction is not undone.
+    pub fn step_back(&mut self) -> Stop {
+        self.unexecute().unwrap_or(Stop::Step)
+    }
+
+    /// Runs backwards until the previous breakpoint, watchpoint or the
+    /// beginning of the history (reverse continue).
+    /// Stops before the instruction with the breakpoint, like `resume`.
+    pub fn reverse_resume(&mut self) -> Stop {
+        loop {
+            if let Some(stop) = self.unexecute() {
+                return stop;
+            }
+            if self.is_breakpoint(self.pc) {
+                return Stop::Breakpoint(self.pc);
+            }
+        }
+    }
+
+    /// Returns to the state after `step` executed instructions,
+    /// see `history::History::rewind`.
+    pub fn rewind(&mut self, step: u64) -> Result<(), String> {
+        self.pc = self.history
+            .rewind(step, &self.program, self.pc, &mut self.tape)?;
+        Ok(())
+    }
+
+    /// Returns the last write of `cell` within the history.
+    pub fn last_write(&self, cell: usize) -> Option<LastWrite> {
+        self.history.last_write(&self.program, &self.tape, cell)
+    }
+
+    /// Runs until a breakpoint, watchpoint, the end of the program
+    /// or until reaching the instruction at `until`.
+    fn run(&mut self, until: Option<usize>, input: &mut dyn Read, output: &mut dyn Write) -> Stop {
+        loop {
+            if self.is_finished() {
+                return Stop::Finished;
+            }
+            if let Some(stop) = self.execute(input, output) {
+                return stop;
+            }
+            if self.is_finished() {
+                return Stop::Finished;
+            }
+            if Some(self.pc) == until {
+                return Stop::Step;
+            }
+            if self.is_breakpoint(self.pc) {
+                return Stop::Breakpoint(self.pc);
+            }
+        }
+    }
+
+    /// Describes the position of instruction `pc`: the source position,
+    /// the instruction and the source line with a marker below the command.
+    fn describe(&self, pc: usize) -> String {
+        let (instr, span) = match (self.program.instructions.get(pc), self.source_map.span(pc)) {
+            (Some(instr), Some(span)) => (instr, span),
+            _ => return "program finished\n".to_string(),
+        };
+        let mut text = format!("at {:}, pc={:}: {:?}\n", span, pc, instr);
+        if let Some(line) = self.lines.get(span.start.line - 1) {
+            let prefix = format!("{:5} | ", span.start.line);
+            let _ = writeln!(text, "{:}{:}", prefix, line);
+            let _ = writeln!(text,
+                             "{:>w$} | {:>c$}",
+                             "",
+                             "^",
+                             w = prefix.len() - 3,
+                             c = span.start.column);
+        }
+        text
+    }
+
+    /// Describes why the debugger stopped and where.
+    fn describe_stop(&self, stop: Stop) -> String {
+        match stop {
+            Stop::Breakpoint(pc) => format!("breakpoint {:}", self.describe(pc)),
+            Stop::Watchpoint { cell, old, new } => {
+                format!("cell {:} changed from {:} to {:}, now {:}",
+                        cell,
+                        old,
+                        new,
+                        self.describe(self.pc))
+            }
+            Stop::Step => self.describe(self.pc),
+            Stop::Finished => "program finished\n".to_string(),
+            Stop::Beginning => {
+                format!("beginning of the history, step {:}, {:}",
+                        self.steps(),
+                        self.describe(self.pc))
+            }
+        }
+    }
+
+    /// Evaluates one debugger command `line` (see `help`).
+    /// The program reads from `input` and writes to `output`,
+    /// so do the commands.
+    ///
+    /// Returns `Ok(false)` if the debugger shall end.
+    pub fn eval(&mut self,
+                line: &str,
+                input: &mut dyn Read,
+                output: &mut dyn Write)
+                -> Result<bool, String> {
+        fn parse_position(arg: &str) -> Result<Position, String> {
+            let mut parts = arg.splitn(2, ':');
+            let line = parts.next().unwrap_or("").parse();
+            let column = parts.next().map_or(Ok(1), str::parse);
+            match (line, column) {
+                (Ok(line), Ok(column)) => Ok(Position { line, column }),
+                _ => Err(format!("Invalid position {:?}, expected LINE[:COLUMN]", arg)),
+            }
+        }
+        fn parse_cell(arg: &str) -> Result<usize, String> {
+            arg.parse()
+                .map_err(|_| format!("Invalid cell {:?}", arg))
+        }
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let arg = words.next().unwrap_or("");
+        let text = match command {
+            "" => String::new(),
+            "quit" | "q" => return Ok(false),
+            "help" | "h" => format!("{:}\n", HELP),
+            "break" | "b" => {
+                let position = parse_position(arg)?;
+                match self.set_breakpoint(position) {
+                    Some(pc) => format!("breakpoint {:}", self.describe(pc)),
+                    None => return Err(format!("No command at or behind {:}", position)),
+                }
+            }
+            "delete" | "d" => {
+                let position = parse_position(arg)?;
+                match self.remove_breakpoint(position) {
+                    Some(pc) => format!("deleted breakpoint at pc={:}\n", pc),
+                    None => return Err(format!("No breakpoint at {:}", position)),
+                }
+            }
+            "watch" => {
+                let cell = parse_cell(arg)?;
+                self.set_watchpoint(cell);
+                format!("watching cell {:}\n", cell)
+            }
+            "unwatch" => {
+                let cell = parse_cell(arg)?;
+                if !self.remove_watchpoint(cell) {
+                    return Err(format!("No watchpoint on cell {:}", cell));
+                }
+                format!("stopped watching cell {:}\n", cell)
+            }
+            "step" | "s" | "next" | "n" | "continue" | "c" => {
+                let stop = panic::catch_unwind(AssertUnwindSafe(|| match command {
+                    "step" | "s" => self.step(input, output),
+                    "next" | "n" => self.step_over(input, output),
+                    _ => self.resume(input, output),
+                }))
+                        .map_err(|_| "Program aborted, see restart".to_string())?;
+                self.describe_stop(stop)
+            }
+            "back" | "bs" => {
+                let stop = self.step_back();
+                self.describe_stop(stop)
+            }
+            "reverse-continue" | "rc" => {
+                let stop = self.reverse_resume();
+                self.describe_stop(stop)
+            }
+            "rewind" => {
+                let step = arg.parse()
+                    .map_err(|_| format!("Invalid step {:?}", arg))?;
+                self.rewind(step)?;
+                format!("step {:}, {:}", step, self.describe(self.pc))
+            }
+            "last-write" | "lw" => {
+                let cell = parse_cell(arg)?;
+                match self.last_write(cell) {
+                    Some(write) => {
+                        format!("cell {:} written at step {:} from {:} to {:} {:}",
+                                cell,
+                                write.step,
+                                write.old,
+                                write.new,
+                                self.describe(write.pc))
+                    }
+                    None => {
+                        format!("cell {:} not written since step {:}\n",
+                                cell,
+                                self.history.start())
+                    }
+                }
+            }
+            "where" | "w" => self.describe(self.pc),
+            "tape" | "t" => tape_view(&self.tape, TAPE_VIEW_RADIUS),
+            "info" | "i" => {
+                let mut text = String::new();
+                for pc in &self.breakpoints {
+                    let _ = write!(text, "breakpoint {:}", self.describe(*pc));
+                }
+                for cell in &self.watchpoints {
+                    let _ = writeln!(text, "watchpoint on cell {:}", cell);
+                }
+                text
+            }
+            "restart" | "r" => {
+                self.restart();
+                self.describe(self.pc)
+            }
+            _ => return Err(format!("Unknown command {:?}, see help", command)),
+        };
+        output
+            .write_all(text.as_bytes())
+            .map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+}
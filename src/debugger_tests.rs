@@ -0,0 +1,319 @@
+//! Tests for the debugger `debugger::Debugger`.
+
+use buffer::*;
+use debugger::{Debugger, Stop};
+use span::Position;
+use tape::Config;
+
+fn debugger(text: &str) -> Debugger {
+    Debugger::new(text, &Config::default()).expect("text must parse")
+}
+
+fn position(line: usize, column: usize) -> Position {
+    Position { line, column }
+}
+
+#[test]
+fn reject_unbalanced() {
+    assert!(Debugger::new("+[", &Config::default()).is_err());
+}
+
+#[test]
+fn step_into() {
+    let mut d = debugger("++[-]");
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    assert_eq!(Stop::Step, d.step(&mut input, &mut output));
+    assert_eq!(Stop::Step, d.step(&mut input, &mut output));
+    assert_eq!(Stop::Step, d.step(&mut input, &mut output));
+    assert_eq!(3, d.pc());
+    assert_eq!(Stop::Step, d.step(&mut input, &mut output));
+    assert_eq!(Stop::Step, d.step(&mut input, &mut output));
+    assert_eq!(3, d.pc());
+}
+
+#[test]
+fn step_over_loop() {
+    let mut d = debugger("++[-]+");
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    d.step(&mut input, &mut output);
+    d.step(&mut input, &mut output);
+    assert_eq!(Stop::Step, d.step_over(&mut input, &mut output));
+    assert_eq!(5, d.pc());
+    assert_eq!(0, d.tape().get());
+    assert_eq!(Stop::Finished, d.step_over(&mut input, &mut output));
+    assert!(d.is_finished());
+}
+
+#[test]
+fn step_over_skipped_loop() {
+    let mut d = debugger("[-]+");
+    assert_eq!(Stop::Step,
+               d.step_over(&mut IBuffer::from_str(""), &mut Vec::new()));
+    assert_eq!(3, d.pc());
+}
+
+#[test]
+fn breakpoint_by_line_and_column() {
+    let mut d = debugger("+++\n  >++ +\n.");
+    assert_eq!(Some(5), d.set_breakpoint(position(2, 5)));
+    assert_eq!(Some(6), d.set_breakpoint(position(2, 6)));
+    assert_eq!(Some(3), d.set_breakpoint(position(2, 1)));
+    assert_eq!(None, d.set_breakpoint(position(4, 1)));
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    assert_eq!(Stop::Breakpoint(3), d.resume(&mut input, &mut output));
+    assert_eq!(Stop::Breakpoint(5), d.resume(&mut input, &mut output));
+    assert_eq!((1, 1), (d.tape().dataptr(), d.tape().get()));
+    assert_eq!(Stop::Breakpoint(6), d.resume(&mut input, &mut output));
+    assert_eq!(Stop::Finished, d.resume(&mut input, &mut output));
+    assert_eq!(&[3], output.as_slice());
+}
+
+#[test]
+fn remove_breakpoint() {
+    let mut d = debugger("+\n+\n+");
+    d.set_breakpoint(position(2, 1));
+    assert_eq!(Some(1), d.remove_breakpoint(position(2, 1)));
+    assert_eq!(None, d.remove_breakpoint(position(2, 1)));
+    assert_eq!(Stop::Finished,
+               d.resume(&mut IBuffer::from_str(""), &mut Vec::new()));
+}
+
+#[test]
+fn debug_char_is_breakpoint() {
+    let mut d = debugger("++#-#.");
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    assert_eq!(Stop::Breakpoint(2), d.resume(&mut input, &mut output));
+    assert_eq!(2, d.tape().get());
+    assert_eq!(Stop::Breakpoint(4), d.resume(&mut input, &mut output));
+    assert_eq!(Stop::Finished, d.resume(&mut input, &mut output));
+    assert_eq!(&[1], output.as_slice());
+}
+
+#[test]
+fn breakpoint_in_loop() {
+    let mut d = debugger("+++[\n-]");
+    d.set_breakpoint(position(2, 1));
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    for value in &[3, 2, 1] {
+        assert_eq!(Stop::Breakpoint(4), d.resume(&mut input, &mut output));
+        assert_eq!(*value, d.tape().get());
+    }
+    assert_eq!(Stop::Finished, d.resume(&mut input, &mut output));
+}
+
+#[test]
+fn step_over_stops_at_breakpoint_in_loop() {
+    let mut d = debugger("+[#-]");
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    d.step(&mut input, &mut output);
+    assert_eq!(Stop::Breakpoint(2), d.step_over(&mut input, &mut output));
+}
+
+#[test]
+fn watchpoint() {
+    let mut d = debugger(">+>+<+++");
+    d.set_watchpoint(1);
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    assert_eq!(Stop::Watchpoint {
+                   cell: 1,
+                   old: 0,
+                   new: 1,
+               },
+               d.resume(&mut input, &mut output));
+    assert_eq!(2, d.pc());
+    assert_eq!(Stop::Watchpoint {
+                   cell: 1,
+                   old: 1,
+                   new: 2,
+               },
+               d.resume(&mut input, &mut output));
+    assert!(d.remove_watchpoint(1));
+    assert_eq!(Stop::Finished, d.resume(&mut input, &mut output));
+    assert_eq!(4, d.tape().cells()[1]);
+}
+
+#[test]
+fn watchpoint_on_input() {
+    let mut d = debugger(",,");
+    d.set_watchpoint(0);
+    let mut input = IBuffer::from_str("aa");
+    let mut output = Vec::new();
+    assert_eq!(Stop::Watchpoint {
+                   cell: 0,
+                   old: 0,
+                   new: 97,
+               },
+               d.resume(&mut input, &mut output));
+    // reading the same value is no change
+    assert_eq!(Stop::Finished, d.resume(&mut input, &mut output));
+}
+
+#[test]
+fn restart() {
+    let mut d = debugger("+++");
+    d.resume(&mut IBuffer::from_str(""), &mut Vec::new());
+    d.restart();
+    assert_eq!(0, d.pc());
+    assert_eq!(0, d.tape().get());
+}
+
+fn eval(d: &mut Debugger, line: &str) -> String {
+    let mut output = Vec::new();
+    let res = d.eval(line, &mut IBuffer::from_str(""), &mut output);
+    assert_eq!(Ok(true), res, "line {:?}", line);
+    String::from_utf8(output).expect("output must be utf-8")
+}
+
+#[test]
+fn eval_break_and_continue() {
+    let mut d = debugger("++\n[->+<]");
+    assert_eq!("breakpoint at 2:3, pc=4: MoveRight(1)\n    2 | [->+<]\n      |   ^\n",
+               eval(&mut d, "break 2:3"));
+    assert_eq!("breakpoint at 2:3, pc=4: MoveRight(1)\n    2 | [->+<]\n      |   ^\n",
+               eval(&mut d, "continue"));
+    assert_eq!("at 2:4, pc=5: Inc(1)\n    2 | [->+<]\n      |    ^\n",
+               eval(&mut d, "step"));
+    assert_eq!("watching cell 1\n", eval(&mut d, "watch 1"));
+    assert_eq!("cell 1 changed from 0 to 1, now at 2:5, pc=6: MoveLeft(1)\n    2 | [->+<]\n      |     ^\n",
+               eval(&mut d, "c"));
+    assert_eq!("deleted breakpoint at pc=4\n", eval(&mut d, "delete 2:3"));
+    assert_eq!("stopped watching cell 1\n", eval(&mut d, "unwatch 1"));
+    assert_eq!("program finished\n", eval(&mut d, "c"));
+}
+
+#[test]
+fn eval_tape_and_info() {
+    let mut d = debugger(">+");
+    eval(&mut d, "c");
+    assert_eq!(" 0 1 2 3 4 5 6 7 8 9\n 0 1 0 0 0 0 0 0 0 0\n   ^\n",
+               eval(&mut d, "tape"));
+    eval(&mut d, "b 1");
+    eval(&mut d, "watch 3");
+    assert_eq!("breakpoint at 1:1, pc=0: MoveRight(1)\n    1 | >+\n      | ^\nwatchpoint on cell 3\n",
+               eval(&mut d, "info"));
+}
+
+#[test]
+fn eval_errors() {
+    let mut d = debugger("+");
+    let mut input = IBuffer::from_str("");
+    assert!(d.eval("break x", &mut input, &mut Vec::new()).is_err());
+    assert!(d.eval("break 7", &mut input, &mut Vec::new()).is_err());
+    assert!(d.eval("delete 1", &mut input, &mut Vec::new()).is_err());
+    assert!(d.eval("unwatch 1", &mut input, &mut Vec::new()).is_err());
+    assert!(d.eval("frobnicate", &mut input, &mut Vec::new()).is_err());
+    assert_eq!(Ok(false), d.eval("quit", &mut input, &mut Vec::new()));
+}
+
+#[test]
+fn eval_aborted_program() {
+    let mut d = debugger("<");
+    assert!(d.eval("step", &mut IBuffer::from_str(""), &mut Vec::new())
+                .is_err());
+}
+
+#[test]
+fn step_back() {
+    let mut d = debugger("+>+");
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    d.resume(&mut input, &mut output);
+    assert_eq!(3, d.steps());
+    assert_eq!(Stop::Step, d.step_back());
+    assert_eq!((2, 1, 0), (d.pc(), d.tape().dataptr(), d.tape().get()));
+    assert_eq!(Stop::Step, d.step_back());
+    assert_eq!(Stop::Step, d.step_back());
+    assert_eq!((0, 0, 0), (d.pc(), d.tape().dataptr(), d.tape().get()));
+    assert_eq!(Stop::Beginning, d.step_back());
+}
+
+#[test]
+fn reverse_continue_to_breakpoint() {
+    let mut d = debugger("+++[\n-]+");
+    let mut input = IBuffer::from_str("");
+    let mut output = Vec::new();
+    d.set_breakpoint(position(2, 1));
+    while d.resume(&mut input, &mut output) != Stop::Finished {}
+    assert_eq!(Stop::Breakpoint(4), d.reverse_resume());
+    assert_eq!(1, d.tape().get());
+    assert_eq!(Stop::Breakpoint(4), d.reverse_resume());
+    assert_eq!(2, d.tape().get());
+    assert_eq!(Stop::Breakpoint(4), d.reverse_resume());
+    assert_eq!(Stop::Beginning, d.reverse_resume());
+    assert_eq!(0, d.pc());
+}
+
+#[test]
+fn reverse_continue_to_watchpoint() {
+    let mut d = debugger("+>++>+");
+    d.resume(&mut IBuffer::from_str(""), &mut Vec::new());
+    d.set_watchpoint(1);
+    assert_eq!(Stop::Watchpoint {
+                   cell: 1,
+                   old: 2,
+                   new: 1,
+               },
+               d.reverse_resume());
+    assert_eq!(3, d.pc());
+}
+
+#[test]
+fn forward_after_step_back_replays_input() {
+    let mut d = debugger(",.");
+    let mut input = IBuffer::from_str("a");
+    let mut output = Vec::new();
+    d.resume(&mut input, &mut output);
+    d.step_back();
+    d.step_back();
+    d.resume(&mut input, &mut output);
+    assert_eq!(b"aa", output.as_slice());
+}
+
+#[test]
+fn eval_reverse_commands() {
+    let mut d = debugger("++\n>+");
+    eval(&mut d, "c");
+    assert_eq!("cell 0 written at step 1 from 1 to 2 at 1:2, pc=1: Inc(1)\n    1 | ++\n      |  ^\n",
+               eval(&mut d, "last-write 0"));
+    assert_eq!("cell 5 not written since step 0\n",
+               eval(&mut d, "lw 5"));
+    assert_eq!("at 2:2, pc=3: Inc(1)\n    2 | >+\n      |  ^\n",
+               eval(&mut d, "back"));
+    assert_eq!("step 1, at 1:2, pc=1: Inc(1)\n    1 | ++\n      |  ^\n",
+               eval(&mut d, "rewind 1"));
+    assert_eq!("beginning of the history, step 0, at 1:1, pc=0: Inc(1)\n    1 | ++\n      | ^\n",
+               eval(&mut d, "rc"));
+    let mut input = IBuffer::from_str("");
+    assert!(d.eval("rewind 9", &mut input, &mut Vec::new()).is_err());
+    assert!(d.eval("rewind x", &mut input, &mut Vec::new()).is_err());
+}
+
+#[test]
+fn debug_expanded_macros() {
+    use dialect::Brainfuck;
+    use preprocess;
+    use std::path::Path;
+
+    let text = "#define ADD(n) +*n\nADD(3)\n>ADD(2).";
+    let brainfuck = Brainfuck {
+        debug: true,
+        ..Brainfuck::default()
+    };
+    let (program, source_map) = preprocess::expand(text, Path::new("main.bf"))
+        .unwrap()
+        .parse_dialect(&brainfuck)
+        .unwrap();
+    let mut d = Debugger::with_program(program, source_map, text, &Config::default());
+    // the breakpoint at the macro stops at its first instruction
+    assert_eq!(Some(4), d.set_breakpoint(position(3, 2)));
+    assert_eq!(Stop::Breakpoint(4),
+               d.resume(&mut IBuffer::from_str(""), &mut Vec::new()));
+    assert_eq!(Some(position(3, 2)), d.source_map().span(4).map(|span| span.start));
+}
@@ -0,0 +1,510 @@
+//! Brainfuck dialects `dialect::Dialect` and translators between them.
+//!
+//! A dialect splits a source text into tokens and maps each token to a
+//! brainfuck command, i.e. a unit `Instruction` like `Inc(1)`. Loop
+//! instructions carry placeholder targets, `parser::parse_dialect` resolves
+//! them. Anything else in the text is a comment.
+//!
+//! Besides plain `Brainfuck`, `Keywords` implements token substitution
+//! languages like Ook! and Blub, where each command is a sequence of
+//! whitespace separated words. Keyword tables are loaded from config files
+//! of `KEY: VALUE` lines (see `Keywords::from_config`):
+//! ```text
+//! name: trivial
+//! >: right
+//! <: left
+//! +: up
+//! -: down
+//! .: out
+//! ,: in
+//! [: begin
+//! ]: end
+//! ```
+//! `translate` writes a `Program` in any dialect, so `convert` translates
+//! between dialects via the shared `Program`.
+
+use std::fs::File;
+use std::io::Read;
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+use boolfuck;
+use parser;
+use printer;
+use span::{Position, Span};
+
+/// Maximum number of characters per line written by `translate`.
+pub const LINE_WIDTH: usize = 72;
+
+/// The commands every keyword table has to define.
+const COMMANDS: [char; 8] = ['>', '<', '+', '-', '.', ',', '[', ']'];
+
+/// The machine the programs of a dialect run on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Machine {
+    /// A tape of cells, run by the engines of `interp`.
+    Cells,
+    /// A tape of bits, run by `boolfuck::run`.
+    Bits,
+}
+
+/// A brainfuck dialect.
+pub trait Dialect {
+    /// Returns the name of the dialect, e.g. `ook`.
+    fn name(&self) -> &str;
+
+    /// Returns the machine the programs of the dialect run on.
+    fn machine(&self) -> Machine {
+        Machine::Cells
+    }
+
+    /// Returns the dialect parsing its debug command as `Breakpoint`,
+    /// `None` if it has no debug command.
+    fn with_breakpoints(&self) -> Option<Box<dyn Dialect>> {
+        None
+    }
+
+    /// Returns the commands in `text` as unit instructions with the span
+    /// of their tokens. Loop instructions have placeholder targets.
+    fn tokenize(&self, text: &str) -> Vec<(Instruction, Span)>;
+
+    /// Returns the token of the command of `instr`, ignoring its operand,
+    /// or `None` if the dialect has no token for it.
+    fn token(&self, instr: Instruction) -> Option<String>;
+
+    /// Returns the separator written between two tokens by `translate`.
+    fn separator(&self) -> &str {
+        " "
+    }
+}
+
+/// Returns the unit instruction of the brainfuck command `c`, including the
+/// debug, pbrain, Brainfork and Extended Brainfuck commands, loop and
+/// procedure instructions
+/// with placeholder targets.
+pub(crate) fn instruction(c: char) -> Option<Instruction> {
+    match c {
+        '<' => Some(MoveLeft(1)),
+        '>' => Some(MoveRight(1)),
+        '+' => Some(Inc(1)),
+        '-' => Some(Dec(1)),
+        '.' => Some(Output),
+        ',' => Some(Input),
+        '[' => Some(LoopEntry(0)),
+        ']' => Some(LoopExit(0)),
+        '#' => Some(Breakpoint),
+        '(' => Some(ProcedureStart(0)),
+        ')' => Some(ProcedureEnd(0)),
+        ':' => Some(Call),
+        'Y' => Some(Fork),
+        '@' => Some(End),
+        '$' => Some(Store),
+        '!' => Some(Load),
+        '}' => Some(ShiftRight),
+        '{' => Some(ShiftLeft),
+        '~' => Some(Not),
+        '^' => Some(Xor),
+        '&' => Some(And),
+        '|' => Some(Or),
+        _ => None,
+    }
+}
+
+/// Returns each character of `text` with its position.
+fn positions(text: &str) -> Vec<(Position, char)> {
+    let mut position = Position { line: 1, column: 1 };
+    let mut chars = Vec::new();
+    for c in text.chars() {
+        chars.push((position, c));
+        if c == '\n' {
+            position = Position {
+                line: position.line + 1,
+                column: 1,
+            };
+        } else {
+            position.column += 1;
+        }
+    }
+    chars
+}
+
+/// Brainfuck with its eight ASCII commands, optionally extended by
+/// pbrain's procedures `(`, `)` and `:`, by Brainfork's `Y` and by
+/// Extended Brainfuck Type I's `@ $ ! } { ~ ^ & |`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Brainfuck {
+    /// Parses the debug character `#` as `Breakpoint` instead of skipping it.
+    pub debug: bool,
+    /// Parses `(`, `)` and `:` as pbrain procedure instructions
+    /// instead of skipping them.
+    pub pbrain: bool,
+    /// Parses Brainfork's `Y` as `Fork` instead of skipping it.
+    pub fork: bool,
+    /// Parses the Extended Brainfuck Type I commands instead of skipping them.
+    pub extended: bool,
+}
+
+impl Brainfuck {
+    /// Returns whether the command `c` is parsed instead of skipped.
+    fn parses(&self, c: char) -> bool {
+        match c {
+            '#' => self.debug,
+            '(' | ')' | ':' => self.pbrain,
+            'Y' => self.fork,
+            '@' | '$' | '!' | '}' | '{' | '~' | '^' | '&' | '|' => self.extended,
+            _ => true,
+        }
+    }
+}
+
+impl Dialect for Brainfuck {
+    fn name(&self) -> &str {
+        match (self.pbrain, self.fork, self.extended) {
+            (false, false, false) => "brainfuck",
+            (true, false, false) => "pbrain",
+            (false, true, false) => "brainfork",
+            (true, true, false) => "pbrain-brainfork",
+            (false, false, true) => "extended",
+            (true, false, true) => "pbrain-extended",
+            (false, true, true) => "brainfork-extended",
+            (true, true, true) => "pbrain-brainfork-extended",
+        }
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<(Instruction, Span)> {
+        positions(text)
+            .into_iter()
+            .filter(|&(_, c)| self.parses(c))
+            .filter_map(|(position, c)| instruction(c).map(|instr| (instr, Span::at(position))))
+            .collect()
+    }
+
+    /// `#` stays a token without `debug`, other programs skip it anyway.
+    fn token(&self, instr: Instruction) -> Option<String> {
+        let command = printer::command(instr).0;
+        if command == '#' || self.parses(command) {
+            Some(command.to_string())
+        } else {
+            None
+        }
+    }
+
+    fn separator(&self) -> &str {
+        ""
+    }
+
+    fn with_breakpoints(&self) -> Option<Box<dyn Dialect>> {
+        Some(Box::new(Brainfuck {
+                          debug: true,
+                          ..*self
+                      }))
+    }
+}
+
+/// Boolfuck, brainfuck on a tape of bits: `+` flips the current bit, `;`
+/// outputs it and `,` reads a bit. `Inc(1)` stands for the flip, `Output`
+/// and `Input` for the bit I/O, so Boolfuck programs only run in the bit
+/// mode of `boolfuck::run_with`. `boolfuck::translate` translates brainfuck
+/// programs to Boolfuck.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Boolfuck;
+
+impl Dialect for Boolfuck {
+    fn name(&self) -> &str {
+        "boolfuck"
+    }
+
+    fn machine(&self) -> Machine {
+        Machine::Bits
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<(Instruction, Span)> {
+        positions(text)
+            .into_iter()
+            .filter_map(|(position, c)| {
+                let instr = match c {
+                    '+' => Inc(1),
+                    ';' => Output,
+                    ',' => Input,
+                    '<' | '>' | '[' | ']' => instruction(c)?,
+                    _ => return None,
+                };
+                Some((instr, Span::at(position)))
+            })
+            .collect()
+    }
+
+    fn token(&self, instr: Instruction) -> Option<String> {
+        match instr {
+            Inc(_) => Some("+".to_string()),
+            Output => Some(";".to_string()),
+            MoveLeft(_) | MoveRight(_) | Input | LoopEntry(_) | LoopExit(_) => {
+                Some(printer::command(instr).0.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn separator(&self) -> &str {
+        ""
+    }
+}
+
+/// A token substitution dialect: each command is a sequence of words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keywords {
+    name: String,
+    /// The brainfuck command and the words of its keyword.
+    table: Vec<(char, Vec<String>)>,
+}
+
+impl Keywords {
+    /// Creates the dialect `name` from the keyword of each brainfuck command.
+    /// Keywords consist of whitespace separated words.
+    ///
+    /// Fails if a command of `COMMANDS` has no keyword, if a keyword is
+    /// empty or if two commands share a keyword.
+    pub fn new(name: &str, keywords: &[(char, &str)]) -> Result<Keywords, String> {
+        let mut table: Vec<(char, Vec<String>)> = Vec::new();
+        for &(command, keyword) in keywords {
+            if instruction(command).is_none() {
+                return Err(format!("Unknown command {:?}", command));
+            }
+            let words: Vec<String> = keyword.split_whitespace().map(String::from).collect();
+            if words.is_empty() {
+                return Err(format!("Empty keyword for {:?}", command));
+            }
+            if let Some(other) = table.iter().find(|entry| entry.1 == words) {
+                return Err(format!("Keyword {:?} of {:?} is already used for {:?}",
+                                   keyword,
+                                   command,
+                                   other.0));
+            }
+            table.retain(|entry| entry.0 != command);
+            table.push((command, words));
+        }
+        if let Some(missing) = COMMANDS.iter().find(|c| table.iter().all(|entry| entry.0 != **c)) {
+            return Err(format!("Missing keyword for {:?}", missing));
+        }
+        // try longer keywords first, so they win over their prefixes
+        table.sort_by_key(|entry| ::std::cmp::Reverse(entry.1.len()));
+        Ok(Keywords {
+               name: name.to_string(),
+               table,
+           })
+    }
+
+    /// Ook!, where each command is a pair of `Ook.`, `Ook?` and `Ook!`.
+    pub fn ook() -> Keywords {
+        Keywords::pairs("ook", "Ook")
+    }
+
+    /// Blub, Ook! with `Blub` instead of `Ook`.
+    pub fn blub() -> Keywords {
+        Keywords::pairs("blub", "Blub")
+    }
+
+    /// The Ook! table with `word` instead of `Ook`.
+    fn pairs(name: &str, word: &str) -> Keywords {
+        let pair = |a: char, b: char| format!("{:}{:} {:}{:}", word, a, word, b);
+        let keywords = [('>', pair('.', '?')),
+                        ('<', pair('?', '.')),
+                        ('+', pair('.', '.')),
+                        ('-', pair('!', '!')),
+                        ('.', pair('!', '.')),
+                        (',', pair('.', '!')),
+                        ('[', pair('!', '?')),
+                        (']', pair('?', '!'))];
+        let keywords: Vec<(char, &str)> = keywords
+            .iter()
+            .map(|&(c, ref keyword)| (c, keyword.as_str()))
+            .collect();
+        Keywords::new(name, &keywords).expect("Valid built-in keywords")
+    }
+
+    /// Parses a keyword table of `KEY: VALUE` lines: the key `name` sets the
+    /// name of the dialect (default: `custom`), a brainfuck command as key
+    /// sets its keyword. Empty lines are ignored.
+    pub fn from_config(text: &str) -> Result<Keywords, String> {
+        let mut name = "custom".to_string();
+        let mut keywords = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let colon = line.find(':')
+                .ok_or_else(|| format!("{:}: expected KEY: VALUE", idx + 1))?;
+            let (key, value) = (line[..colon].trim(), line[colon + 1..].trim());
+            let mut chars = key.chars();
+            match (key, chars.next(), chars.next()) {
+                ("name", _, _) => name = value.to_string(),
+                (_, Some(command), None) if instruction(command).is_some() => {
+                    keywords.push((command, value))
+                }
+                _ => return Err(format!("{:}: Unknown key {:?}", idx + 1, key)),
+            }
+        }
+        Keywords::new(&name, &keywords)
+    }
+}
+
+impl Dialect for Keywords {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<(Instruction, Span)> {
+        // the words of `text` with the span of each word
+        let mut words: Vec<(String, Span)> = Vec::new();
+        let mut in_word = false;
+        for (position, c) in positions(text) {
+            if c.is_whitespace() {
+                in_word = false;
+            } else if in_word {
+                let word = words.last_mut().expect("Current word");
+                word.0.push(c);
+                word.1.end = position;
+            } else {
+                words.push((c.to_string(), Span::at(position)));
+                in_word = true;
+            }
+        }
+
+        let mut tokens = Vec::new();
+        let mut idx = 0;
+        while idx < words.len() {
+            let matched = self.table.iter().find(|entry| {
+                let keyword = &entry.1;
+                idx + keyword.len() <= words.len() &&
+                keyword
+                    .iter()
+                    .zip(&words[idx..])
+                    .all(|(expected, word)| *expected == word.0)
+            });
+            match matched {
+                Some(&(command, ref keyword)) => {
+                    let span = words[idx].1.merge(&words[idx + keyword.len() - 1].1);
+                    tokens.push((instruction(command).expect("Valid command"), span));
+                    idx += keyword.len();
+                }
+                // not a keyword, but a comment
+                None => idx += 1,
+            }
+        }
+        tokens
+    }
+
+    fn token(&self, instr: Instruction) -> Option<String> {
+        let command = printer::command(instr).0;
+        self.table
+            .iter()
+            .find(|entry| entry.0 == command)
+            .map(|entry| entry.1.join(" "))
+    }
+}
+
+/// Returns the dialect `name`: `brainfuck` (or `bf`), `pbrain`, `brainfork`,
+/// `extended`, `boolfuck`, `ook` or `blub`, otherwise the keyword table in the config file `name`.
+pub fn lookup(name: &str) -> Result<Box<dyn Dialect>, String> {
+    match name {
+        "brainfuck" | "bf" => Ok(Box::new(Brainfuck::default())),
+        "pbrain" => {
+            Ok(Box::new(Brainfuck {
+                            pbrain: true,
+                            ..Brainfuck::default()
+                        }))
+        }
+        "brainfork" => {
+            Ok(Box::new(Brainfuck {
+                            fork: true,
+                            ..Brainfuck::default()
+                        }))
+        }
+        "extended" => {
+            Ok(Box::new(Brainfuck {
+                            extended: true,
+                            ..Brainfuck::default()
+                        }))
+        }
+        "boolfuck" => Ok(Box::new(Boolfuck)),
+        "ook" => Ok(Box::new(Keywords::ook())),
+        "blub" => Ok(Box::new(Keywords::blub())),
+        _ => {
+            let mut text = String::new();
+            File::open(name)
+                .and_then(|mut file| file.read_to_string(&mut text))
+                .map_err(|err| {
+                             format!("Unknown dialect {:?}, cannot read it because {:}", name, err)
+                         })?;
+            Keywords::from_config(&text)
+                .map(|keywords| Box::new(keywords) as Box<dyn Dialect>)
+                .map_err(|err| format!("{:}:{:}", name, err))
+        }
+    }
+}
+
+/// Returns `program` as source text of `dialect`. Optimized instructions
+/// are expanded like `printer::to_bf_source` does. Lines are wrapped after
+/// `LINE_WIDTH` characters.
+/// Fails on the first instruction without a token in `dialect`.
+pub fn translate(program: &Program, dialect: &dyn Dialect) -> Result<String, String> {
+    let separator = dialect.separator();
+    let mut text = String::new();
+    let mut column = 0;
+    for (pc, instr) in program.instructions.iter().enumerate() {
+        let (_, count) = printer::command(*instr);
+        let token = dialect.token(*instr)
+            .ok_or_else(|| {
+                            format!("Cannot translate {:?} at pc={:} to {:}",
+                                    instr,
+                                    pc,
+                                    dialect.name())
+                        })?;
+        for _ in 0..count {
+            let len = token.chars().count();
+            if column > 0 && column + separator.len() + len > LINE_WIDTH {
+                text.push('\n');
+                column = 0;
+            } else if column > 0 {
+                text.push_str(separator);
+                column += separator.len();
+            }
+            text.push_str(&token);
+            column += len;
+        }
+    }
+    if column > 0 {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Returns `program` of the dialect `from` as source text of `to` like
+/// `translate`. Programs on cells are translated to the bits of Boolfuck
+/// by `boolfuck::translate` first. Fails on programs on bits for a dialect
+/// on cells, which would not mean the same.
+pub fn translate_between(program: &Program,
+                         from: &dyn Dialect,
+                         to: &dyn Dialect)
+                         -> Result<String, String> {
+    match (from.machine(), to.machine()) {
+        (Machine::Cells, Machine::Bits) => translate(&boolfuck::translate(program)?, to),
+        (Machine::Bits, Machine::Cells) => {
+            Err(format!("Cannot translate the bits of {:} to the cells of {:}",
+                        from.name(),
+                        to.name()))
+        }
+        _ => translate(program, to),
+    }
+}
+
+/// Translates `text` from the dialect `from` to the dialect `to`.
+/// Fails like `translate_between`.
+///
+/// # Panics
+///
+/// Panics on unbalanced loops or procedures like `parser::parse_dialect`.
+pub fn convert(text: &str, from: &dyn Dialect, to: &dyn Dialect) -> Result<String, String> {
+    translate_between(&parser::parse_dialect(text, from).0, from, to)
+}
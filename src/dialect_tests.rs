@@ -0,0 +1,178 @@
+//! Tests for the dialects `dialect::Dialect`.
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+use ast::Instruction::*;
+use dialect::*;
+use parser::{self, ParseError};
+use span::{Position, Span};
+
+const TRIVIAL: &str = "name: trivial\n\n>: right\n<: left\n+: up\n-: down\n.: out\n,: in\n\
+                       [: begin\n]: end\n#: stop here";
+
+fn span(line: usize, column: usize, end_line: usize, end_column: usize) -> Span {
+    Span {
+        start: Position { line, column },
+        end: Position {
+            line: end_line,
+            column: end_column,
+        },
+    }
+}
+
+#[test]
+fn tokenize_brainfuck() {
+    assert_eq!(vec![(Inc(1), span(1, 1, 1, 1)),
+                    (LoopEntry(0), span(1, 3, 1, 3)),
+                    (LoopExit(0), span(2, 1, 2, 1))],
+               Brainfuck::default().tokenize("+ [#\n]"));
+    assert_eq!(Breakpoint, Brainfuck { debug: true, ..Brainfuck::default() }.tokenize("#")[0].0);
+}
+
+#[test]
+fn tokenize_ook() {
+    let tokens = Keywords::ook().tokenize("Ook. Ook? comment Ook! Ook!\nOok. Ook.\nOok!\n  Ook.");
+    assert_eq!(vec![(MoveRight(1), span(1, 1, 1, 9)),
+                    (Dec(1), span(1, 19, 1, 27)),
+                    (Inc(1), span(2, 1, 2, 9)),
+                    (Output, span(3, 1, 4, 6))],
+               tokens);
+}
+
+#[test]
+fn parse_ook() {
+    // +[-].
+    let text = "Ook. Ook. Ook! Ook? Ook! Ook! Ook? Ook! Ook! Ook.";
+    let (program, source_map) = parser::parse_dialect(text, &Keywords::ook());
+    assert_eq!(vec![Inc(1), LoopEntry(3), Dec(1), LoopExit(1), Output],
+               program.instructions);
+    assert_eq!(Some(span(1, 11, 1, 19)), source_map.span(1));
+    assert_eq!(parser::parse("+[-].".to_string()), program);
+}
+
+#[test]
+#[should_panic(expected = "Unbalanced ']' at 1:11-1:19")]
+fn parse_ook_unbalanced() {
+    parser::parse_dialect("Ook. Ook. Ook? Ook!", &Keywords::ook());
+}
+
+#[test]
+fn check_dialects() {
+    assert_eq!(Ok(()), parser::check_dialect("Blub! Blub? Blub? Blub!", &Keywords::blub()));
+    assert_eq!(Err(ParseError::UnbalancedLoopEntry { line: 2, column: 3 }),
+               parser::check_dialect("Blub. Blub.\n  Blub! Blub?", &Keywords::blub()));
+    assert_eq!(Err(ParseError::UnbalancedLoopExit { line: 1, column: 1 }),
+               parser::check_dialect("] [", &Brainfuck::default()));
+}
+
+#[test]
+fn keywords_from_config() {
+    let trivial = Keywords::from_config(TRIVIAL).unwrap();
+    assert_eq!("trivial", trivial.name());
+    assert_eq!(vec![Inc(1), Breakpoint, Output],
+               trivial
+                   .tokenize("up stop here out stop")
+                   .into_iter()
+                   .map(|token| token.0)
+                   .collect::<Vec<_>>());
+    assert_eq!(Some("stop here".to_string()), trivial.token(Breakpoint));
+    assert_eq!(None, Keywords::ook().token(Breakpoint));
+}
+
+#[test]
+fn invalid_keywords() {
+    assert_eq!(Err("Missing keyword for '['".to_string()),
+               Keywords::new("x",
+                             &[('>', "r"), ('<', "l"), ('+', "u"), ('-', "d"), ('.', "o"),
+                               (',', "i"), (']', "e")]));
+    assert_eq!(Err("Keyword \"a\" of '<' is already used for '>'".to_string()),
+               Keywords::new("x", &[('>', "a"), ('<', "a")]));
+    assert!(Keywords::new("x", &[('>', " ")]).is_err());
+    assert_eq!(Err("2: Unknown key \"*\"".to_string()),
+               Keywords::from_config("name: x\n*: star"));
+    assert!(Keywords::from_config("up").is_err());
+}
+
+#[test]
+fn translate_program() {
+    let program = parser::parse("++[->+<]#".to_string());
+    assert_eq!(Ok("++[->+<]\n".to_string()), translate(&program, &Brainfuck::default()));
+    assert_eq!(Ok("Blub. Blub. Blub. Blub. Blub! Blub? Blub! Blub! Blub. Blub? Blub. Blub.\n\
+                   Blub? Blub. Blub? Blub!\n"
+                      .to_string()),
+               translate(&program, &Keywords::blub()));
+    assert_eq!(Ok(String::new()), translate(&parser::parse(String::new()), &Keywords::ook()));
+}
+
+#[test]
+fn translate_fails_without_token() {
+    let pbrain = lookup("pbrain").unwrap();
+    let (program, _) = parser::parse_dialect("+(.):", &*pbrain);
+    assert_eq!(Err("Cannot translate ProcedureStart(3) at pc=1 to ook".to_string()),
+               translate(&program, &Keywords::ook()));
+    assert_eq!(Err("Cannot translate ProcedureStart(3) at pc=1 to brainfuck".to_string()),
+               translate(&program, &Brainfuck::default()));
+    assert_eq!(Ok("+(.):\n".to_string()), translate(&program, &*pbrain));
+}
+
+#[test]
+fn translate_between_machines() {
+    let program = parser::parse("+.".to_string());
+    let bits = translate_between(&program, &Brainfuck::default(), &Boolfuck).unwrap();
+    assert!(bits.contains(';'), "{:}", bits);
+    let (program, _) = parser::parse_dialect(&bits, &Boolfuck);
+    assert_eq!(Err("Cannot translate the bits of boolfuck to the cells of brainfuck".to_string()),
+               translate_between(&program, &Boolfuck, &Brainfuck::default()));
+    assert_eq!(Err("Cannot translate the bits of boolfuck to the cells of ook".to_string()),
+               convert(&bits, &Boolfuck, &Keywords::ook()));
+    assert_eq!(Ok(bits.clone()), convert(&bits, &Boolfuck, &Boolfuck));
+}
+
+#[test]
+fn convert_between_dialects() {
+    let trivial = Keywords::from_config(TRIVIAL).unwrap();
+    let text = "+++[>++<-]>.";
+    let ook = convert(text, &Brainfuck::default(), &Keywords::ook()).unwrap();
+    let words = convert(&ook, &Keywords::ook(), &trivial).unwrap();
+    assert_eq!("up up up begin right up up left down end right out\n", words);
+    assert_eq!(Ok(format!("{:}\n", text)),
+               convert(&words, &trivial, &Brainfuck::default()));
+}
+
+#[test]
+fn lookup_dialects() {
+    assert_eq!("brainfuck", lookup("bf").unwrap().name());
+    assert_eq!("pbrain", lookup("pbrain").unwrap().name());
+    assert_eq!("brainfork", lookup("brainfork").unwrap().name());
+    assert_eq!("extended", lookup("extended").unwrap().name());
+    assert_eq!("boolfuck", lookup("boolfuck").unwrap().name());
+    assert_eq!("ook", lookup("ook").unwrap().name());
+    assert_eq!("blub", lookup("blub").unwrap().name());
+    assert!(lookup("no such dialect").is_err());
+
+    let fname = env::temp_dir().join("bf_interp_dialect_lookup.cfg");
+    File::create(&fname)
+        .unwrap()
+        .write_all(TRIVIAL.as_bytes())
+        .unwrap();
+    assert_eq!("trivial", lookup(fname.to_str().unwrap()).unwrap().name());
+}
+
+#[test]
+fn dialect_machines() {
+    assert_eq!(Machine::Bits, lookup("boolfuck").unwrap().machine());
+    for name in &["bf", "pbrain", "brainfork", "extended", "ook", "blub"] {
+        assert_eq!(Machine::Cells, lookup(name).unwrap().machine(), "{:}", name);
+    }
+}
+
+#[test]
+fn dialects_with_breakpoints() {
+    let pbrain = lookup("pbrain").unwrap().with_breakpoints().unwrap();
+    assert_eq!(vec![Breakpoint, Call],
+               pbrain.tokenize("#:").into_iter().map(|(instr, _)| instr).collect::<Vec<_>>());
+    assert!(lookup("boolfuck").unwrap().with_breakpoints().is_none());
+    assert!(lookup("ook").unwrap().with_breakpoints().is_none());
+}
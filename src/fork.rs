@@ -0,0 +1,187 @@
+//! Brainfork, multithreaded brainfuck with the fork instruction `Y`,
+//! and its scheduler `fork::run_with`.
+//!
+//! `Y` (`Instruction::Fork`) forks the current thread. All threads share
+//! the tape, each thread has its own pc, data-pointer and pbrain call stack.
+//! The forking thread sets its current cell to `0`, the new thread moves
+//! its data-pointer one to the right and sets that cell to `1`, so the two
+//! threads can tell each other apart, e.g. `Y[child]` only runs the loop in
+//! the new thread. Both continue behind the `Y`.
+//!
+//! The scheduler executes one instruction of one thread at a time and the
+//! `Scheduler` picks the thread: `RoundRobin` takes turns in the order the
+//! threads were created, `Random` picks a thread with a pseudo random
+//! generator seeded by the given seed. Both are deterministic, so the same
+//! program, input and scheduler always interleave the threads the same way.
+//! A thread ends behind the last instruction or at Extended Brainfuck's `@`
+//! (`Instruction::End`), the program ends when all threads ended.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use ast::Program;
+use ast::Instruction::*;
+use interp;
+use tape::{Config, Tape};
+
+/// Maximum number of threads running at the same time.
+/// Forking more threads panics instead of exhausting the memory.
+pub const MAX_THREADS: usize = 1 << 16;
+
+/// Picks the thread executing the next instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scheduler {
+    /// Each thread executes one instruction in turn, the default.
+    #[default]
+    RoundRobin,
+    /// A random thread executes the next instruction,
+    /// the random numbers are generated from the given seed.
+    Random(u64),
+}
+
+impl FromStr for Scheduler {
+    type Err = String;
+
+    /// Parses `round-robin`, `random` (seed `0`) or `random:SEED`.
+    fn from_str(s: &str) -> Result<Scheduler, String> {
+        match s {
+            "round-robin" => Ok(Scheduler::RoundRobin),
+            "random" => Ok(Scheduler::Random(0)),
+            _ if s.starts_with("random:") => {
+                s["random:".len()..]
+                    .parse()
+                    .map(Scheduler::Random)
+                    .map_err(|_| format!("Invalid seed in {:?}", s))
+            }
+            _ => Err(format!("Unknown scheduler {:?}, expected round-robin or random:SEED", s)),
+        }
+    }
+}
+
+impl fmt::Display for Scheduler {
+    /// Writes the scheduler as parsed by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Scheduler::RoundRobin => f.write_str("round-robin"),
+            Scheduler::Random(seed) => write!(f, "random:{:}", seed),
+        }
+    }
+}
+
+/// A xorshift64* pseudo random number generator.
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        // the state must not be zero
+        Random(seed.wrapping_add(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// The state of a thread while another thread runs.
+#[derive(Debug, Clone)]
+struct Thread {
+    pc: usize,
+    dataptr: usize,
+    calls: Vec<usize>,
+}
+
+/// Returns whether `program` forks, i.e. needs `run_with` to run.
+pub fn forks(program: &Program) -> bool {
+    program.instructions.contains(&Fork)
+}
+
+/// Runs the brainfork `program` with `scheduler`.
+/// See `interp::interp` for the meaning of `input` and `output`.
+pub fn run(program: &Program,
+           input: &mut dyn Read,
+           output: &mut dyn Write,
+           scheduler: Scheduler) {
+    run_with(program,
+             &mut Tape::new(&Config::default()),
+             input,
+             output,
+             scheduler);
+}
+
+/// Runs the brainfork `program` with `scheduler` on `tape`, see the module
+/// documentation. The first thread starts at the data-pointer of `tape`,
+/// afterwards the data-pointer is the one of the thread which executed the
+/// last instruction.
+/// Programs without `Fork` run like `interp::interp_with`.
+///
+/// # Panics
+///
+/// Panics like `interp::interp_with` and if more than `MAX_THREADS` threads
+/// run at the same time.
+pub fn run_with(program: &Program,
+                tape: &mut Tape,
+                input: &mut dyn Read,
+                output: &mut dyn Write,
+                scheduler: Scheduler) {
+    let end = program.instructions.len();
+    let mut threads = Vec::new();
+    if end > 0 {
+        threads.push(Thread {
+                         pc: 0,
+                         dataptr: tape.dataptr(),
+                         calls: Vec::new(),
+                     });
+    }
+    let mut random = match scheduler {
+        Scheduler::RoundRobin => None,
+        Scheduler::Random(seed) => Some(Random::new(seed)),
+    };
+    // the next thread of the round robin scheduler
+    let mut next = 0;
+
+    while !threads.is_empty() {
+        let idx = match random {
+            Some(ref mut random) => (random.next() % threads.len() as u64) as usize,
+            None => next % threads.len(),
+        };
+        if threads[idx].pc >= end {
+            threads.remove(idx);
+            next = idx;
+            continue;
+        }
+        let mut child = None;
+        {
+            let thread = &mut threads[idx];
+            tape.set_dataptr(thread.dataptr);
+            tape.swap_calls(&mut thread.calls);
+            thread.pc = match program.instructions[thread.pc] {
+                Fork => {
+                    tape.set(0);
+                    tape.move_right(1);
+                    tape.set(1);
+                    child = Some(Thread {
+                                     pc: thread.pc + 1,
+                                     dataptr: tape.dataptr(),
+                                     calls: tape.calls().to_vec(),
+                                 });
+                    tape.move_left(1);
+                    thread.pc + 1
+                }
+                _ => interp::step(program, thread.pc, tape, input, output),
+            };
+            thread.dataptr = tape.dataptr();
+            tape.swap_calls(&mut thread.calls);
+        }
+        if let Some(child) = child {
+            if threads.len() >= MAX_THREADS {
+                panic!("More than {:} threads", MAX_THREADS);
+            }
+            threads.push(child);
+        }
+        next = idx + 1;
+    }
+}
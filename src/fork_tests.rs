@@ -0,0 +1,126 @@
+//! Tests for the Brainfork scheduler `fork::run_with`.
+
+use ast::Program;
+use ast::Instruction::*;
+use buffer::*;
+use fork::*;
+use interp;
+use parser::{parse_with, Options};
+use tape::{Config, Tape};
+
+fn parse_fork(text: &str) -> Program {
+    let options = Options {
+        fork: true,
+        ..Options::default()
+    };
+    parse_with(text, &options).0
+}
+
+fn execute_and_check(text: &str, scheduler: Scheduler, exp_output: &[u8]) {
+    let mut output = Vec::new();
+    run(&parse_fork(text),
+        &mut IBuffer::from_str(""),
+        &mut output,
+        scheduler);
+    assert_eq!(exp_output, output.as_slice());
+}
+
+#[test]
+fn parse_scheduler() {
+    assert_eq!(Ok(Scheduler::RoundRobin), "round-robin".parse());
+    assert_eq!(Ok(Scheduler::Random(0)), "random".parse());
+    assert_eq!(Ok(Scheduler::Random(42)), "random:42".parse());
+    assert!("random:x".parse::<Scheduler>().is_err());
+    assert!("fifo".parse::<Scheduler>().is_err());
+    assert_eq!("random:42", Scheduler::Random(42).to_string());
+}
+
+#[test]
+fn fork_sets_cells() {
+    let mut tape = Tape::new(&Config::default());
+    tape.inc(5);
+    run_with(&parse_fork("Y"),
+             &mut tape,
+             &mut IBuffer::from_str(""),
+             &mut Vec::new(),
+             Scheduler::RoundRobin);
+    assert_eq!(&[0, 1, 0], &tape.cells()[0..3]);
+}
+
+#[test]
+fn round_robin_interleaves_instructions() {
+    // the child runs first after the fork: it prints cell 1, the parent cell 0
+    execute_and_check("Y+.", Scheduler::RoundRobin, &[2, 1]);
+    // the parent skips the child's loop and prints first
+    execute_and_check("Y[>>+<<-]>>+.", Scheduler::RoundRobin, &[1, 2]);
+}
+
+#[test]
+fn nested_forks() {
+    // the parent and the child fork again, the forks zero the cells of three
+    // threads, the grandchild prints the 1 of its fork first
+    execute_and_check("Y>Y<.", Scheduler::RoundRobin, &[1, 0, 0, 0]);
+}
+
+#[test]
+fn random_is_reproducible() {
+    let program = "Y[>>+<<-]>>>>++++++++[<<++++++++>>-]<<+.";
+    let outputs: Vec<Vec<u8>> = (0..16)
+        .map(|seed| {
+                 let mut output = Vec::new();
+                 run(&parse_fork(program),
+                     &mut IBuffer::from_str(""),
+                     &mut output,
+                     Scheduler::Random(seed));
+                 output
+             })
+        .collect();
+    for (seed, output) in outputs.iter().enumerate() {
+        let mut again = Vec::new();
+        run(&parse_fork(program),
+            &mut IBuffer::from_str(""),
+            &mut again,
+            Scheduler::Random(seed as u64));
+        assert_eq!(*output, again);
+        assert!(*output == b"AB" || *output == b"BA");
+    }
+    // different seeds interleave differently
+    assert!(outputs.iter().any(|output| output == b"AB"));
+    assert!(outputs.iter().any(|output| output == b"BA"));
+}
+
+#[test]
+fn without_fork_like_interp() {
+    let program = parse_fork("++++++[>++++++++<-]>.");
+    assert!(!forks(&program));
+    let mut expected = Vec::new();
+    interp::interp(&program, &mut IBuffer::from_str(""), &mut expected);
+    execute_and_check("++++++[>++++++++<-]>.", Scheduler::Random(3), &expected);
+    execute_and_check("", Scheduler::RoundRobin, b"");
+}
+
+#[test]
+fn threads_call_procedures() {
+    let options = Options {
+        pbrain: true,
+        fork: true,
+        ..Options::default()
+    };
+    // procedures 0 and 1 print the cell right of the caller,
+    // the parent calls 0, the child calls 1 with its own call stack
+    let program = parse_with("(>.<)+(>.<)-Y:", &options).0;
+    assert!(forks(&program));
+    let mut output = Vec::new();
+    run(&program,
+        &mut IBuffer::from_str(""),
+        &mut output,
+        Scheduler::RoundRobin);
+    assert_eq!(vec![0, 1], output);
+}
+
+#[test]
+#[should_panic(expected = "needs the brainfork scheduler")]
+fn engines_reject_fork() {
+    let program = Program { instructions: vec![Fork] };
+    interp::interp(&program, &mut IBuffer::from_str(""), &mut Vec::new());
+}
@@ -0,0 +1,71 @@
+//! Source formatter `format::format` and minifier `format::minify`.
+//!
+//! In contrast to `printer::to_bf_source` both work on the source text,
+//! so the formatter can keep the comments.
+
+use ast::Instruction;
+use ast::Instruction::*;
+use printer::{Options, Printer};
+
+/// Returns the instruction for the brainfuck command `c` or `None`
+/// if `c` is a comment character.
+/// Loop instructions get a dummy target, as only the command matters here.
+fn command(c: char) -> Option<Instruction> {
+    match c {
+        '<' => Some(MoveLeft(1)),
+        '>' => Some(MoveRight(1)),
+        '+' => Some(Inc(1)),
+        '-' => Some(Dec(1)),
+        '.' => Some(Output),
+        ',' => Some(Input),
+        '[' => Some(LoopEntry(0)),
+        ']' => Some(LoopExit(0)),
+        _ => None,
+    }
+}
+
+/// Reformats the brainfuck source `text` with the layout `options`
+/// (see `printer::Options`).
+/// Commands are laid out like `printer::to_bf_source` does.
+/// Comments are kept, each comment line is trimmed and put on a line of its own.
+///
+/// Unbalanced `[` and `]` are formatted as they are.
+pub fn format(text: &str, options: &Options) -> String {
+    let mut printer = Printer::new(*options);
+    let mut comment = String::new();
+    for c in text.chars() {
+        match command(c) {
+            Some(instr) => {
+                printer.comment(&comment);
+                comment.clear();
+                printer.print(instr);
+            }
+            None => comment.push(c),
+        }
+    }
+    printer.comment(&comment);
+    printer.finish()
+}
+
+/// Returns whether the command `b` directly following `a` cancels out `a`.
+fn cancels(a: char, b: char) -> bool {
+    matches!((a, b), ('+', '-') | ('-', '+') | ('<', '>') | ('>', '<'))
+}
+
+/// Minifies the brainfuck source `text`.
+/// All comment characters are removed as well as redundant
+/// pairs of `+-`, `-+`, `<>` and `><`.
+/// Pairs are also removed if they only become adjacent by removing
+/// other pairs, e.g. `+<>-` is removed completely.
+pub fn minify(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars().filter(|c| command(*c).is_some()) {
+        match out.chars().last() {
+            Some(last) if cancels(last, c) => {
+                out.pop();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
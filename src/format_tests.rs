@@ -0,0 +1,70 @@
+//! Tests for the formatter `format::format` and minifier `format::minify`.
+
+use format::{format, minify};
+use parser::parse;
+use printer::{to_bf_source, Options};
+
+const OPTIONS: Options = Options {
+    width: 0,
+    indent: 2,
+};
+
+#[test]
+fn format_without_comments_like_printer() {
+    let text = "++[->+[-]<]  ,.";
+    assert_eq!(to_bf_source(&parse(text.to_string()), &OPTIONS),
+               format(text, &OPTIONS));
+}
+
+#[test]
+fn format_keeps_comments() {
+    let text = "set to two ++\n  loop [ move -> + <  ]  print .";
+    assert_eq!("set to two\n++\nloop\n[\n  move\n  ->+<\n]\nprint\n.\n",
+               format(text, &OPTIONS));
+}
+
+#[test]
+fn format_keeps_comment_lines() {
+    let text = "first line\n\n   second line\n+";
+    assert_eq!("first line\nsecond line\n+\n", format(text, &OPTIONS));
+}
+
+#[test]
+fn format_unbalanced() {
+    assert_eq!("]\n[\n  +\n", format("]  [+", &OPTIONS));
+}
+
+#[test]
+fn format_is_idempotent() {
+    let text = "a[b+[c-]d>e]f";
+    let formatted = format(text, &OPTIONS);
+    assert_eq!(formatted, format(&formatted, &OPTIONS));
+}
+
+#[test]
+fn minify_strips_comments() {
+    assert_eq!("++[->+<].", minify("two ++ loop [ - > + < ] print ."));
+}
+
+#[test]
+fn minify_removes_redundant_pairs() {
+    assert_eq!("+", minify("++-"));
+    assert_eq!(">", minify("<>>"));
+    assert_eq!("", minify("+-<>-+><"));
+}
+
+#[test]
+fn minify_removes_nested_pairs() {
+    assert_eq!(".", minify("+<>-."));
+}
+
+#[test]
+fn minify_keeps_pairs_across_loops() {
+    assert_eq!("+[-]+", minify("+[-]+"));
+    assert_eq!("+[]-", minify("+[]-"));
+}
+
+#[test]
+fn minify_keeps_mixed_pairs() {
+    assert_eq!("+>-<", minify("+>-<"));
+}
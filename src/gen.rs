@@ -0,0 +1,114 @@
+//! Generator `gen::generate` of short brainfuck programs printing a text.
+//!
+//! A generated program sets up a few cells with a multiplier loop, e.g.
+//! `++++++++[>+++++++++>++++++++++++<<-]` sets cell 1 to 72 and cell 2 to
+//! 96, and prints each byte from the cell which is cheapest to reach and to
+//! adjust, keeping the adjusted value for the following bytes.
+//!
+//! The cost of a program is its number of commands. `generate` searches the
+//! loop counters and the cell values derived from the most frequent bytes
+//! for the cheapest program, the program without a loop included.
+
+use std::cmp;
+
+/// Maximum number of cells set up by the multiplier loop.
+pub const MAX_CELLS: usize = 8;
+
+/// Maximum loop counter of the multiplier loop.
+pub const MAX_FACTOR: u32 = 20;
+
+/// Returns a short brainfuck program printing `bytes` on 8 bit cells,
+/// see the module documentation.
+pub fn generate(bytes: &[u8]) -> String {
+    let mut best = print(bytes, 0, &[]);
+    for factor in 2..MAX_FACTOR + 1 {
+        let multipliers = multipliers(bytes, factor);
+        for cells in 1..cmp::min(MAX_CELLS, multipliers.len()) + 1 {
+            let mut chosen = multipliers[..cells].to_vec();
+            chosen.sort_by_key(|&(_, first)| first);
+            let chosen: Vec<u32> = chosen.iter().map(|&(multiplier, _)| multiplier).collect();
+            let program = print(bytes, factor, &chosen);
+            if program.len() < best.len() {
+                best = program;
+            }
+        }
+    }
+    best
+}
+
+/// Returns the multipliers of `factor` closest to the bytes, the most
+/// frequent first, with the index of the first byte needing them.
+fn multipliers(bytes: &[u8], factor: u32) -> Vec<(u32, usize)> {
+    // the frequency and first index of each multiplier
+    let mut counts: Vec<(u32, usize, usize)> = Vec::new();
+    for (idx, &byte) in bytes.iter().enumerate() {
+        let multiplier = (byte as u32 + factor / 2) / factor;
+        if multiplier == 0 {
+            continue;
+        }
+        match counts.iter_mut().find(|entry| entry.0 == multiplier) {
+            Some(entry) => entry.1 += 1,
+            None => counts.push((multiplier, 1, idx)),
+        }
+    }
+    counts.sort_by_key(|&(_, count, first)| (cmp::Reverse(count), first));
+    counts
+        .into_iter()
+        .map(|(multiplier, _, first)| (multiplier, first))
+        .collect()
+}
+
+/// Returns the commands changing the cell `from` to `to`, the shorter way
+/// around the 8 bit wrap.
+fn adjust(from: u8, to: u8) -> String {
+    let up = to.wrapping_sub(from) as usize;
+    if up <= 128 {
+        "+".repeat(up)
+    } else {
+        "-".repeat(256 - up)
+    }
+}
+
+/// Returns the number of commands of `adjust(from, to)`.
+fn adjust_cost(from: u8, to: u8) -> usize {
+    let up = to.wrapping_sub(from) as usize;
+    cmp::min(up, 256 - up)
+}
+
+/// Returns the program printing `bytes`, whose loop with counter `factor`
+/// sets up cells with `multipliers` times `factor` right of the counter.
+/// Without `multipliers` the program has no loop and starts at cell 0.
+fn print(bytes: &[u8], factor: u32, multipliers: &[u32]) -> String {
+    let mut program = String::new();
+    let mut cells = vec![0u8];
+    if !multipliers.is_empty() {
+        program.push_str(&"+".repeat(factor as usize));
+        program.push('[');
+        for &multiplier in multipliers {
+            program.push('>');
+            program.push_str(&"+".repeat(multiplier as usize));
+            cells.push((multiplier * factor) as u8);
+        }
+        program.push_str(&"<".repeat(multipliers.len()));
+        program.push_str("-]");
+    }
+    let mut dataptr = 0;
+    for &byte in bytes {
+        let cost = |cell: usize| {
+            (cmp::max(cell, dataptr) - cmp::min(cell, dataptr)) + adjust_cost(cells[cell], byte)
+        };
+        let cell = (0..cells.len())
+            .min_by_key(|&cell| cost(cell))
+            .expect("At least one cell");
+        if cell > dataptr {
+            program.push_str(&">".repeat(cell - dataptr));
+        } else {
+            program.push_str(&"<".repeat(dataptr - cell));
+        }
+        program.push_str(&adjust(cells[cell], byte));
+        program.push('.');
+        cells[cell] = byte;
+        dataptr = cell;
+    }
+    program
+}
@@ -0,0 +1,59 @@
+//! Tests for the program generator `gen::generate`.
+
+use buffer::*;
+use gen::*;
+use interp;
+use opt;
+use parser;
+
+/// Checks that the generated program prints `bytes` and returns it.
+fn generate_and_check(bytes: &[u8]) -> String {
+    let text = generate(bytes);
+    let program = parser::parse(text.clone());
+    for p in &[opt::optimize(&program), program] {
+        let mut output = Vec::new();
+        interp::interp(p, &mut IBuffer::from_str(""), &mut output);
+        assert_eq!(bytes, output.as_slice(), "{:}", text);
+    }
+    text
+}
+
+#[test]
+fn generate_nothing() {
+    assert_eq!("", generate_and_check(b""));
+}
+
+#[test]
+fn generate_small_bytes_without_loop() {
+    assert_eq!("+++.-.", generate_and_check(&[3, 2]));
+    assert_eq!("-.", generate_and_check(&[255]));
+}
+
+#[test]
+fn generate_with_multiplier_loop() {
+    let text = generate_and_check(b"A");
+    assert!(text.contains('['), "{:}", text);
+    assert!(text.len() < 65, "{:}", text);
+}
+
+#[test]
+fn generate_short_text() {
+    let text = generate_and_check(b"Hello World!\n");
+    // each byte from zero would take more than a thousand commands
+    assert!(text.len() < 150, "{:} commands: {:}", text.len(), text);
+}
+
+#[test]
+fn generate_all_bytes() {
+    let bytes: Vec<u8> = (0..=255).rev().collect();
+    generate_and_check(&bytes);
+}
+
+#[test]
+fn generate_long_text() {
+    let text = "The quick brown fox jumps over the lazy dog. ".repeat(8);
+    let program = generate_and_check(text.as_bytes());
+    // reusing the cell values takes a few commands per byte
+    assert!(program.len() < 6 * text.len(), "{:}", program.len());
+    assert_eq!(program, generate(text.as_bytes()));
+}
@@ -0,0 +1,389 @@
+//! Execution history `history::History` for reverse debugging.
+//!
+//! Brainfuck instructions are cheap to undo: each one changes at most the
+//! data-pointer and the cell at the data-pointer. The history records an
+//! undo log entry with the pc, the data-pointer and the overwritten cell
+//! value per step and logs all input bytes, so re-executing a step after
+//! undoing it reads the same input again. Output is not undone.
+//! pbrain procedure instructions additionally record the procedure
+//! definition or call they change.
+//!
+//! The undo log keeps the last `Limits::steps` steps. Additionally, the
+//! history takes a snapshot of the tape every `Limits::snapshot_interval`
+//! steps, so `rewind` reaches further back by restoring a snapshot and
+//! replaying the logged input from there. The input read before the oldest
+//! snapshot and the start of the undo log is dropped from the log.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use ast::Program;
+use ast::Instruction::*;
+use interp;
+use tape::Tape;
+
+/// Limits of the memory used by a `History`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Number of steps kept in the undo log.
+    pub steps: usize,
+    /// Number of steps between two snapshots of the tape.
+    pub snapshot_interval: u64,
+    /// Number of snapshots kept.
+    pub snapshots: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            steps: 1 << 20,
+            snapshot_interval: 1 << 16,
+            snapshots: 256,
+        }
+    }
+}
+
+/// The change of the pbrain procedures or the storage register by one step.
+#[derive(Debug, Clone, Copy)]
+enum Frame {
+    None,
+    /// The procedure numbered by the old cell value was defined,
+    /// with the given previous definition.
+    Defined(Option<usize>),
+    /// A procedure was called.
+    Called,
+    /// A procedure returned to the given return address.
+    Returned(usize),
+    /// The storage register with the given old value was overwritten.
+    Stored(u32),
+}
+
+/// The undo log entry of one step.
+#[derive(Debug, Clone, Copy)]
+struct Undo {
+    pc: usize,
+    dataptr: usize,
+    old: u32,
+    frame: Frame,
+}
+
+/// The state before step `step`.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    step: u64,
+    pc: usize,
+    tape: Tape,
+    input_pos: usize,
+}
+
+/// The last write of a cell, see `History::last_write`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LastWrite {
+    /// The step which wrote the cell, steps count from `0`.
+    pub step: u64,
+    /// The index of the instruction which wrote the cell.
+    pub pc: usize,
+    /// The value before the write.
+    pub old: u32,
+    /// The value after the write.
+    pub new: u32,
+}
+
+/// An undone step, see `History::undo`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Undone {
+    /// The index of the undone instruction, the next instruction to execute.
+    pub pc: usize,
+    /// The restored data-pointer.
+    pub cell: usize,
+    /// The value of `cell` before undoing the step.
+    pub before: u32,
+    /// The restored value of `cell`.
+    pub after: u32,
+}
+
+/// Reads the logged input first and logs everything read from `inner`.
+struct Replay<'a> {
+    log: &'a mut Vec<Option<u8>>,
+    /// The input position of the first entry of `log`.
+    start: usize,
+    pos: &'a mut usize,
+    inner: &'a mut dyn Read,
+}
+
+impl<'a> Read for Replay<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let byte = match self.log.get(*self.pos - self.start) {
+            Some(byte) => *byte,
+            None => {
+                let mut byte = [0u8; 1];
+                let byte = match self.inner.read(&mut byte)? {
+                    0 => None,
+                    _ => Some(byte[0]),
+                };
+                self.log.push(byte);
+                byte
+            }
+        };
+        *self.pos += 1;
+        match byte {
+            Some(byte) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+/// The execution history of a program, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct History {
+    limits: Limits,
+    undo: VecDeque<Undo>,
+    /// The step of the first entry of `undo`.
+    start: u64,
+    snapshots: Vec<Snapshot>,
+    /// The input position before step `start`.
+    start_input_pos: usize,
+    /// The logged input from position `input_start` on.
+    input: Vec<Option<u8>>,
+    input_start: usize,
+    input_pos: usize,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::new(Limits::default())
+    }
+}
+
+impl History {
+    /// Creates an empty history.
+    pub fn new(limits: Limits) -> History {
+        History {
+            limits,
+            undo: VecDeque::new(),
+            start: 0,
+            snapshots: Vec::new(),
+            start_input_pos: 0,
+            input: Vec::new(),
+            input_start: 0,
+            input_pos: 0,
+        }
+    }
+
+    /// Forgets all steps and the logged input.
+    pub fn clear(&mut self) {
+        *self = History::new(self.limits);
+    }
+
+    /// Returns the number of executed steps.
+    pub fn steps(&self) -> u64 {
+        self.start + self.undo.len() as u64
+    }
+
+    /// Returns the first step `undo` can go back to.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the input position of the first logged input byte,
+    /// the bytes before are not needed anymore and dropped.
+    pub fn input_start(&self) -> usize {
+        self.input_start
+    }
+
+    /// Executes the instruction at `pc` like `interp::step` and records it.
+    /// `Input` reads the logged input first, if the step was undone before.
+    pub fn step(&mut self,
+                program: &Program,
+                pc: usize,
+                tape: &mut Tape,
+                input: &mut dyn Read,
+                output: &mut dyn Write)
+                -> usize {
+        let step = self.steps();
+        if step.is_multiple_of(self.limits.snapshot_interval) &&
+           self.snapshots.last().is_none_or(|s| s.step < step) {
+            self.snapshots.push(Snapshot {
+                                    step,
+                                    pc,
+                                    tape: tape.clone(),
+                                    input_pos: self.input_pos,
+                                });
+            if self.snapshots.len() > self.limits.snapshots {
+                self.snapshots.remove(0);
+            }
+        }
+        let old = tape.cells().get(tape.dataptr()).cloned().unwrap_or(0);
+        let undo = Undo {
+            pc,
+            dataptr: tape.dataptr(),
+            old,
+            frame: match program.instructions[pc] {
+                ProcedureStart(_) => Frame::Defined(tape.procedure(old)),
+                Call => Frame::Called,
+                ProcedureEnd(_) => tape.calls().last().map_or(Frame::None, |&r| Frame::Returned(r)),
+                Store => Frame::Stored(tape.storage()),
+                _ => Frame::None,
+            },
+        };
+        let next = {
+            let mut replay = Replay {
+                log: &mut self.input,
+                start: self.input_start,
+                pos: &mut self.input_pos,
+                inner: input,
+            };
+            interp::step(program, pc, tape, &mut replay, output)
+        };
+        // only record executed steps, `interp::step` panics otherwise
+        self.undo.push_back(undo);
+        if self.undo.len() > self.limits.steps {
+            let first = self.undo.pop_front().expect("A step");
+            if program.instructions[first.pc] == Input {
+                self.start_input_pos += 1;
+            }
+            self.start += 1;
+        }
+        self.trim_input();
+        next
+    }
+
+    /// Drops the logged input neither the undo log nor a snapshot replays.
+    /// The input is only moved once half of the log is unused.
+    fn trim_input(&mut self) {
+        let oldest = self.snapshots
+            .first()
+            .map_or(self.start_input_pos,
+                    |s| cmp::min(s.input_pos, self.start_input_pos));
+        let unused = oldest - self.input_start;
+        if unused > 0 && unused >= self.input.len() / 2 {
+            self.input.drain(..unused);
+            self.input_start = oldest;
+        }
+    }
+
+    /// Undoes the last step on `tape`.
+    /// Returns `None` at the start of the undo log.
+    pub fn undo(&mut self, program: &Program, tape: &mut Tape) -> Option<Undone> {
+        let undo = self.undo.pop_back()?;
+        tape.set_dataptr(undo.dataptr);
+        let before = tape.cells().get(undo.dataptr).cloned().unwrap_or(0);
+        if undo.dataptr < tape.cells().len() {
+            tape.set(undo.old);
+        }
+        if program.instructions[undo.pc] == Input {
+            self.input_pos -= 1;
+        }
+        match undo.frame {
+            Frame::None => (),
+            Frame::Defined(old) => tape.set_procedure(undo.old, old),
+            Frame::Called => {
+                tape.ret();
+            }
+            Frame::Returned(return_address) => tape.push_call(return_address),
+            Frame::Stored(storage) => tape.set_storage(storage),
+        }
+        let step = self.steps();
+        while self.snapshots.last().is_some_and(|s| s.step > step) {
+            self.snapshots.pop();
+        }
+        Some(Undone {
+                 pc: undo.pc,
+                 cell: undo.dataptr,
+                 before,
+                 after: undo.old,
+             })
+    }
+
+    /// Returns to the state before step `step`, which is the state after
+    /// `step` executed steps. `pc` is the next instruction to execute.
+    /// Returns the next instruction to execute at `step`.
+    ///
+    /// Goes back step by step with the undo log. If `step` lies before the
+    /// undo log or replaying from the latest snapshot before `step` is
+    /// shorter, restores that snapshot and replays the logged input instead.
+    pub fn rewind(&mut self,
+                  step: u64,
+                  program: &Program,
+                  mut pc: usize,
+                  tape: &mut Tape)
+                  -> Result<usize, String> {
+        if step > self.steps() {
+            return Err(format!("Step {:} was not executed yet, now at step {:}",
+                               step,
+                               self.steps()));
+        }
+        let snapshot = self.snapshots
+            .iter()
+            .rev()
+            .find(|s| s.step <= step)
+            .cloned();
+        // undo step by step unless replaying from the snapshot is shorter
+        let use_undo = step >= self.start &&
+                       snapshot
+                           .as_ref()
+                           .is_none_or(|s| self.steps() - step <= step - s.step);
+        if use_undo {
+            while self.steps() > step {
+                pc = self.undo(program, tape).expect("Step is in the undo log").pc;
+            }
+            return Ok(pc);
+        }
+        let snapshot =
+            snapshot.ok_or_else(|| format!("Step {:} is not in the history anymore", step))?;
+
+        // restore the snapshot and replay the logged input up to `step`
+        if snapshot.step >= self.start {
+            self.undo.truncate((snapshot.step - self.start) as usize);
+        } else {
+            self.undo.clear();
+            self.start = snapshot.step;
+            self.start_input_pos = snapshot.input_pos;
+        }
+        while self.snapshots.last().is_some_and(|s| s.step > snapshot.step) {
+            self.snapshots.pop();
+        }
+        *tape = snapshot.tape;
+        self.input_pos = snapshot.input_pos;
+        pc = snapshot.pc;
+        while self.steps() < step {
+            pc = self.step(program, pc, tape, &mut io::empty(), &mut io::sink());
+        }
+        Ok(pc)
+    }
+
+    /// Returns the last write of `cell` within the undo log.
+    /// `Inc`, `Dec`, `Input` and the Extended Brainfuck instructions `Load`,
+    /// `ShiftRight`, `ShiftLeft`, `Not`, `Xor`, `And` and `Or` write their
+    /// cell, even if its value does not change.
+    /// `tape` is the current tape.
+    pub fn last_write(&self, program: &Program, tape: &Tape, cell: usize) -> Option<LastWrite> {
+        let writes = |undo: &Undo| {
+            undo.dataptr == cell &&
+            matches!(program.instructions[undo.pc],
+                     Inc(_) | Dec(_) | Input | Load | ShiftRight | ShiftLeft | Not | Xor | And |
+                     Or)
+        };
+        let idx = self.undo.iter().rposition(&writes)?;
+        let undo = self.undo[idx];
+        // the value after the write is overwritten by the next step at the cell
+        let new = self.undo
+            .iter()
+            .skip(idx + 1)
+            .find(|later| later.dataptr == cell)
+            .map_or_else(|| tape.cells()[cell], |later| later.old);
+        Some(LastWrite {
+                 step: self.start + idx as u64,
+                 pc: undo.pc,
+                 old: undo.old,
+                 new,
+             })
+    }
+}
@@ -0,0 +1,230 @@
+//! Tests for the execution history `history::History`.
+
+use std::io;
+
+use buffer::*;
+use ast::Program;
+use history::{History, LastWrite, Limits};
+use parser::{parse, parse_with, Options};
+use tape::{Config, Tape};
+
+/// Executes `steps` steps of `program` recording them in `history`.
+/// Returns the next pc.
+fn run(history: &mut History,
+       program: &Program,
+       tape: &mut Tape,
+       input: &mut IBuffer,
+       steps: usize)
+       -> usize {
+    let mut pc = 0;
+    for _ in 0..steps {
+        pc = history.step(program, pc, tape, input, &mut io::sink());
+    }
+    pc
+}
+
+fn limits(steps: usize, snapshot_interval: u64) -> Limits {
+    Limits {
+        steps,
+        snapshot_interval,
+        snapshots: 4,
+    }
+}
+
+#[test]
+fn undo_restores_tape() {
+    let program = parse("++>+<-".to_string());
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 6);
+    assert_eq!((0, 1), (tape.dataptr(), tape.get()));
+    assert_eq!(6, history.steps());
+    let mut pcs = Vec::new();
+    while let Some(undone) = history.undo(&program, &mut tape) {
+        pcs.push(undone.pc);
+    }
+    assert_eq!(vec![5, 4, 3, 2, 1, 0], pcs);
+    assert_eq!(Tape::new(&Config::default()), tape);
+    assert_eq!(0, history.steps());
+}
+
+#[test]
+fn replay_input_after_undo() {
+    let program = parse(",>,".to_string());
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    let mut input = IBuffer::from_str("ab");
+    run(&mut history, &program, &mut tape, &mut input, 3);
+    history.undo(&program, &mut tape);
+    history.undo(&program, &mut tape);
+    history.undo(&program, &mut tape);
+    // the input is exhausted, the logged input is read again
+    run(&mut history, &program, &mut tape, &mut input, 3);
+    assert_eq!(&[97, 98], &tape.cells()[..2]);
+}
+
+#[test]
+fn undo_log_limit() {
+    let program = parse("++++++".to_string());
+    let mut history = History::new(limits(4, 100));
+    let mut tape = Tape::new(&Config::default());
+    run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 6);
+    assert_eq!((2, 6), (history.start(), history.steps()));
+    for _ in 0..4 {
+        assert!(history.undo(&program, &mut tape).is_some());
+    }
+    assert_eq!(None, history.undo(&program, &mut tape));
+    assert_eq!(2, tape.get());
+}
+
+#[test]
+fn rewind_with_undo_log() {
+    let program = parse("+++++".to_string());
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    let pc = run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 5);
+    assert_eq!(Ok(2), history.rewind(2, &program, pc, &mut tape));
+    assert_eq!(2, tape.get());
+    assert!(history.rewind(3, &program, 2, &mut tape).is_err());
+}
+
+#[test]
+fn rewind_before_undo_log_from_snapshot() {
+    let program = parse(",+>,+>,+".to_string());
+    let mut history = History::new(limits(2, 3));
+    let mut tape = Tape::new(&Config::default());
+    let pc = run(&mut history, &program, &mut tape, &mut IBuffer::from_str("abc"), 8);
+    assert_eq!(6, history.start());
+    // restores the snapshot before step 3 and replays the logged input
+    assert_eq!(Ok(4), history.rewind(4, &program, pc, &mut tape));
+    assert_eq!((1, 98), (tape.dataptr(), tape.get()));
+    assert_eq!(&[98, 98, 0], &tape.cells()[..3]);
+    assert_eq!((3, 4), (history.start(), history.steps()));
+    // executing again reads the logged input
+    let pc = (4..8).fold(4, |pc, _| {
+        history.step(&program, pc, &mut tape, &mut io::empty(), &mut io::sink())
+    });
+    assert_eq!(8, pc);
+    assert_eq!(&[98, 99, 100], &tape.cells()[..3]);
+}
+
+#[test]
+fn input_log_limit() {
+    let program = parse(",[,]".to_string());
+    let text: String = (0..200u8).map(|i| (b'a' + i % 26) as char).collect();
+    let mut history = History::new(limits(8, 16));
+    let mut tape = Tape::new(&Config::default());
+    let pc = run(&mut history, &program, &mut tape, &mut IBuffer::from_str(&text), 300);
+    // the input before the oldest snapshot is dropped
+    assert!(history.input_start() > 100, "{:}", history.input_start());
+    for &step in &[295, 250] {
+        let mut expected = Tape::new(&Config::default());
+        run(&mut History::default(),
+            &program,
+            &mut expected,
+            &mut IBuffer::from_str(&text),
+            step as usize);
+        assert!(history.rewind(step, &program, pc, &mut tape).is_ok());
+        assert_eq!(expected, tape, "{:}", step);
+    }
+}
+
+#[test]
+fn rewind_out_of_history() {
+    let program = parse("++++++++".to_string());
+    let mut history = History::new(Limits {
+                                       steps: 2,
+                                       snapshot_interval: 2,
+                                       snapshots: 1,
+                                   });
+    let mut tape = Tape::new(&Config::default());
+    let pc = run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 8);
+    assert!(history.rewind(5, &program, pc, &mut tape).is_err());
+    assert_eq!(Ok(6), history.rewind(6, &program, pc, &mut tape));
+    assert_eq!(6, tape.get());
+}
+
+#[test]
+fn last_write() {
+    let program = parse("+++>+<-><".to_string());
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 9);
+    assert_eq!(Some(LastWrite {
+                        step: 6,
+                        pc: 6,
+                        old: 3,
+                        new: 2,
+                    }),
+               history.last_write(&program, &tape, 0));
+    assert_eq!(Some(LastWrite {
+                        step: 4,
+                        pc: 4,
+                        old: 0,
+                        new: 1,
+                    }),
+               history.last_write(&program, &tape, 1));
+    assert_eq!(None, history.last_write(&program, &tape, 2));
+}
+
+#[test]
+fn last_write_extended() {
+    let options = Options {
+        extended: true,
+        ..Options::default()
+    };
+    let program = parse_with("+++$>!~.", &options).0;
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 8);
+    assert_eq!(Some(LastWrite {
+                        step: 6,
+                        pc: 6,
+                        old: 3,
+                        new: 252,
+                    }),
+               history.last_write(&program, &tape, 1));
+    // `$` only reads its cell
+    assert_eq!(2, history.last_write(&program, &tape, 0).unwrap().step);
+}
+
+#[test]
+fn undo_procedures() {
+    let options = Options {
+        pbrain: true,
+        ..Options::default()
+    };
+    let program = parse_with("(+)(-)::", &options).0;
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    let pc = run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 5);
+    // returned from the call of the redefined procedure
+    assert_eq!((7, Some(4), &[][..]), (pc, tape.procedure(0), tape.calls()));
+    history.undo(&program, &mut tape);
+    assert_eq!(&[7], tape.calls());
+    history.undo(&program, &mut tape);
+    history.undo(&program, &mut tape);
+    assert_eq!((Some(4), &[][..]), (tape.procedure(0), tape.calls()));
+    history.undo(&program, &mut tape);
+    assert_eq!(Some(1), tape.procedure(0));
+    history.undo(&program, &mut tape);
+    assert_eq!(Tape::new(&Config::default()), tape);
+}
+
+#[test]
+fn undo_store() {
+    let options = Options {
+        extended: true,
+        ..Options::default()
+    };
+    let program = parse_with("++$+$", &options).0;
+    let mut history = History::default();
+    let mut tape = Tape::new(&Config::default());
+    run(&mut history, &program, &mut tape, &mut IBuffer::from_str(""), 5);
+    assert_eq!(3, tape.storage());
+    history.undo(&program, &mut tape);
+    assert_eq!(2, tape.storage());
+    history.undo(&program, &mut tape);
+    history.undo(&program, &mut tape);
+    assert_eq!(0, tape.storage());
+}
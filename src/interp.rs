@@ -1,49 +1,140 @@
 //! Interpreter of brainfuck ast.
 
+use std::fmt;
 use std::io::{Read, Write};
+use std::str::FromStr;
 
 use ast::Program;
 use ast::Instruction::*;
+use bytecode;
+use tape::{Config, Tape};
+use threaded;
 
 /// Interprets the brainfuck `program`.
 /// Reads (instruction `Input` aka `,`) from input.
 /// Writes (instruction `Output` aka `.`) to output.
-/// The size of the band is in general undefined.
-/// Currentl,y its 30000 bytes.
-pub fn interp(program: &Program, input: &mut Read, output: &mut Write) {
-    let mut memory = vec![0u8; 30000];
+/// The tape has the default configuration (see `tape::Config`):
+/// 30000 byte cells.
+pub fn interp(program: &Program, input: &mut dyn Read, output: &mut dyn Write) {
+    interp_with(program, &mut Tape::new(&Config::default()), input, output);
+}
 
+/// Interprets the brainfuck `program` on the given `tape`.
+/// The program starts with the cells and the data-pointer of `tape`
+/// and leaves its final state in `tape`.
+pub fn interp_with(program: &Program, tape: &mut Tape, input: &mut dyn Read, output: &mut dyn Write) {
     let mut pc: usize = 0;
-    let mut dataptr: usize = 0;
 
-    fn get_char(input: &mut Read) -> u8 {
-        let mut buf = [0u8; 1];
-        match input.read(&mut buf) {
-            Err(_) => panic!("Cannot read from stdin"),
-            Ok(_) => buf[0],
-        }
+    while pc < program.instructions.len() {
+        pc = step(program, pc, tape, input, output);
     }
+}
 
-    while pc < program.instructions.len() {
-        match program.instructions[pc] {
-            MoveLeft(offset) => dataptr -= offset,
-            MoveRight(offset) => dataptr += offset,
-            Inc(increment) => memory[dataptr] = memory[dataptr].wrapping_add(increment),
-            Dec(decrement) => memory[dataptr] = memory[dataptr].wrapping_sub(decrement),
-            Output => write!(output, "{:}", memory[dataptr] as char).expect("Output error"),
-            Input => memory[dataptr] = get_char(input),
-            LoopEntry(target) => {
-                if 0 == memory[dataptr] {
-                    pc = target;
-                }
+/// Executes the single instruction at `pc` of `program` on `tape`
+/// and returns the index of the next instruction to execute.
+/// The program has finished, if the returned index is behind its last instruction.
+#[inline]
+pub fn step(program: &Program,
+            pc: usize,
+            tape: &mut Tape,
+            input: &mut dyn Read,
+            output: &mut dyn Write)
+            -> usize {
+    match program.instructions[pc] {
+        MoveLeft(offset) => tape.move_left(offset),
+        MoveRight(offset) => tape.move_right(offset),
+        Inc(increment) => tape.inc(increment),
+        Dec(decrement) => tape.dec(decrement),
+        Output => tape.write(output),
+        Input => tape.read(input),
+        LoopEntry(target) => {
+            if tape.is_zero() {
+                return target + 1;
             }
-            LoopExit(target) => {
-                if 0 != memory[dataptr] {
-                    pc = target;
-                }
+        }
+        LoopExit(target) => {
+            if !tape.is_zero() {
+                return target + 1;
             }
-            // ref c => panic!("Unknown instruction {:?} at pc={:}", c, pc),
         }
-        pc += 1;
+        Breakpoint => (),
+        ProcedureStart(end) => {
+            tape.define(pc + 1);
+            return end + 1;
+        }
+        ProcedureEnd(_) => return tape.ret(),
+        Call => return tape.call(pc + 1),
+        Fork => panic!("Fork at pc={:} needs the brainfork scheduler `fork::run_with`", pc),
+        End => return program.instructions.len(),
+        Store => tape.store(),
+        Load => tape.load(),
+        ShiftRight => tape.shift_right(),
+        ShiftLeft => tape.shift_left(),
+        Not => tape.not(),
+        Xor => tape.xor(),
+        And => tape.and(),
+        Or => tape.or(),
+    }
+    pc + 1
+}
+
+/// The available interpreter engines.
+/// All engines behave the same, they only differ in their runtime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Engine {
+    /// `interp::interp`: matches on each instruction at runtime.
+    Match,
+    /// `threaded::compile`: compiles the program into closures first.
+    Threaded,
+    /// `bytecode::encode`: encodes the program into compact bytecode first.
+    Bytecode,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    /// Parses the lower case engine name, e.g. `threaded`.
+    fn from_str(s: &str) -> Result<Engine, String> {
+        match s {
+            "match" => Ok(Engine::Match),
+            "threaded" => Ok(Engine::Threaded),
+            "bytecode" => Ok(Engine::Bytecode),
+            _ => Err(format!("Unknown engine {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    /// Writes the lower case engine name as parsed by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+                        Engine::Match => "match",
+                        Engine::Threaded => "threaded",
+                        Engine::Bytecode => "bytecode",
+                    })
+    }
+}
+
+/// Interprets the brainfuck `program` with the given `engine`.
+/// See `interp::interp` for the meaning of `input` and `output`.
+pub fn run(engine: Engine, program: &Program, input: &mut dyn Read, output: &mut dyn Write) {
+    run_with(engine,
+             program,
+             &mut Tape::new(&Config::default()),
+             input,
+             output);
+}
+
+/// Interprets the brainfuck `program` with the given `engine` on `tape`.
+/// See `interp::interp_with` for the meaning of `tape`.
+pub fn run_with(engine: Engine,
+                program: &Program,
+                tape: &mut Tape,
+                input: &mut dyn Read,
+                output: &mut dyn Write) {
+    match engine {
+        Engine::Match => interp_with(program, tape, input, output),
+        Engine::Threaded => threaded::compile(program).run_with(tape, input, output),
+        Engine::Bytecode => bytecode::encode(program).run_with(tape, input, output),
     }
 }
@@ -202,3 +202,134 @@ fn loop_find_zero_right() {
                       "",
                       &[0, 9, 0]);
 }
+
+#[test]
+fn skip_breakpoint() {
+    execute_and_check(&[Inc(1), Breakpoint, Output], "", &[1]);
+}
+
+#[test]
+fn engine_names() {
+    use interp::Engine;
+    for engine in &[Engine::Match, Engine::Threaded, Engine::Bytecode] {
+        assert_eq!(Ok(*engine), engine.to_string().parse());
+    }
+    assert_eq!("threaded", Engine::Threaded.to_string());
+}
+
+/// Runs the pbrain `text` on all engines, unoptimized and optimized.
+fn execute_pbrain_and_check(text: &str, exp_output: &[u8]) {
+    use interp::Engine;
+    use opt;
+    use parser::{self, Options};
+
+    let options = Options {
+        pbrain: true,
+        ..Options::default()
+    };
+    let program = parser::parse_with(text, &options).0;
+    for p in &[opt::optimize(&program), program] {
+        for engine in &[Engine::Match, Engine::Threaded, Engine::Bytecode] {
+            let mut output = Vec::new();
+            interp::run(*engine, p, &mut IBuffer::from_str(""), &mut output);
+            assert_eq!(exp_output, output.as_slice(), "{:?}", engine);
+        }
+    }
+}
+
+#[test]
+fn call_procedures() {
+    // procedure 0 prints and increments cell 1, procedure 1 calls it twice
+    execute_pbrain_and_check("(>.+<)>+++<::+(-::+):", &[3, 4, 5, 6]);
+}
+
+#[test]
+fn redefine_procedure() {
+    execute_pbrain_and_check("(>+<)(>++<):>.", &[2]);
+}
+
+#[test]
+fn recursive_procedure() {
+    // procedure 0 counts cell 1 down to 0 by calling itself
+    execute_pbrain_and_check("(>.[-<:>]<)>+++<:", &[3, 2, 1, 0]);
+}
+
+#[test]
+fn deep_recursion_in_nested_loops() {
+    use interp::Engine;
+    use opt;
+    use parser::{self, Options};
+    use tape::{CellWidth, Config, Tape};
+
+    // procedure 0 calls itself inside 40 nested loops until cell 1 is 0,
+    // so 4090 calls nest, each inside the loops of the calling procedure
+    let text = ["(>", &"[".repeat(40), "-<:>", &"]".repeat(40), "<)>", &"+".repeat(4090), "<:"]
+        .concat();
+    let options = Options {
+        pbrain: true,
+        ..Options::default()
+    };
+    let program = parser::parse_with(&text, &options).0;
+    let config = Config {
+        cell_width: CellWidth::Bits16,
+        ..Config::default()
+    };
+    for p in &[opt::optimize(&program), program] {
+        for engine in &[Engine::Match, Engine::Threaded, Engine::Bytecode] {
+            let mut tape = Tape::new(&config);
+            interp::run_with(*engine, p, &mut tape, &mut IBuffer::from_str(""), &mut Vec::new());
+            assert_eq!(&[0, 0], &tape.cells()[..2], "{:?}", engine);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Undefined procedure 1")]
+fn call_undefined_procedure() {
+    execute_and_check(&[ProcedureStart(1), ProcedureEnd(0), Inc(1), Call], "", &[]);
+}
+
+#[test]
+#[should_panic(expected = "Procedure calls nested deeper than")]
+fn endless_recursion() {
+    execute_and_check(&[ProcedureStart(2), Call, ProcedureEnd(0), Call], "", &[]);
+}
+
+fn execute_extended_and_check(text: &str, exp_output: &[u8]) {
+    use interp::Engine;
+    use opt;
+    use parser::{self, Options};
+
+    let options = Options {
+        pbrain: true,
+        extended: true,
+        ..Options::default()
+    };
+    let program = parser::parse_with(text, &options).0;
+    for p in &[opt::optimize(&program), program] {
+        for engine in &[Engine::Match, Engine::Threaded, Engine::Bytecode] {
+            let mut output = Vec::new();
+            interp::run(*engine, p, &mut IBuffer::from_str(""), &mut output);
+            assert_eq!(exp_output, output.as_slice(), "{:?}", engine);
+        }
+    }
+}
+
+#[test]
+fn storage_register() {
+    execute_extended_and_check("++++++$>+++!.", &[6]);
+    execute_extended_and_check("++++++$>+++^.>+++&.>+++|.", &[5, 2, 7]);
+}
+
+#[test]
+fn bit_shifts_and_not() {
+    execute_extended_and_check("+++}.>+++{.>~.", &[1, 6, 255]);
+}
+
+#[test]
+fn end_program() {
+    execute_extended_and_check("+.@+.", &[1]);
+    execute_extended_and_check("+[.[@]]+.", &[1]);
+    // ends inside a loop of a procedure
+    execute_extended_and_check("(>[.@]<)>+<:>.", &[1]);
+}
@@ -0,0 +1,881 @@
+//! A tiny structured language, compiled to brainfuck by `lang::compile`.
+//!
+//! ```text
+//! # prints the letters from A up to the letter read
+//! var last;
+//! read last;
+//! var letter = 'A';
+//! while letter <= last {
+//!     print letter;
+//!     letter = letter + 1;
+//! }
+//! if letter == 'A' { print "none"; } else { print "\n"; }
+//! ```
+//! * `var NAME = EXPR;` declares a variable, `var NAME;` one set to `0`.
+//!   Variables are declared before their use and never twice.
+//! * `NAME = EXPR;` assigns a variable.
+//! * `if EXPR { ... } else { ... }`, `else if` and `while EXPR { ... }` run
+//!   their blocks while the expression is not `0`.
+//! * `print EXPR, "TEXT", ...;` outputs the value of each expression as a
+//!   byte and each text, `read NAME;` reads a byte into a variable.
+//! * Expressions are unsigned numbers, characters like `'a'` or `'\n'`,
+//!   variables, `( )`, the unary operators `-` and `!`, and the binary
+//!   operators `* / %`, `+ -`, `== != < <= > >=`, `&&` and `||` in the order
+//!   of precedence. Comparisons and logical operators yield `0` or `1`,
+//!   division by `0` yields `0` and leaves the remainder at the dividend.
+//! * `#` starts a comment up to the end of the line.
+//!
+//! Values are cells, so arithmetic wraps at the cell width of the tape.
+//! Each variable gets a cell of its own at the start of the tape, the
+//! temporary values of expressions live in the cells behind the variables.
+//! The instructions of each statement and expression map to its source text
+//! in the `SourceMap`, so the debugger steps through the source lines.
+
+use std::collections::HashMap;
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+use span::{Position, SourceMap, Span};
+
+/// The file extension of sources in the language, e.g. `countdown.bfl`.
+pub const EXTENSION: &str = "bfl";
+
+/// Symbols of the language, longer symbols first.
+const SYMBOLS: [&str; 21] = ["==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">",
+                             "!", "=", "(", ")", "{", "}", ";", ","];
+
+/// Keywords, which are no variable names.
+const KEYWORDS: [&str; 6] = ["var", "if", "else", "while", "print", "read"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u32),
+    Text(Vec<u8>),
+    Symbol(&'static str),
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug)]
+enum ExprKind {
+    Number(u32),
+    /// The cell of the variable.
+    Var(usize),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug)]
+struct Expr {
+    kind: ExprKind,
+    span: Span,
+}
+
+#[derive(Debug)]
+enum StmtKind {
+    Assign(usize, Expr),
+    PrintText(Vec<u8>),
+    PrintValue(Expr),
+    Read(usize),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+}
+
+#[derive(Debug)]
+struct Stmt {
+    kind: StmtKind,
+    span: Span,
+}
+
+/// Compiles the `text` of a source to a brainfuck program and the source
+/// position of each instruction, see the module documentation.
+/// Errors are formatted as `LINE:COLUMN: message`.
+pub fn compile(text: &str) -> Result<(Program, SourceMap), String> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        next: 0,
+        vars: HashMap::new(),
+    };
+    let mut stmts = Vec::new();
+    while parser.peek() != &Token::End {
+        parser.statement(&mut stmts)?;
+    }
+    let mut compiler = Compiler {
+        instructions: Vec::new(),
+        spans: Vec::new(),
+        span: Span::at(Position { line: 1, column: 1 }),
+        dataptr: 0,
+        temps: parser.vars.len(),
+        loops: Vec::new(),
+    };
+    compiler.block(&stmts);
+    Ok((Program { instructions: compiler.instructions }, SourceMap::new(compiler.spans)))
+}
+
+fn error(position: Position, msg: String) -> String {
+    format!("{:}: {:}", position, msg)
+}
+
+/// Splits `text` into tokens with their spans, the last token is `End`.
+fn tokenize(text: &str) -> Result<Vec<(Token, Span)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = Vec::new();
+    let mut position = Position { line: 1, column: 1 };
+    for c in text.chars() {
+        chars.push((c, position));
+        if c == '\n' {
+            position = Position {
+                line: position.line + 1,
+                column: 1,
+            };
+        } else {
+            position.column += 1;
+        }
+    }
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (c, start) = chars[idx];
+        let begin = idx;
+        if c.is_whitespace() {
+            idx += 1;
+            continue;
+        } else if c == '#' {
+            while idx < chars.len() && chars[idx].0 != '\n' {
+                idx += 1;
+            }
+            continue;
+        }
+        let token = if c.is_ascii_alphabetic() || c == '_' {
+            while chars
+                      .get(idx)
+                      .is_some_and(|&(c, _)| c.is_ascii_alphanumeric() || c == '_') {
+                idx += 1;
+            }
+            Token::Ident(chars[begin..idx].iter().map(|&(c, _)| c).collect())
+        } else if c.is_ascii_digit() {
+            while idx < chars.len() && chars[idx].0.is_ascii_digit() {
+                idx += 1;
+            }
+            let digits: String = chars[begin..idx].iter().map(|&(c, _)| c).collect();
+            let number = digits
+                .parse()
+                .map_err(|_| error(start, format!("Number {:} is too large", digits)))?;
+            Token::Number(number)
+        } else if c == '"' || c == '\'' {
+            idx += 1;
+            let mut bytes = Vec::new();
+            loop {
+                let (c, position) = match chars.get(idx) {
+                    Some(&(c, position)) if c != '\n' => (c, position),
+                    _ => return Err(error(start, format!("Missing closing {:}", c))),
+                };
+                idx += 1;
+                let c = if c == chars[begin].0 {
+                    break;
+                } else if c == '\\' {
+                    idx += 1;
+                    match chars.get(idx - 1).map(|&(c, _)| c) {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(c @ '\\') | Some(c @ '"') | Some(c @ '\'') => c,
+                        _ => return Err(error(position, "Unknown escape sequence".to_string())),
+                    }
+                } else {
+                    c
+                };
+                let mut buf = [0u8; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            if c == '"' {
+                Token::Text(bytes)
+            } else if bytes.len() == 1 {
+                Token::Number(bytes[0] as u32)
+            } else {
+                return Err(error(start, "Expected a single byte character".to_string()));
+            }
+        } else {
+            let rest: String = chars[idx..].iter().take(2).map(|&(c, _)| c).collect();
+            match SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+                Some(symbol) => {
+                    idx += symbol.len();
+                    Token::Symbol(symbol)
+                }
+                None => return Err(error(start, format!("Unexpected {:?}", c))),
+            }
+        };
+        let span = Span {
+            start,
+            end: chars[idx - 1].1,
+        };
+        tokens.push((token, span));
+    }
+    tokens.push((Token::End, Span::at(position)));
+    Ok(tokens)
+}
+
+/// A recursive descent parser resolving the variables to their cells.
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    next: usize,
+    vars: HashMap<String, usize>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.next].1
+    }
+
+    /// Returns the span from `start` up to the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.merge(&self.tokens[self.next - 1].1)
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].0.clone();
+        if token != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    /// Consumes `symbol` if it is next.
+    fn accept(&mut self, symbol: &str) -> bool {
+        match *self.peek() {
+            Token::Symbol(next) if next == symbol => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{:}'", symbol)))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        let found = match *self.peek() {
+            Token::Ident(ref name) => name.clone(),
+            Token::Number(number) => number.to_string(),
+            Token::Text(_) => "text".to_string(),
+            Token::Symbol(symbol) => format!("'{:}'", symbol),
+            Token::End => "end of file".to_string(),
+        };
+        error(self.span().start, format!("Expected {:}, got {:}", expected, found))
+    }
+
+    /// Consumes the keyword `keyword` if it is next.
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        match *self.peek() {
+            Token::Ident(ref name) if name == keyword => (),
+            _ => return false,
+        }
+        self.advance();
+        true
+    }
+
+    /// Parses a variable name, returns the name and its span.
+    fn name(&mut self) -> Result<(String, Span), String> {
+        let span = self.span();
+        match *self.peek() {
+            Token::Ident(ref name) if !KEYWORDS.contains(&name.as_str()) => (),
+            _ => return Err(self.unexpected("a variable")),
+        }
+        match self.advance() {
+            Token::Ident(name) => Ok((name, span)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Parses the name of a declared variable, returns its cell.
+    fn var(&mut self) -> Result<usize, String> {
+        let (name, span) = self.name()?;
+        self.vars
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| error(span.start, format!("Undeclared variable {:}", name)))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, String> {
+        self.expect("{")?;
+        let mut stmts = Vec::new();
+        while !self.accept("}") {
+            if self.peek() == &Token::End {
+                return Err(self.unexpected("'}'"));
+            }
+            self.statement(&mut stmts)?;
+        }
+        Ok(stmts)
+    }
+
+    /// Parses a statement and appends it to `stmts`,
+    /// a `print` with several arguments as several statements.
+    fn statement(&mut self, stmts: &mut Vec<Stmt>) -> Result<(), String> {
+        let start = self.span();
+        if self.accept_keyword("var") {
+            let (name, span) = self.name()?;
+            let value = if self.accept("=") {
+                self.expr()?
+            } else {
+                Expr {
+                    kind: ExprKind::Number(0),
+                    span,
+                }
+            };
+            self.expect(";")?;
+            if self.vars.contains_key(&name) {
+                return Err(error(span.start, format!("Variable {:} is already declared", name)));
+            }
+            let cell = self.vars.len();
+            self.vars.insert(name, cell);
+            stmts.push(Stmt {
+                           kind: StmtKind::Assign(cell, value),
+                           span: self.span_from(start),
+                       });
+        } else if self.accept_keyword("if") {
+            let cond = self.expr()?;
+            let span = self.span_from(start);
+            let then = self.block()?;
+            let mut otherwise = Vec::new();
+            if self.accept_keyword("else") {
+                if let Token::Ident(ref name) = *self.peek() {
+                    if name == "if" {
+                        self.statement(&mut otherwise)?;
+                    }
+                }
+                if otherwise.is_empty() {
+                    otherwise = self.block()?;
+                }
+            }
+            stmts.push(Stmt {
+                           kind: StmtKind::If(cond, then, otherwise),
+                           span,
+                       });
+        } else if self.accept_keyword("while") {
+            let cond = self.expr()?;
+            let span = self.span_from(start);
+            let body = self.block()?;
+            stmts.push(Stmt {
+                           kind: StmtKind::While(cond, body),
+                           span,
+                       });
+        } else if self.accept_keyword("print") {
+            let mut args = Vec::new();
+            loop {
+                let kind = match *self.peek() {
+                    Token::Text(ref text) => StmtKind::PrintText(text.clone()),
+                    _ => StmtKind::PrintValue(self.expr()?),
+                };
+                if let StmtKind::PrintText(_) = kind {
+                    self.advance();
+                }
+                args.push(kind);
+                if !self.accept(",") {
+                    break;
+                }
+            }
+            self.expect(";")?;
+            let span = self.span_from(start);
+            stmts.extend(args.into_iter().map(|kind| Stmt { kind, span }));
+        } else if self.accept_keyword("read") {
+            let cell = self.var()?;
+            self.expect(";")?;
+            stmts.push(Stmt {
+                           kind: StmtKind::Read(cell),
+                           span: self.span_from(start),
+                       });
+        } else {
+            let cell = self.var()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            stmts.push(Stmt {
+                           kind: StmtKind::Assign(cell, value),
+                           span: self.span_from(start),
+                       });
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.binary(0)
+    }
+
+    /// Parses the binary operators of precedence `level` and above.
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[(&str, Op)]; 5] = [&[("||", Op::Or)],
+                                            &[("&&", Op::And)],
+                                            &[("==", Op::Eq),
+                                              ("!=", Op::Ne),
+                                              ("<=", Op::Le),
+                                              (">=", Op::Ge),
+                                              ("<", Op::Lt),
+                                              (">", Op::Gt)],
+                                            &[("+", Op::Add), ("-", Op::Sub)],
+                                            &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Mod)]];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        'operators: loop {
+            for &(symbol, op) in LEVELS[level] {
+                if self.accept(symbol) {
+                    let right = self.binary(level + 1)?;
+                    let span = left.span.merge(&right.span);
+                    left = Expr {
+                        kind: ExprKind::Binary(op, Box::new(left), Box::new(right)),
+                        span,
+                    };
+                    continue 'operators;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let start = self.span();
+        if self.accept("!") {
+            let operand = self.unary()?;
+            Ok(Expr {
+                   span: start.merge(&operand.span),
+                   kind: ExprKind::Not(Box::new(operand)),
+               })
+        } else if self.accept("-") {
+            let operand = self.unary()?;
+            let zero = Expr {
+                kind: ExprKind::Number(0),
+                span: start,
+            };
+            Ok(Expr {
+                   span: start.merge(&operand.span),
+                   kind: ExprKind::Binary(Op::Sub, Box::new(zero), Box::new(operand)),
+               })
+        } else if self.accept("(") {
+            let expr = self.expr()?;
+            self.expect(")")?;
+            Ok(expr)
+        } else if let Token::Number(number) = *self.peek() {
+            self.advance();
+            Ok(Expr {
+                   kind: ExprKind::Number(number),
+                   span: start,
+               })
+        } else {
+            let cell = self.var()?;
+            Ok(Expr {
+                   kind: ExprKind::Var(cell),
+                   span: start,
+               })
+        }
+    }
+}
+
+/// Generates the instructions, tracking the data-pointer at compile time.
+/// Every loop ends at the cell it started at, so the data-pointer is the
+/// same after each loop, however often it ran.
+///
+/// Temporary cells are allocated and released like a stack behind the
+/// variables. They are `0` when allocated and the code using a temporary
+/// cell sets it back to `0` before releasing it.
+struct Compiler {
+    instructions: Vec<Instruction>,
+    spans: Vec<Span>,
+    /// The span of the instructions emitted next.
+    span: Span,
+    dataptr: usize,
+    /// The next free temporary cell.
+    temps: usize,
+    /// The indices of the open `LoopEntry`s.
+    loops: Vec<usize>,
+}
+
+impl Compiler {
+    fn emit(&mut self, instr: Instruction) {
+        self.instructions.push(instr);
+        self.spans.push(self.span);
+    }
+
+    fn goto(&mut self, cell: usize) {
+        if cell > self.dataptr {
+            self.emit(MoveRight(cell - self.dataptr));
+        } else if cell < self.dataptr {
+            self.emit(MoveLeft(self.dataptr - cell));
+        }
+        self.dataptr = cell;
+    }
+
+    /// Adds `value` to `cell`.
+    fn inc(&mut self, cell: usize, mut value: u32) {
+        self.goto(cell);
+        while value > 0 {
+            let step = value.min(255);
+            self.emit(Inc(step as u8));
+            value -= step;
+        }
+    }
+
+    /// Subtracts `value` from `cell`.
+    fn dec(&mut self, cell: usize, mut value: u32) {
+        self.goto(cell);
+        while value > 0 {
+            let step = value.min(255);
+            self.emit(Dec(step as u8));
+            value -= step;
+        }
+    }
+
+    /// Starts a loop running while `cell` is not `0`.
+    fn open(&mut self, cell: usize) {
+        self.goto(cell);
+        self.loops.push(self.instructions.len());
+        self.emit(LoopEntry(0));
+    }
+
+    /// Ends the loop started by `open(cell)`.
+    fn close(&mut self, cell: usize) {
+        self.goto(cell);
+        let entry = self.loops.pop().expect("An open loop");
+        self.instructions[entry] = LoopEntry(self.instructions.len());
+        self.emit(LoopExit(entry));
+    }
+
+    fn clear(&mut self, cell: usize) {
+        self.open(cell);
+        self.dec(cell, 1);
+        self.close(cell);
+    }
+
+    fn temp(&mut self) -> usize {
+        self.temps += 1;
+        self.temps - 1
+    }
+
+    fn release(&mut self, cell: usize) {
+        assert_eq!(cell + 1, self.temps, "Temporary cells are released in reverse order");
+        self.temps -= 1;
+    }
+
+    /// Adds `src` to each cell of `dsts`, or subtracts it if the flag is
+    /// `false`, and sets `src` to `0`.
+    fn transfer(&mut self, src: usize, dsts: &[(usize, bool)]) {
+        self.open(src);
+        self.dec(src, 1);
+        for &(dst, add) in dsts {
+            if add {
+                self.inc(dst, 1);
+            } else {
+                self.dec(dst, 1);
+            }
+        }
+        self.close(src);
+    }
+
+    /// Adds `src` to `dst`, keeping `src`.
+    fn copy(&mut self, src: usize, dst: usize) {
+        let temp = self.temp();
+        self.transfer(src, &[(dst, true), (temp, true)]);
+        self.transfer(temp, &[(src, true)]);
+        self.release(temp);
+    }
+
+    /// Sets `cell` to `1` if it is not `0`.
+    fn normalize(&mut self, cell: usize) {
+        let temp = self.temp();
+        self.open(cell);
+        self.clear(cell);
+        self.inc(temp, 1);
+        self.close(cell);
+        self.transfer(temp, &[(cell, true)]);
+        self.release(temp);
+    }
+
+    /// Sets `cell` to `1` if it is `0` and to `0` otherwise.
+    fn not(&mut self, cell: usize) {
+        let temp = self.temp();
+        self.inc(temp, 1);
+        self.open(cell);
+        self.clear(cell);
+        self.dec(temp, 1);
+        self.close(cell);
+        self.transfer(temp, &[(cell, true)]);
+        self.release(temp);
+    }
+
+    /// Runs `then` if `cond` is not `0` and `otherwise` else,
+    /// `cond` is `0` afterwards.
+    fn if_else<T, O>(&mut self, cond: usize, then: T, otherwise: O)
+        where T: FnOnce(&mut Compiler),
+              O: FnOnce(&mut Compiler)
+    {
+        let flag = self.temp();
+        self.inc(flag, 1);
+        self.open(cond);
+        self.clear(cond);
+        then(self);
+        self.dec(flag, 1);
+        self.close(cond);
+        self.open(flag);
+        self.dec(flag, 1);
+        otherwise(self);
+        self.close(flag);
+        self.release(flag);
+    }
+
+    /// Sets `result`, which is `left` or `right`, to `1` if `left < right`
+    /// and to `0` otherwise, the other cell to `0`.
+    /// Both are decremented until `right` is `0` or `left` is `0` before.
+    fn less(&mut self, left: usize, right: usize, result: usize) {
+        let less = self.temp();
+        self.open(right);
+        let left_copy = self.temp();
+        self.copy(left, left_copy);
+        self.if_else(left_copy,
+                     |this| {
+                         this.dec(left, 1);
+                         this.dec(right, 1);
+                     },
+                     |this| {
+                         this.inc(less, 1);
+                         this.clear(right);
+                     });
+        self.release(left_copy);
+        self.close(right);
+        self.clear(left);
+        self.transfer(less, &[(result, true)]);
+        self.release(less);
+    }
+
+    /// Sets the `0` cell `flag` to whether `divisor` fits into `dividend`,
+    /// i.e. `divisor != 0 && !(dividend < divisor)`.
+    fn fits(&mut self, dividend: usize, divisor: usize, flag: usize) {
+        self.copy(divisor, flag);
+        let left = self.temp();
+        self.copy(dividend, left);
+        let right = self.temp();
+        self.copy(divisor, right);
+        self.less(left, right, left);
+        self.release(right);
+        self.not(left);
+        self.binary(Op::And, flag, left);
+        self.release(left);
+    }
+
+    /// Divides `dividend` by `divisor`, leaves the quotient or, with
+    /// `remainder`, the remainder in `dividend` and sets `divisor` to `0`.
+    fn divide(&mut self, dividend: usize, divisor: usize, remainder: bool) {
+        let quotient = self.temp();
+        let flag = self.temp();
+        self.fits(dividend, divisor, flag);
+        self.open(flag);
+        self.clear(flag);
+        let temp = self.temp();
+        self.copy(divisor, temp);
+        self.transfer(temp, &[(dividend, false)]);
+        self.release(temp);
+        self.inc(quotient, 1);
+        self.fits(dividend, divisor, flag);
+        self.close(flag);
+        self.release(flag);
+        self.clear(divisor);
+        if remainder {
+            self.clear(quotient);
+        } else {
+            self.clear(dividend);
+            self.transfer(quotient, &[(dividend, true)]);
+        }
+        self.release(quotient);
+    }
+
+    /// Sets `left` to `left op right` and `right` to `0`.
+    fn binary(&mut self, op: Op, left: usize, right: usize) {
+        match op {
+            Op::Add => self.transfer(right, &[(left, true)]),
+            Op::Sub => self.transfer(right, &[(left, false)]),
+            Op::Mul => {
+                let count = self.temp();
+                self.transfer(left, &[(count, true)]);
+                self.open(count);
+                self.dec(count, 1);
+                self.copy(right, left);
+                self.close(count);
+                self.release(count);
+                self.clear(right);
+            }
+            Op::Div => self.divide(left, right, false),
+            Op::Mod => self.divide(left, right, true),
+            Op::Eq => {
+                self.binary(Op::Sub, left, right);
+                self.not(left);
+            }
+            Op::Ne => {
+                self.binary(Op::Sub, left, right);
+                self.normalize(left);
+            }
+            Op::Lt => self.less(left, right, left),
+            Op::Gt => self.less(right, left, left),
+            Op::Le => {
+                self.less(right, left, left);
+                self.not(left);
+            }
+            Op::Ge => {
+                self.less(left, right, left);
+                self.not(left);
+            }
+            Op::And => {
+                self.normalize(left);
+                let both = self.temp();
+                self.open(left);
+                self.dec(left, 1);
+                self.open(right);
+                self.clear(right);
+                self.inc(both, 1);
+                self.close(right);
+                self.close(left);
+                self.clear(right);
+                self.transfer(both, &[(left, true)]);
+                self.release(both);
+            }
+            Op::Or => {
+                self.normalize(left);
+                self.normalize(right);
+                self.transfer(right, &[(left, true)]);
+                self.normalize(left);
+            }
+        }
+    }
+
+    /// Sets the `0` cell `target` to the value of `expr`.
+    fn expr(&mut self, expr: &Expr, target: usize) {
+        self.span = expr.span;
+        match expr.kind {
+            ExprKind::Number(number) => self.inc(target, number),
+            ExprKind::Var(cell) => self.copy(cell, target),
+            ExprKind::Not(ref operand) => {
+                self.expr(operand, target);
+                self.span = expr.span;
+                self.not(target);
+            }
+            ExprKind::Binary(op, ref left, ref right) => {
+                self.expr(left, target);
+                let temp = self.temp();
+                self.expr(right, temp);
+                self.span = expr.span;
+                self.binary(op, target, temp);
+                self.release(temp);
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        self.span = stmt.span;
+        match stmt.kind {
+            StmtKind::Assign(cell, ref value) => {
+                let temp = self.temp();
+                self.expr(value, temp);
+                self.span = stmt.span;
+                self.clear(cell);
+                self.transfer(temp, &[(cell, true)]);
+                self.release(temp);
+            }
+            StmtKind::PrintText(ref text) => {
+                let temp = self.temp();
+                let mut value = 0;
+                for &byte in text {
+                    let byte = byte as u32;
+                    if byte > value {
+                        self.inc(temp, byte - value);
+                    } else {
+                        self.dec(temp, value - byte);
+                    }
+                    self.emit(Output);
+                    value = byte;
+                }
+                self.dec(temp, value);
+                self.release(temp);
+            }
+            StmtKind::PrintValue(ref value) => {
+                let temp = self.temp();
+                self.expr(value, temp);
+                self.span = stmt.span;
+                self.goto(temp);
+                self.emit(Output);
+                self.clear(temp);
+                self.release(temp);
+            }
+            StmtKind::Read(cell) => {
+                self.goto(cell);
+                self.emit(Input);
+            }
+            StmtKind::If(ref cond, ref then, ref otherwise) => {
+                let temp = self.temp();
+                self.expr(cond, temp);
+                self.span = stmt.span;
+                if otherwise.is_empty() {
+                    self.open(temp);
+                    self.clear(temp);
+                    self.block(then);
+                    self.span = stmt.span;
+                    self.close(temp);
+                } else {
+                    self.if_else(temp,
+                                 |this| {
+                                     this.block(then);
+                                     this.span = stmt.span;
+                                 },
+                                 |this| {
+                                     this.block(otherwise);
+                                     this.span = stmt.span;
+                                 });
+                }
+                self.release(temp);
+            }
+            StmtKind::While(ref cond, ref body) => {
+                let temp = self.temp();
+                self.expr(cond, temp);
+                self.span = stmt.span;
+                self.open(temp);
+                self.block(body);
+                self.span = stmt.span;
+                self.clear(temp);
+                self.expr(cond, temp);
+                self.span = stmt.span;
+                self.close(temp);
+                self.release(temp);
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Tests for the structured language compiler `lang::compile`.
+
+use buffer::*;
+use lang::*;
+use interp;
+use opt;
+use span::{Position, Span};
+
+/// Compiles and runs `text` with `input`, unoptimized and optimized,
+/// checks both outputs are equal and returns the output.
+fn run(text: &str, input: &str) -> Vec<u8> {
+    let (program, source_map) = compile(text).unwrap();
+    assert_eq!(program.instructions.len(), source_map.spans().len());
+    let mut outputs = Vec::new();
+    for p in &[opt::optimize(&program), program] {
+        let mut output = Vec::new();
+        interp::interp(p, &mut IBuffer::from_str(input), &mut output);
+        outputs.push(output);
+    }
+    assert_eq!(outputs[0], outputs[1]);
+    outputs.pop().unwrap()
+}
+
+/// Returns the output of printing `expr`.
+fn eval(expr: &str) -> u8 {
+    run(&format!("print {:};", expr), "")[0]
+}
+
+#[test]
+fn compile_nothing() {
+    let (program, _) = compile("# only a comment\n").unwrap();
+    assert!(program.instructions.is_empty());
+}
+
+#[test]
+fn print_text_and_values() {
+    assert_eq!(b"Hi!\n".to_vec(), run("print \"Hi\", '!', 10;", ""));
+    assert_eq!(b"\t\"'\\".to_vec(), run(r#"print "\t\"", '\'', '\\';"#, ""));
+}
+
+#[test]
+fn variables() {
+    let text = "var a = 'a';\nvar b;\nb = a + 2;\na = b;\nprint a, b;";
+    assert_eq!(b"cc".to_vec(), run(text, ""));
+}
+
+#[test]
+fn arithmetic() {
+    assert_eq!(7, eval("3 + 4"));
+    assert_eq!(14, eval("(3 + 4) * 2"));
+    assert_eq!(11, eval("3 + 4 * 2"));
+    assert_eq!(255, eval("3 - 4"));
+    assert_eq!(253, eval("-3"));
+    assert_eq!(6, eval("20 / 3"));
+    assert_eq!(2, eval("20 % 3"));
+    assert_eq!(0, eval("20 / 0"));
+    assert_eq!(20, eval("20 % 0"));
+    assert_eq!(2, eval("12 / 3 / 2"));
+    assert_eq!(0, eval("0 * 9"));
+}
+
+#[test]
+fn comparisons() {
+    let cases = [("3 == 3", 1), ("3 == 4", 0), ("3 != 4", 1), ("3 != 3", 0), ("3 < 4", 1),
+                 ("4 < 3", 0), ("3 < 3", 0), ("0 < 1", 1), ("3 <= 3", 1), ("4 <= 3", 0),
+                 ("4 > 3", 1), ("3 > 3", 0), ("3 >= 3", 1), ("3 >= 4", 0), ("0 >= 0", 1)];
+    for &(expr, expected) in &cases {
+        assert_eq!(expected, eval(expr), "{:}", expr);
+    }
+}
+
+#[test]
+fn logical_operators() {
+    let cases = [("2 && 3", 1), ("2 && 0", 0), ("0 && 3", 0), ("0 || 0", 0), ("0 || 5", 1),
+                 ("128 || 128", 1), ("!0", 1), ("!7", 0), ("1 < 2 && 2 < 3 || 0", 1)];
+    for &(expr, expected) in &cases {
+        assert_eq!(expected, eval(expr), "{:}", expr);
+    }
+}
+
+#[test]
+fn if_else() {
+    let text = "var x;\nread x;\n\
+                if x == 'a' { print \"A\"; }\n\
+                else if x == 'b' { print \"B\"; }\n\
+                else { print \"?\"; }\n\
+                if x { print \"!\"; }";
+    assert_eq!(b"A!".to_vec(), run(text, "a"));
+    assert_eq!(b"B!".to_vec(), run(text, "b"));
+    assert_eq!(b"?!".to_vec(), run(text, "c"));
+    assert_eq!(b"?".to_vec(), run(text, ""));
+}
+
+#[test]
+fn while_loops() {
+    let text = "var n = 5;\nwhile n > 0 {\n    print '0' + n;\n    n = n - 1;\n}";
+    assert_eq!(b"54321".to_vec(), run(text, ""));
+    let reverse = "var c;\nread c;\n\
+                   while c != '\\n' && c {\n    print c - 'a' + 'A';\n    read c;\n}";
+    assert_eq!(b"HELLO".to_vec(), run(reverse, "hello\nworld"));
+}
+
+#[test]
+fn print_decimal() {
+    let text = "var n;\nread n;\n\
+                var hundreds = n / 100;\nvar tens = n / 10 % 10;\n\
+                if hundreds { print '0' + hundreds; }\n\
+                if n >= 10 { print '0' + tens; }\n\
+                print '0' + n % 10, '\\n';";
+    assert_eq!(b"7\n".to_vec(), run(text, "\x07"));
+    assert_eq!(b"42\n".to_vec(), run(text, "*"));
+    assert_eq!(b"127\n".to_vec(), run(text, "\x7f"));
+}
+
+#[test]
+fn source_map() {
+    let (program, source_map) = compile("var x = 1;\nprint x;").unwrap();
+    let output = program.instructions.iter().position(|&instr| instr == ::ast::Instruction::Output);
+    let span = source_map.span(output.unwrap()).unwrap();
+    assert_eq!(Span {
+                   start: Position { line: 2, column: 1 },
+                   end: Position { line: 2, column: 8 },
+               },
+               span);
+    assert_eq!(1, source_map.span(0).unwrap().start.line);
+}
+
+#[test]
+fn compile_errors() {
+    let cases = [("print x;", "1:7: Undeclared variable x"),
+                 ("var x;\nvar x;", "2:5: Variable x is already declared"),
+                 ("var x = 1", "1:10: Expected ';', got end of file"),
+                 ("var if;", "1:5: Expected a variable, got if"),
+                 ("while 1 { print 1;", "1:19: Expected '}', got end of file"),
+                 ("print \"open;", "1:7: Missing closing \""),
+                 ("print 'ab';", "1:7: Expected a single byte character"),
+                 ("print 4294967296;", "1:7: Number 4294967296 is too large"),
+                 ("print 1 @ 2;", "1:9: Unexpected '@'"),
+                 ("print (1;", "1:9: Expected ')', got ';'")];
+    for &(text, msg) in &cases {
+        assert_eq!(Err(msg.to_string()), compile(text).map(|_| ()), "{:}", text);
+    }
+}
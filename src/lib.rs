@@ -1,11 +1,39 @@
 //! bf-interp is a brainfuck interpreter.
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
 // `pub` just to include them in the documentation.
 pub mod ast;
 pub mod loop_helper;
+pub mod span;
+pub mod dialect;
+pub mod preprocess;
 pub mod parser;
+pub mod lang;
 pub mod opt;
+pub mod tape;
 pub mod interp;
+pub mod fork;
+pub mod boolfuck;
+pub mod threaded;
+pub mod bytecode;
+pub mod printer;
+pub mod format;
+pub mod gen;
+pub mod repl;
+pub mod history;
+pub mod debugger;
+pub mod profile;
+pub mod trace;
+pub mod coverage;
+pub mod runner;
+pub mod bench;
+#[cfg(feature = "serde")]
+pub mod schema;
 
 #[cfg(test)]
 mod buffer;
@@ -14,6 +42,47 @@ mod buffer_tests;
 #[cfg(test)]
 mod parser_tests;
 #[cfg(test)]
+mod preprocess_tests;
+#[cfg(test)]
+mod lang_tests;
+#[cfg(test)]
+mod dialect_tests;
+#[cfg(test)]
+#[allow(clippy::byte_char_slices)]
 mod interp_tests;
 #[cfg(test)]
+mod fork_tests;
+#[cfg(test)]
+mod boolfuck_tests;
+#[cfg(test)]
+mod tape_tests;
+#[cfg(test)]
 mod opt_tests;
+#[cfg(test)]
+mod threaded_tests;
+#[cfg(test)]
+mod bytecode_tests;
+#[cfg(test)]
+mod printer_tests;
+#[cfg(test)]
+mod format_tests;
+#[cfg(test)]
+mod gen_tests;
+#[cfg(test)]
+mod repl_tests;
+#[cfg(test)]
+mod history_tests;
+#[cfg(test)]
+mod debugger_tests;
+#[cfg(test)]
+mod profile_tests;
+#[cfg(test)]
+mod trace_tests;
+#[cfg(test)]
+mod coverage_tests;
+#[cfg(test)]
+mod runner_tests;
+#[cfg(test)]
+mod bench_tests;
+#[cfg(all(test, feature = "serde"))]
+mod schema_tests;
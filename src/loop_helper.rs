@@ -1,9 +1,26 @@
 //! Implements a loop-up algorithm to speedup loop execution at runtime.
 
-use std::usize;
+use std::fmt;
 
 use ast::Instruction;
 use self::Instruction::*;
+use span::Span;
+
+/// Where a loop instruction is: its source span if known, otherwise its index.
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    idx: usize,
+    span: Option<Span>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{:}", span),
+            None => write!(f, "pc={:}", self.idx),
+        }
+    }
+}
 
 /// LoopHelper does the book-keeping while assembling a new list of instructions
 /// (brainfuck program).
@@ -13,12 +30,25 @@ use self::Instruction::*;
 ///
 /// This implementation assumes that Loop-Entries and Loop-Exits are paired and balanced.
 /// They may be nested.
+/// If the source spans of the loop instructions are passed in
+/// (`loop_entry_at` and `loop_exit_at`), panics report source locations.
+///
+/// pbrain procedure definitions (`ProcedureStart` and `ProcedureEnd`) are
+/// paired the same way. Loops and procedure bodies have to nest properly,
+/// e.g. `([)]` is unbalanced.
 #[derive(Debug)]
 pub struct LoopHelper {
-    bracket_stack: Vec<usize>,
+    /// The open bracket (`[` or `(`) of each unclosed entry.
+    bracket_stack: Vec<(char, Location)>,
     relocs: Vec<(usize, usize)>,
 }
 
+impl Default for LoopHelper {
+    fn default() -> LoopHelper {
+        LoopHelper::new()
+    }
+}
+
 impl LoopHelper {
     /// Creates a new loop helper.
     /// Call before starting to assemble the instruction list.
@@ -34,9 +64,39 @@ impl LoopHelper {
     /// This method returns just a placeholder that has to patched after
     /// assembling the whole instruction list with `relocate`.
     pub fn loop_entry(&mut self, idx: usize) -> Instruction {
-        self.bracket_stack.push(idx);
+        self.push_entry('[', Location { idx, span: None })
+    }
+
+    /// Like `loop_entry` for a loop-entry parsed from `span` in the source text.
+    pub fn loop_entry_at(&mut self, idx: usize, span: Span) -> Instruction {
+        self.push_entry('[',
+                        Location {
+                            idx,
+                            span: Some(span),
+                        })
+    }
+
+    /// Like `loop_entry` for a procedure-start (pbrain's `(`).
+    pub fn procedure_start(&mut self, idx: usize) -> Instruction {
+        self.push_entry('(', Location { idx, span: None })
+    }
+
+    /// Like `procedure_start` for a procedure-start parsed from `span` in the source text.
+    pub fn procedure_start_at(&mut self, idx: usize, span: Span) -> Instruction {
+        self.push_entry('(',
+                        Location {
+                            idx,
+                            span: Some(span),
+                        })
+    }
+
+    fn push_entry(&mut self, bracket: char, location: Location) -> Instruction {
+        self.bracket_stack.push((bracket, location));
         // value must be patched later
-        LoopEntry(usize::MAX)
+        match bracket {
+            '(' => ProcedureStart(usize::MAX),
+            _ => LoopEntry(usize::MAX),
+        }
     }
 
     /// Returns the loop-exit instruction to be inserted at index `idx`.
@@ -46,11 +106,57 @@ impl LoopHelper {
     /// Panics if loop-exit is not matched by a previously visitied loop-entry on
     /// the same nesting level.
     pub fn loop_exit(&mut self, idx: usize) -> Instruction {
-        if let Some(loop_entry) = self.bracket_stack.pop() {
-            self.relocs.push((loop_entry, idx));
-            LoopExit(loop_entry)
-        } else {
-            panic!("Unbalanced {:?} at pc={:}", ']', idx);
+        self.pop_entry(']', Location { idx, span: None })
+    }
+
+    /// Like `loop_exit` for a loop-exit parsed from `span` in the source text.
+    ///
+    /// # Panics
+    ///
+    /// Panics like `loop_exit`, but reports `span` as location.
+    pub fn loop_exit_at(&mut self, idx: usize, span: Span) -> Instruction {
+        self.pop_entry(']',
+                       Location {
+                           idx,
+                           span: Some(span),
+                       })
+    }
+
+    /// Like `loop_exit` for a procedure-end (pbrain's `)`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the procedure-end is not matched by a previously visited
+    /// procedure-start on the same nesting level.
+    pub fn procedure_end(&mut self, idx: usize) -> Instruction {
+        self.pop_entry(')', Location { idx, span: None })
+    }
+
+    /// Like `procedure_end` for a procedure-end parsed from `span` in the source text.
+    ///
+    /// # Panics
+    ///
+    /// Panics like `procedure_end`, but reports `span` as location.
+    pub fn procedure_end_at(&mut self, idx: usize, span: Span) -> Instruction {
+        self.pop_entry(')',
+                       Location {
+                           idx,
+                           span: Some(span),
+                       })
+    }
+
+    fn pop_entry(&mut self, bracket: char, location: Location) -> Instruction {
+        let open = if bracket == ')' { '(' } else { '[' };
+        match self.bracket_stack.pop() {
+            Some((entry_bracket, entry)) if entry_bracket == open => {
+                self.relocs.push((entry.idx, location.idx));
+                if bracket == ')' {
+                    ProcedureEnd(entry.idx)
+                } else {
+                    LoopExit(entry.idx)
+                }
+            }
+            _ => panic!("Unbalanced {:?} at {:}", bracket, location),
         }
     }
 
@@ -59,17 +165,19 @@ impl LoopHelper {
     /// # Panics
     ///
     /// Panics if not all loop-entries were matched by corresponding loop-exits.
-    pub fn relocate(mut self, instructions: &mut Vec<Instruction>) {
-        if let Some(unbalanced_idx) = self.bracket_stack.pop() {
-            panic!("Unbalanced {:?} at pc={:}", '[', unbalanced_idx);
+    pub fn relocate(mut self, instructions: &mut [Instruction]) {
+        if let Some((bracket, unbalanced)) = self.bracket_stack.pop() {
+            panic!("Unbalanced {:?} at {:}", bracket, unbalanced);
         }
         for (idx, value) in self.relocs {
-            if idx >= instructions.len() || LoopEntry(usize::MAX) != instructions[idx] {
-                panic!("Unexpected instruction {:?} at pc={:} for reloc",
-                       instructions[idx],
-                       idx);
-            } else {
-                instructions[idx] = LoopEntry(value);
+            match instructions.get(idx) {
+                Some(&LoopEntry(usize::MAX)) => instructions[idx] = LoopEntry(value),
+                Some(&ProcedureStart(usize::MAX)) => instructions[idx] = ProcedureStart(value),
+                _ => {
+                    panic!("Unexpected instruction {:?} at pc={:} for reloc",
+                           instructions.get(idx),
+                           idx)
+                }
             }
         }
     }
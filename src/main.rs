@@ -0,0 +1,631 @@
+//! `bf-interp` runs, checks and dumps brainfuck programs.
+//!
+//! See `USAGE` for the command line.
+
+extern crate bf_interp;
+
+use bf_interp::{ast, boolfuck, coverage, dialect, fork, gen, interp, lang, opt, parser,
+                preprocess, printer, profile, runner, trace};
+use bf_interp::debugger::Debugger;
+use bf_interp::dialect::{Dialect, Machine};
+use bf_interp::interp::Engine;
+use bf_interp::repl::Repl;
+use bf_interp::span::SourceMap;
+use bf_interp::tape::{Config, Tape};
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::panic;
+use std::process;
+
+const USAGE: &str = "\
+Usage: bf-interp <COMMAND> [OPTIONS] FILE
+       bf-interp repl [OPTIONS] [FILE]
+       bf-interp test [PATH]
+
+Commands:
+  run FILE              runs the program, reads from stdin and writes to stdout
+  check FILE            checks the syntax of the program
+  dump FILE             prints the instruction list of the program
+                        with the source position of each instruction
+  optimize FILE         prints the program optimized for -O as brainfuck
+  repl [FILE]           runs each entered line on a persistent tape,
+                        after running FILE (enter :help for the commands)
+  debug FILE            debugs the program (enter help for the commands),
+                        the program reads from stdin or --input
+  profile FILE          runs the program like run and afterwards prints
+                        the loops and source lines executed most to stderr
+  trace FILE            runs the program like run on the match engine and records
+                        each executed instruction to stderr or --trace-output
+  coverage FILE         runs the program like run and writes the lcov coverage
+                        report to stderr or merges it into --lcov
+  translate FILE        prints the program in the dialect of --to,
+                        translating brainfuck to the bits of boolfuck
+  gen FILE              prints a short program printing the bytes of FILE,
+                        in the dialect of --to
+  test [PATH]           runs the tests in the directory PATH (default: .)
+                        or the test PATH on all engines: each .bf file with
+                        a sidecar NAME.spec or a header of # KEY: VALUE lines
+                        listing input, output, exit and tape options
+
+A FILE ending in .bfl is compiled from the structured language of the lang
+module instead of parsed.
+
+Options:
+  -O LEVEL, -OLEVEL     optimization level: 0 disables the optimizer,
+                        1 and above run it (default: 1)
+  --engine NAME         match, threaded or bytecode (default: match),
+                        Brainfork programs which fork and the profile, trace,
+                        coverage and debug commands only run on match
+  --dialect NAME        dialect of FILE: brainfuck, pbrain, brainfork, extended,
+                        boolfuck, ook, blub or a keyword table file of COMMAND: KEYWORD lines
+                        (default: brainfuck)
+  --scheduler NAME      runs Brainfork threads round-robin or random:SEED
+                        instead of --engine (default: round-robin)
+  --to NAME             dialect translate writes, like --dialect
+  --input FILE          reads the input from FILE instead of stdin
+  --macros              expands the #include, #define and #macro directives
+                        and the macros of FILE before parsing it
+  --inline-input        FILE ends at its first !, the rest is the input
+                        instead of stdin, unless --input is given
+  --tape-size N         number of cells (default: 30000)
+  --cell-width BITS     8, 16 or 32 (default: 8)
+  --eof MODE            value read at the end of input:
+                        zero, minus-one or unchanged (default: zero)
+  --top N               number of loops and lines in the profile (default: 10)
+  --trace-format FMT    json (one record per line) or binary (default: json)
+  --trace-output FILE   writes the trace to FILE instead of stderr
+  --pc-range START..END only traces the instructions in the range
+  --lcov FILE           merges the coverage into the lcov report FILE
+  --fail-under PERCENT  fails if fewer lines of FILE are covered";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Check,
+    Dump,
+    Optimize,
+    Repl,
+    Debug,
+    Profile,
+    Trace,
+    Coverage,
+    Translate,
+    Gen,
+    Test,
+}
+
+/// The parsed command line.
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    /// only optional for `Command::Repl` and `Command::Test`
+    fname: Option<String>,
+    opt_level: u32,
+    /// `None` without `--engine`, which runs on `Engine::Match`
+    engine: Option<Engine>,
+    dialect: String,
+    to: String,
+    scheduler: fork::Scheduler,
+    input: Option<String>,
+    inline_input: bool,
+    macros: bool,
+    tape: Config,
+    top: usize,
+    trace: trace::Options,
+    trace_output: Option<String>,
+    lcov: Option<String>,
+    fail_under: Option<f64>,
+}
+
+fn parse_value<T, I>(args: &mut I, flag: &str) -> Result<T, String>
+    where T: std::str::FromStr,
+          T::Err: ToString,
+          I: Iterator<Item = String>
+{
+    match args.next() {
+        Some(value) => value.parse().map_err(|err: T::Err| err.to_string()),
+        None => Err(format!("{:} expects a value", flag)),
+    }
+}
+
+/// Parses a pc range `START..END`, either bound may be omitted.
+fn parse_range(text: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("Invalid pc range {:?}, expected START..END", text);
+    let idx = text.find("..").ok_or_else(invalid)?;
+    let bound = |text: &str, default| if text.is_empty() {
+        Ok(default)
+    } else {
+        text.parse().map_err(|_| invalid())
+    };
+    Ok(bound(&text[..idx], 0)?..bound(&text[idx + 2..], usize::MAX)?)
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("run") => Command::Run,
+        Some("check") => Command::Check,
+        Some("dump") => Command::Dump,
+        Some("optimize") => Command::Optimize,
+        Some("repl") => Command::Repl,
+        Some("debug") => Command::Debug,
+        Some("profile") => Command::Profile,
+        Some("trace") => Command::Trace,
+        Some("coverage") => Command::Coverage,
+        Some("translate") => Command::Translate,
+        Some("gen") => Command::Gen,
+        Some("test") => Command::Test,
+        Some(command) => return Err(format!("Unknown command {:?}", command)),
+        None => return Err("Missing command".to_string()),
+    };
+    let mut fname = None;
+    let mut opt_level = 1;
+    let mut engine = None;
+    let mut dialect = "brainfuck".to_string();
+    let mut to = "brainfuck".to_string();
+    let mut scheduler = fork::Scheduler::default();
+    let mut input = None;
+    let mut inline_input = false;
+    let mut macros = false;
+    let mut tape = Config::default();
+    let mut top = 10;
+    let mut trace = trace::Options::default();
+    let mut trace_output = None;
+    let mut lcov = None;
+    let mut fail_under = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-O" => opt_level = parse_value(&mut args, "-O")?,
+            "--engine" => engine = Some(parse_value(&mut args, "--engine")?),
+            "--dialect" => dialect = parse_value(&mut args, "--dialect")?,
+            "--to" => to = parse_value(&mut args, "--to")?,
+            "--scheduler" => scheduler = parse_value(&mut args, "--scheduler")?,
+            "--input" => input = Some(parse_value(&mut args, "--input")?),
+            "--inline-input" => inline_input = true,
+            "--macros" => macros = true,
+            "--tape-size" => tape.size = parse_value(&mut args, "--tape-size")?,
+            "--cell-width" => tape.cell_width = parse_value(&mut args, "--cell-width")?,
+            "--eof" => tape.eof = parse_value(&mut args, "--eof")?,
+            "--top" => top = parse_value(&mut args, "--top")?,
+            "--trace-format" => trace.format = parse_value(&mut args, "--trace-format")?,
+            "--trace-output" => trace_output = Some(parse_value(&mut args, "--trace-output")?),
+            "--lcov" => lcov = Some(parse_value(&mut args, "--lcov")?),
+            "--fail-under" => fail_under = Some(parse_value(&mut args, "--fail-under")?),
+            "--pc-range" => {
+                let range: String = parse_value(&mut args, "--pc-range")?;
+                trace.pcs = Some(parse_range(&range)?);
+            }
+            _ if arg.starts_with("-O") => {
+                opt_level = arg[2..]
+                    .parse()
+                    .map_err(|_| format!("Invalid optimization level {:?}", arg))?
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {:?}", arg)),
+            _ if fname.is_none() => fname = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}", arg)),
+        }
+    }
+
+    if fname.is_none() && command != Command::Repl && command != Command::Test {
+        return Err("Missing FILE".to_string());
+    }
+    Ok(Args {
+           command,
+           fname,
+           opt_level,
+           engine,
+           dialect,
+           to,
+           scheduler,
+           input,
+           inline_input,
+           macros,
+           tape,
+           top,
+           trace,
+           trace_output,
+           lcov,
+           fail_under,
+       })
+}
+
+fn read_file(fname: &str) -> Result<Vec<u8>, String> {
+    let mut contents = Vec::new();
+    File::open(fname)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .map_err(|err| format!("Cannot read {:} because {:}", fname, err))?;
+    Ok(contents)
+}
+
+/// Reads the program text of `fname`, up to the first `!` with `--inline-input`.
+fn read_program(args: &Args, fname: &str) -> Result<String, String> {
+    let contents = read_file(fname)?;
+    let text = if args.inline_input {
+        parser::split_input(&contents).0
+    } else {
+        &contents
+    };
+    Ok(String::from_utf8_lossy(text).into_owned())
+}
+
+/// Opens the program input: the file of `--input`, the inline input of FILE
+/// with `--inline-input` or stdin.
+fn open_input(args: &Args) -> Result<Box<dyn Read>, String> {
+    match (&args.input, &args.fname) {
+        (Some(fname), _) => Ok(Box::new(io::Cursor::new(read_file(fname)?))),
+        (None, Some(fname)) if args.inline_input => {
+            let contents = read_file(fname)?;
+            match parser::split_input(&contents).1 {
+                Some(input) => Ok(Box::new(io::Cursor::new(input.to_vec()))),
+                None => Ok(Box::new(io::stdin())),
+            }
+        }
+        _ => Ok(Box::new(io::stdin())),
+    }
+}
+
+/// Returns whether `fname` is a source of the structured language of `lang`.
+fn is_lang(fname: &str) -> bool {
+    Path::new(fname)
+        .extension()
+        .is_some_and(|ext| ext == lang::EXTENSION)
+}
+
+/// Loads, checks and parses the program in `fname` written in `dialect`,
+/// with `--macros` after expanding its macros.
+/// A `.bfl` file is compiled from the structured language of `lang` instead.
+/// The program is optimized for an optimization level above `0`.
+/// Returns the program with the source positions of its instructions.
+fn load_program(args: &Args,
+                fname: &str,
+                dialect: &dyn Dialect,
+                opt_level: u32)
+                -> Result<(ast::Program, SourceMap), String> {
+    let text = read_program(args, fname)?;
+    let (program, source_map) = if is_lang(fname) {
+        lang::compile(&text).map_err(|err| format!("{:}:{:}", fname, err))?
+    } else if args.macros {
+        preprocess::expand(&text, Path::new(fname))?
+            .parse_dialect(dialect)
+            .map_err(|err| format!("{:}:{:}", fname, err))?
+    } else {
+        parser::check_dialect(&text, dialect).map_err(|err| format!("{:}:{:}", fname, err))?;
+        parser::parse_dialect(&text, dialect)
+    };
+    if opt_level > 0 {
+        Ok(opt::optimize_with(&program, &source_map))
+    } else {
+        Ok((program, source_map))
+    }
+}
+
+/// Loads the program in `fname` like `load_program` for the commands which
+/// step through it on a single tape of cells like the match engine.
+/// Fails on other engines, on Boolfuck programs and on programs which fork.
+fn load_single_program(args: &Args,
+                       fname: &str,
+                       dialect: &dyn Dialect,
+                       opt_level: u32)
+                       -> Result<(ast::Program, SourceMap), String> {
+    let command = format!("{:?}", args.command).to_lowercase();
+    match args.engine {
+        Some(engine) if engine != Engine::Match => {
+            return Err(format!("The {:} command runs programs on the match engine, not on {:}",
+                               command,
+                               engine));
+        }
+        _ => (),
+    }
+    if dialect.machine() == Machine::Bits {
+        return Err(format!("The {:} command cannot run {:} programs on a tape of bits",
+                           command,
+                           dialect.name()));
+    }
+    let (program, source_map) = load_program(args, fname, dialect, opt_level)?;
+    if fork::forks(&program) {
+        return Err(format!("{:}: The {:} command cannot run programs which fork", fname, command));
+    }
+    Ok((program, source_map))
+}
+
+/// Runs the REPL on stdin and stdout until `:quit` or the end of stdin.
+/// Program input (`,`) reads from stdin as well.
+fn repl(args: &Args) -> Result<(), String> {
+    // aborted programs are reported by the REPL, skip the backtrace
+    panic::set_hook(Box::new(|info| eprintln!("{:}", info)));
+    let mut repl = Repl::new(args.tape, args.engine.unwrap_or(Engine::Match));
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if let Some(ref fname) = args.fname {
+        repl.eval(&format!(":load {:}", fname), &mut input, &mut out)?;
+    }
+    loop {
+        write!(out, "bf> ")
+            .and_then(|_| out.flush())
+            .map_err(|err| err.to_string())?;
+        let mut line = String::new();
+        if 0 == input.read_line(&mut line).map_err(|err| err.to_string())? {
+            return Ok(());
+        }
+        match repl.eval(&line, &mut input, &mut out) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(msg) => eprintln!("{:}", msg),
+        }
+    }
+}
+
+/// Runs the debugger on `fname` until `quit` or the end of stdin.
+fn debug(args: &Args, fname: &str) -> Result<(), String> {
+    // aborted programs are reported by the debugger, skip the backtrace
+    panic::set_hook(Box::new(|info| eprintln!("{:}", info)));
+    let text = read_program(args, fname)?;
+    // the debugger steps through the unoptimized program and stops at `#`
+    let dialect = dialect::lookup(&args.dialect)?;
+    let dialect = dialect.with_breakpoints().unwrap_or(dialect);
+    let (program, source_map) = load_single_program(args, fname, &*dialect, 0)?;
+    let mut debugger = Debugger::with_program(program, source_map, &text, &args.tape);
+    // not locked, the program may read from stdin as well
+    let commands = io::stdin();
+    let mut program_input = open_input(args)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    debugger.eval("where", &mut program_input, &mut out)?;
+    loop {
+        write!(out, "(bfdb) ")
+            .and_then(|_| out.flush())
+            .map_err(|err| err.to_string())?;
+        let mut line = String::new();
+        if 0 == commands.read_line(&mut line).map_err(|err| err.to_string())? {
+            return Ok(());
+        }
+        match debugger.eval(&line, &mut program_input, &mut out) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(msg) => eprintln!("{:}", msg),
+        }
+    }
+}
+
+/// Runs `fname`, reports its coverage and checks `--fail-under`.
+fn coverage(args: &Args, fname: &str) -> Result<(), String> {
+    let dialect = dialect::lookup(&args.dialect)?;
+    let (program, commands) = load_single_program(args, fname, &*dialect, 0)?;
+    let (program, source_map) = if args.opt_level > 0 {
+        opt::optimize_with(&program, &commands)
+    } else {
+        (program, commands.clone())
+    };
+    let mut input = open_input(args)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut tape = Tape::new(&args.tape);
+    let run = coverage::record(&program, &source_map, &commands, &mut tape, &mut input, &mut out);
+    out.flush().map_err(|err| err.to_string())?;
+
+    let mut report = coverage::Coverage::default();
+    match args.lcov {
+        Some(ref lcov) if Path::new(lcov).exists() => {
+            let text = String::from_utf8_lossy(&read_file(lcov)?).into_owned();
+            report = coverage::Coverage::from_lcov(&text)
+                .map_err(|err| format!("{:}:{:}", lcov, err))?;
+        }
+        _ => (),
+    }
+    report.add(fname, &run);
+    match args.lcov {
+        Some(ref lcov) => {
+            File::create(lcov)
+                .and_then(|mut file| file.write_all(report.to_lcov().as_bytes()))
+                .map_err(|err| format!("Cannot write {:} because {:}", lcov, err))?
+        }
+        None => eprint!("{:}", report.to_lcov()),
+    }
+
+    let file = &report.files[fname];
+    let (lines, hit) = file.line_summary();
+    let (branches, taken) = file.loop_summary();
+    let percent = if lines == 0 {
+        100.0
+    } else {
+        100.0 * hit as f64 / lines as f64
+    };
+    eprintln!("{:}: lines {:}/{:} ({:.1}%), loop branches {:}/{:}",
+              fname,
+              hit,
+              lines,
+              percent,
+              taken,
+              branches);
+    match args.fail_under {
+        Some(min) if percent < min => {
+            Err(format!("Line coverage {:.1}% is below {:}%", percent, min))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs the tests in `path` and reports each test and engine.
+/// Fails if any test fails.
+fn test(path: &str) -> Result<(), String> {
+    // failures are reported with their message, skip the panic output
+    panic::set_hook(Box::new(|_| ()));
+    let files = runner::discover(Path::new(path))
+        .map_err(|err| format!("Cannot read {:} because {:}", path, err))?;
+    let (mut passed, mut failed) = (0, 0);
+    for file in files {
+        let case = match runner::load(&file) {
+            Ok(Some(case)) => case,
+            Ok(None) => continue,
+            Err(msg) => {
+                println!("FAIL {:}", msg);
+                failed += 1;
+                continue;
+            }
+        };
+        for (engine, result) in runner::run_case(&case) {
+            let name = format!("{:} ({:})", file.display(), engine);
+            match result {
+                Ok(()) => {
+                    println!("ok   {:}", name);
+                    passed += 1;
+                }
+                Err(failure) => {
+                    print!("FAIL {:}: {:}", name, failure.describe());
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("\ntest result: {:} passed, {:} failed", passed, failed);
+    if failed > 0 {
+        Err(format!("{:} tests failed", failed))
+    } else {
+        Ok(())
+    }
+}
+
+fn execute(args: &Args) -> Result<(), String> {
+    let fname = args.fname.as_ref().map_or("", String::as_str);
+    let dialect = dialect::lookup(&args.dialect)?;
+    match args.command {
+        Command::Check => {
+            load_program(args, fname, &*dialect, 0)?;
+        }
+        Command::Translate => {
+            let (program, _) = load_program(args, fname, &*dialect, 0)?;
+            let to = dialect::lookup(&args.to)?;
+            let text = dialect::translate_between(&program, &*dialect, &*to)
+                .map_err(|err| format!("{:}: {:}", fname, err))?;
+            print!("{:}", text);
+        }
+        Command::Gen => {
+            let program = parser::parse(gen::generate(&read_file(fname)?));
+            print!("{:}", dialect::translate(&program, &*dialect::lookup(&args.to)?)?);
+        }
+        Command::Optimize => {
+            let (program, _) = load_program(args, fname, &*dialect, args.opt_level)?;
+            let options = printer::Options {
+                width: 80,
+                ..printer::Options::default()
+            };
+            print!("{:}", printer::to_bf_source(&program, &options));
+        }
+        Command::Dump => {
+            let (program, source_map) = load_program(args, fname, &*dialect, args.opt_level)?;
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            for (pc, (instr, span)) in program
+                    .instructions
+                    .iter()
+                    .zip(source_map.spans())
+                    .enumerate() {
+                writeln!(out, "{:6}: {:20} {:}", pc, format!("{:?}", instr), span)
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        Command::Run => {
+            let (program, _) = load_program(args, fname, &*dialect, args.opt_level)?;
+            let mut input = open_input(args)?;
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let mut tape = Tape::new(&args.tape);
+            if dialect.machine() == Machine::Bits {
+                if let Some(engine) = args.engine {
+                    return Err(format!("{:}: {:} programs run on a tape of bits, not on the {:} \
+                                        engine",
+                                       fname,
+                                       dialect.name(),
+                                       engine));
+                }
+                boolfuck::run(&program, args.tape.size, &mut input, &mut out);
+            } else if fork::forks(&program) {
+                match args.engine {
+                    Some(engine) if engine != Engine::Match => {
+                        return Err(format!("{:}: Programs which fork only run on the match \
+                                            engine, not on {:}",
+                                           fname,
+                                           engine));
+                    }
+                    _ => (),
+                }
+                fork::run_with(&program, &mut tape, &mut input, &mut out, args.scheduler);
+            } else {
+                interp::run_with(args.engine.unwrap_or(Engine::Match),
+                                 &program,
+                                 &mut tape,
+                                 &mut input,
+                                 &mut out);
+            }
+            out.flush().map_err(|err| err.to_string())?;
+        }
+        Command::Profile => {
+            let (program, source_map) =
+                load_single_program(args, fname, &*dialect, args.opt_level)?;
+            let mut input = open_input(args)?;
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let mut tape = Tape::new(&args.tape);
+            let profile = profile::profile_with(&program, &mut tape, &mut input, &mut out);
+            out.flush().map_err(|err| err.to_string())?;
+            let text = read_program(args, fname)?;
+            eprint!("\n{:}", profile.report(&source_map, &text, args.top));
+        }
+        Command::Trace => {
+            let (program, _) = load_single_program(args, fname, &*dialect, args.opt_level)?;
+            let mut input = open_input(args)?;
+            let sink: Box<dyn Write> = match args.trace_output {
+                Some(ref fname) => {
+                    Box::new(File::create(fname).map_err(|err| {
+                                 format!("Cannot write {:} because {:}", fname, err)
+                             })?)
+                }
+                None => Box::new(io::stderr()),
+            };
+            let mut tracer = trace::Tracer::new(io::BufWriter::new(sink), args.trace.clone());
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let mut tape = Tape::new(&args.tape);
+            trace::run(&program,
+                       &mut tape,
+                       &mut input,
+                       &mut out,
+                       &mut |record| tracer.record(record))
+                    .and_then(|_| tracer.finish())
+                    .and_then(|_| out.flush())
+                    .map_err(|err| format!("Cannot write the trace because {:}", err))?;
+        }
+        Command::Coverage => coverage(args, fname)?,
+        Command::Repl => repl(args)?,
+        Command::Debug => debug(args, fname)?,
+        Command::Test => test(args.fname.as_ref().map_or(".", String::as_str))?,
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{:}", USAGE);
+        return;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{:}\n\n{:}", msg, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(msg) = execute(&args) {
+        eprintln!("{:}", msg);
+        process::exit(1);
+    }
+}
@@ -4,6 +4,7 @@ use ast::{Instruction, Program};
 use ast::Instruction::*;
 
 use loop_helper::LoopHelper;
+use span::{SourceMap, Span};
 
 /// Returns an optimized version of `program`.
 /// Optimized means, that the returned program will executed fewer or
@@ -11,59 +12,76 @@ use loop_helper::LoopHelper;
 /// Optimizing an already optimized program is legal, but will yield
 /// the same program as then input `program`.
 pub fn optimize(program: &Program) -> Program {
+    optimize_spans(program, None).0
+}
+
+/// Optimizes `program` like `optimize` and keeps track of the source
+/// positions in `source_map` (the source map of `program`).
+/// Returns the optimized program and its source map: the span of a merged
+/// instruction covers the spans of all instructions it was merged from.
+///
+/// # Panics
+///
+/// Panics if `source_map` has fewer spans than `program` has instructions.
+pub fn optimize_with(program: &Program, source_map: &SourceMap) -> (Program, SourceMap) {
+    assert!(source_map.spans().len() >= program.instructions.len(),
+            "Source map with {:} spans for {:} instructions",
+            source_map.spans().len(),
+            program.instructions.len());
+    let (program, spans) = optimize_spans(program, Some(source_map.spans()));
+    (program, SourceMap::new(spans))
+}
+
+/// Returns the instruction merging `last` and `instr`
+/// or `None` if they cannot be merged.
+fn merge(last: Instruction, instr: Instruction) -> Option<Instruction> {
+    match (last, instr) {
+        (MoveLeft(old_offset), MoveLeft(offset)) => Some(MoveLeft(old_offset + offset)),
+        (MoveRight(old_offset), MoveRight(offset)) => Some(MoveRight(old_offset + offset)),
+        // do not wrap, cells may be wider than 8 bits
+        (Inc(old_val), Inc(val)) => old_val.checked_add(val).map(Inc),
+        (Dec(old_val), Dec(val)) => old_val.checked_add(val).map(Dec),
+        _ => None,
+    }
+}
+
+/// Optimizes `program`, whose instruction spans are `spans` if known.
+/// Returns the spans of the optimized program, which are empty for unknown `spans`.
+fn optimize_spans(program: &Program, spans: Option<&[Span]>) -> (Program, Vec<Span>) {
     let mut loop_helper = LoopHelper::new();
     // optimized program
     let mut instructions: Vec<Instruction> = Vec::new();
+    let mut new_spans: Vec<Span> = Vec::new();
 
-    for instr in program.instructions.iter() {
-        match *instr {
-            MoveLeft(offset) => {
-                let last_instr = instructions.last().cloned();
-                if let Some(MoveLeft(old_offset)) = last_instr {
-                    let last_idx = instructions.len() - 1;
-                    instructions[last_idx] = MoveLeft(old_offset + offset);
-                } else {
-                    instructions.push(*instr);
-                }
-            }
-            MoveRight(offset) => {
-                let last_instr = instructions.last().cloned();
-                if let Some(MoveRight(old_offset)) = last_instr {
-                    let last_idx = instructions.len() - 1;
-                    instructions[last_idx] = MoveRight(old_offset + offset);
-                } else {
-                    instructions.push(*instr);
-                }
-            }
-            Inc(val) => {
-                let last_instr = instructions.last().cloned();
-                if let Some(Inc(old_val)) = last_instr {
-                    let last_idx = instructions.len() - 1;
-                    instructions[last_idx] = Inc(old_val.wrapping_add(val));
-                } else {
-                    instructions.push(*instr);
-                }
-            }
-            Dec(val) => {
-                let last_instr = instructions.last().cloned();
-                if let Some(Dec(old_val)) = last_instr {
-                    let last_idx = instructions.len() - 1;
-                    instructions[last_idx] = Dec(old_val.wrapping_add(val));
-                } else {
-                    instructions.push(*instr);
-                }
-            }
-            LoopEntry(_) => {
-                let idx = instructions.len();
-                instructions.push(loop_helper.loop_entry(idx));
-            }
-            LoopExit(_) => {
-                let idx = instructions.len();
-                instructions.push(loop_helper.loop_exit(idx));
+    for (old_idx, instr) in program.instructions.iter().enumerate() {
+        let span = spans.map(|spans| spans[old_idx]);
+        let merged = instructions.last().and_then(|last| merge(*last, *instr));
+        if let Some(merged) = merged {
+            let last_idx = instructions.len() - 1;
+            instructions[last_idx] = merged;
+            if let Some(span) = span {
+                new_spans[last_idx] = new_spans[last_idx].merge(&span);
             }
-            instr => instructions.push(instr),
+            continue;
         }
+        let idx = instructions.len();
+        instructions.push(match (*instr, span) {
+                              (LoopEntry(_), Some(span)) => loop_helper.loop_entry_at(idx, span),
+                              (LoopEntry(_), None) => loop_helper.loop_entry(idx),
+                              (LoopExit(_), Some(span)) => loop_helper.loop_exit_at(idx, span),
+                              (LoopExit(_), None) => loop_helper.loop_exit(idx),
+                              (ProcedureStart(_), Some(span)) => {
+                                  loop_helper.procedure_start_at(idx, span)
+                              }
+                              (ProcedureStart(_), None) => loop_helper.procedure_start(idx),
+                              (ProcedureEnd(_), Some(span)) => {
+                                  loop_helper.procedure_end_at(idx, span)
+                              }
+                              (ProcedureEnd(_), None) => loop_helper.procedure_end(idx),
+                              (instr, _) => instr,
+                          });
+        new_spans.extend(span);
     }
     loop_helper.relocate(&mut instructions);
-    Program { instructions: instructions }
+    (Program { instructions }, new_spans)
 }
@@ -2,7 +2,9 @@
 
 use ast::Program;
 use ast::Instruction::*;
-use opt::optimize;
+use opt::{optimize, optimize_with};
+use parser::{parse_with, Options};
+use span::{Position, Span};
 
 #[test]
 fn merge_inc() {
@@ -77,3 +79,48 @@ fn keep_loop() {
                                                 LoopExit(2)],
                          }))
 }
+
+#[test]
+fn merge_inc_without_wrapping() {
+    assert_eq!(Program { instructions: vec![Inc(255), Inc(45)] },
+               optimize(&Program { instructions: vec![Inc(200), Inc(55), Inc(45)] }));
+}
+
+#[test]
+fn merge_dec_without_wrapping() {
+    assert_eq!(Program { instructions: vec![Dec(250), Dec(10)] },
+               optimize(&Program { instructions: vec![Dec(250), Dec(10)] }));
+}
+
+#[test]
+fn merge_spans() {
+    let (program, source_map) = parse_with("++ +\n[-\n>>]<", &Options::default());
+    let (program, source_map) = optimize_with(&program, &source_map);
+    let span = |start: (usize, usize), end: (usize, usize)| {
+        Span {
+            start: Position {
+                line: start.0,
+                column: start.1,
+            },
+            end: Position {
+                line: end.0,
+                column: end.1,
+            },
+        }
+    };
+    assert_eq!(Program { instructions: vec![Inc(3), LoopEntry(4), Dec(1), MoveRight(2), LoopExit(1), MoveLeft(1)] },
+               program);
+    assert_eq!(&[span((1, 1), (1, 4)),
+                 span((2, 1), (2, 1)),
+                 span((2, 2), (2, 2)),
+                 span((3, 1), (3, 2)),
+                 span((3, 3), (3, 3)),
+                 span((3, 4), (3, 4))],
+               source_map.spans());
+}
+
+#[test]
+fn optimize_with_keeps_program() {
+    let (program, source_map) = parse_with(",>>+[-<+>]<.", &Options::default());
+    assert_eq!(optimize(&program), optimize_with(&program, &source_map).0);
+}
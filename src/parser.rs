@@ -1,8 +1,16 @@
-//! Parser `parser::parse` for brainfuck.
+//! Parser `parser::parse` for brainfuck and its dialects (see `dialect`).
+
+use std::fmt;
+use std::io::{self, Read};
 
 use ast::Program;
 use ast::Instruction::*;
+use dialect::{self, Brainfuck, Dialect};
 use loop_helper::LoopHelper;
+use span::{Position, SourceMap, Span};
+
+/// The number of bytes `parse_reader` reads at once.
+const CHUNK_SIZE: usize = 8192;
 
 /// Parses (and lexes) the input `text` and returns an ast.
 /// Whitespace in `text` ist skipped.
@@ -13,27 +21,252 @@ use loop_helper::LoopHelper;
 ///
 /// Panics on parsing errors: unbalanced `[` and `]`.
 pub fn parse(text: String) -> Program {
+    parse_with(&text, &Options::default()).0
+}
+
+/// Parses the brainfuck program in `bytes` like `parse`, without requiring
+/// valid UTF-8: all bytes but the 8 brainfuck symbols are skipped,
+/// including non-ASCII bytes like Latin-1 comments.
+///
+/// # Panics
+///
+/// Panics on parsing errors: unbalanced `[` and `]`.
+pub fn parse_bytes(bytes: &[u8]) -> Program {
+    parse_reader(bytes).expect("Reading from a slice does not fail")
+}
+
+/// Parses the brainfuck program read from `reader` like `parse_bytes`.
+/// The program is read and tokenized in chunks of `CHUNK_SIZE` bytes,
+/// so only the instructions are kept in memory, not the text.
+/// Fails if reading fails.
+///
+/// # Panics
+///
+/// Panics on parsing errors: unbalanced `[` and `]`.
+/// The panic message contains the `line:column` of the unbalanced bracket,
+/// columns count bytes, so a UTF-8 character may take several columns.
+pub fn parse_reader(mut reader: impl Read) -> io::Result<Program> {
+    let mut loop_helper = LoopHelper::new();
+    let mut instructions = Vec::new();
+    let mut position = Position { line: 1, column: 1 };
+    let mut chunk = [0u8; CHUNK_SIZE];
+    loop {
+        let len = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for &byte in &chunk[..len] {
+            let idx = instructions.len();
+            let span = Span::at(position);
+            if byte == b'\n' {
+                position.line += 1;
+                position.column = 1;
+                continue;
+            }
+            position.column += 1;
+            if !byte.is_ascii() {
+                continue;
+            }
+            instructions.push(match dialect::instruction(byte as char) {
+                                  Some(LoopEntry(_)) => loop_helper.loop_entry_at(idx, span),
+                                  Some(LoopExit(_)) => loop_helper.loop_exit_at(idx, span),
+                                  // the 8 brainfuck symbols only
+                                  Some(instr @ (MoveLeft(_) | MoveRight(_) | Inc(_) | Dec(_) |
+                                                Output | Input)) => instr,
+                                  _ => continue,
+                              });
+        }
+    }
+    loop_helper.relocate(&mut instructions);
+    Ok(Program { instructions })
+}
+
+/// Options for `parse_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Options {
+    /// Parses the debug character `#` as `Breakpoint` instead of skipping it.
+    pub debug: bool,
+    /// Parses the pbrain procedure commands `(`, `)` and `:`
+    /// instead of skipping them.
+    pub pbrain: bool,
+    /// Parses Brainfork's fork command `Y` instead of skipping it.
+    pub fork: bool,
+    /// Parses the Extended Brainfuck Type I commands `@ $ ! } { ~ ^ & |`
+    /// instead of skipping them.
+    pub extended: bool,
+    /// Ends the program at the first `!`, the rest of the text is the input
+    /// of the program (see `parse_with_input`). Takes precedence over
+    /// Extended Brainfuck's `!`.
+    pub inline_input: bool,
+}
+
+/// Parses `text` like `parse` with the given `options`.
+/// Returns the ast together with the source position of each instruction.
+///
+/// # Panics
+///
+/// Panics on parsing errors: unbalanced `[` and `]` or `(` and `)`.
+/// The panic message contains the `line:column` of the unbalanced bracket.
+pub fn parse_with(text: &str, options: &Options) -> (Program, SourceMap) {
+    let text = if options.inline_input {
+        &text[..split_input(text.as_bytes()).0.len()]
+    } else {
+        text
+    };
+    parse_dialect(text,
+                  &Brainfuck {
+                      debug: options.debug,
+                      pbrain: options.pbrain,
+                      fork: options.fork,
+                      extended: options.extended,
+                  })
+}
+
+/// Parses `text` like `parse_with` and additionally returns the input
+/// behind the first `!` with `Options::inline_input`, e.g. `,[.,]!abc`
+/// echoes `abc`. The input is `None` without the option or without a `!`.
+pub fn parse_with_input(text: &str, options: &Options) -> (Program, SourceMap, Option<Vec<u8>>) {
+    let (program, source_map) = parse_with(text, options);
+    let input = if options.inline_input {
+        split_input(text.as_bytes()).1.map(<[u8]>::to_vec)
+    } else {
+        None
+    };
+    (program, source_map, input)
+}
+
+/// Splits `text` at the first `!` into the program and the input behind it.
+/// The input is `None` if `text` has no `!`.
+pub fn split_input(text: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match text.iter().position(|&b| b == b'!') {
+        Some(idx) => (&text[..idx], Some(&text[idx + 1..])),
+        None => (text, None),
+    }
+}
+
+/// Parses `text` written in `dialect` like `parse_with`.
+///
+/// # Panics
+///
+/// Panics on parsing errors: unbalanced loops or procedures.
+/// The panic message contains the `line:column` of the unbalanced token.
+pub fn parse_dialect(text: &str, dialect: &dyn Dialect) -> (Program, SourceMap) {
     let mut loop_helper = LoopHelper::new();
-    // "lex" token stream - aka skip whitespace
-    let token = text.chars()
-        .filter(move |c| {
-                    *c == '>' || *c == '<' || *c == '+' || *c == '-' || *c == '.' ||
-                    *c == ',' || *c == '[' || *c == ']'
-                });
-    let mut instructions = token
-        .enumerate()
-        .map(|(idx, c)| match c {
-                 '<' => MoveLeft(1),
-                 '>' => MoveRight(1),
-                 '+' => Inc(1),
-                 '-' => Dec(1),
-                 '.' => Output,
-                 ',' => Input,
-                 '[' => loop_helper.loop_entry(idx),
-                 ']' => loop_helper.loop_exit(idx),
-                 c => panic!("Unknown instruction {:?} at pc={:}", c, idx),
-             })
-        .collect::<Vec<_>>();
+    let mut instructions = Vec::new();
+    let mut spans = Vec::new();
+
+    for (instr, span) in dialect.tokenize(text) {
+        let idx = instructions.len();
+        instructions.push(match instr {
+                              LoopEntry(_) => loop_helper.loop_entry_at(idx, span),
+                              LoopExit(_) => loop_helper.loop_exit_at(idx, span),
+                              ProcedureStart(_) => loop_helper.procedure_start_at(idx, span),
+                              ProcedureEnd(_) => loop_helper.procedure_end_at(idx, span),
+                              _ => instr,
+                          });
+        spans.push(span);
+    }
     loop_helper.relocate(&mut instructions);
-    Program { instructions: instructions }
+    (Program { instructions }, SourceMap::new(spans))
+}
+
+/// Syntax error found by `check`.
+/// Lines and columns start at `1`, columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseError {
+    /// `]` without a preceding matching `[`.
+    UnbalancedLoopExit { line: usize, column: usize },
+    /// `[` without a following matching `]`.
+    UnbalancedLoopEntry { line: usize, column: usize },
+    /// `)` without a preceding matching `(`.
+    UnbalancedProcedureEnd { line: usize, column: usize },
+    /// `(` without a following matching `)`.
+    UnbalancedProcedureStart { line: usize, column: usize },
+}
+
+impl ParseError {
+    /// Returns the position of the unbalanced token.
+    pub fn position(&self) -> Position {
+        match *self {
+            ParseError::UnbalancedLoopExit { line, column } |
+            ParseError::UnbalancedLoopEntry { line, column } |
+            ParseError::UnbalancedProcedureEnd { line, column } |
+            ParseError::UnbalancedProcedureStart { line, column } => Position { line, column },
+        }
+    }
+
+    /// Returns the same error at `position`,
+    /// e.g. mapped back to the source of a `preprocess::Expansion`.
+    pub fn moved_to(&self, position: Position) -> ParseError {
+        let Position { line, column } = position;
+        match *self {
+            ParseError::UnbalancedLoopExit { .. } => {
+                ParseError::UnbalancedLoopExit { line, column }
+            }
+            ParseError::UnbalancedLoopEntry { .. } => {
+                ParseError::UnbalancedLoopEntry { line, column }
+            }
+            ParseError::UnbalancedProcedureEnd { .. } => {
+                ParseError::UnbalancedProcedureEnd { line, column }
+            }
+            ParseError::UnbalancedProcedureStart { .. } => {
+                ParseError::UnbalancedProcedureStart { line, column }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnbalancedLoopExit { line, column } => {
+                write!(f, "{:}:{:}: unbalanced {:?}", line, column, ']')
+            }
+            ParseError::UnbalancedLoopEntry { line, column } => {
+                write!(f, "{:}:{:}: unbalanced {:?}", line, column, '[')
+            }
+            ParseError::UnbalancedProcedureEnd { line, column } => {
+                write!(f, "{:}:{:}: unbalanced {:?}", line, column, ')')
+            }
+            ParseError::UnbalancedProcedureStart { line, column } => {
+                write!(f, "{:}:{:}: unbalanced {:?}", line, column, '(')
+            }
+        }
+    }
+}
+
+/// Checks the syntax of `text` without parsing it.
+/// `parse` does not panic on `text` if `check` returns `Ok`.
+pub fn check(text: &str) -> Result<(), ParseError> {
+    check_dialect(text, &Brainfuck::default())
+}
+
+/// Checks the syntax of `text` written in `dialect`.
+/// `parse_dialect` does not panic on `text` if `check_dialect` returns `Ok`.
+/// Errors report the start of the unbalanced token.
+/// Loops and procedures have to nest, so `(]` is an unbalanced `]`.
+pub fn check_dialect(text: &str, dialect: &dyn Dialect) -> Result<(), ParseError> {
+    // the open brackets, `true` for procedures
+    let mut entries = Vec::new();
+    for (instr, span) in dialect.tokenize(text) {
+        let Position { line, column } = span.start;
+        match instr {
+            LoopEntry(_) => entries.push((false, line, column)),
+            ProcedureStart(_) => entries.push((true, line, column)),
+            LoopExit(_) if entries.pop().is_none_or(|entry| entry.0) => {
+                return Err(ParseError::UnbalancedLoopExit { line, column });
+            }
+            ProcedureEnd(_) if entries.pop().is_none_or(|entry| !entry.0) => {
+                return Err(ParseError::UnbalancedProcedureEnd { line, column });
+            }
+            _ => (),
+        }
+    }
+    match entries.pop() {
+        Some((false, line, column)) => Err(ParseError::UnbalancedLoopEntry { line, column }),
+        Some((true, line, column)) => Err(ParseError::UnbalancedProcedureStart { line, column }),
+        None => Ok(()),
+    }
 }
@@ -3,7 +3,38 @@
 use ast::Program;
 use ast::Instruction::*;
 
-use parser::parse;
+use dialect::Brainfuck;
+use parser::{check, check_dialect, parse, parse_bytes, parse_reader, parse_with, parse_with_input,
+             split_input, Options, ParseError};
+use span::{Position, Span};
+
+use std::io::{self, Read};
+
+/// Reads one byte at a time, failing with `Interrupted` before each byte
+/// and with `error` at the end if given.
+struct TrickleReader<'a> {
+    bytes: &'a [u8],
+    interrupt: bool,
+    error: Option<io::ErrorKind>,
+}
+
+impl<'a> Read for TrickleReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        match (self.bytes.split_first(), self.error) {
+            (Some((&byte, rest)), _) => {
+                buf[0] = byte;
+                self.bytes = rest;
+                Ok(1)
+            }
+            (None, Some(kind)) => Err(kind.into()),
+            (None, None) => Ok(0),
+        }
+    }
+}
 
 #[test]
 fn skip_white_space() {
@@ -62,3 +93,199 @@ fn parse_unbalanced_move_left() {
 fn parse_unbalanced_move_right() {
     let _ = parse("]".to_string());
 }
+
+#[test]
+fn check_balanced() {
+    assert_eq!(Ok(()), check("+[>[-]<\n-]."));
+}
+
+#[test]
+fn check_unbalanced_loop_exit() {
+    assert_eq!(Err(ParseError::UnbalancedLoopExit { line: 2, column: 3 }),
+               check("[]\n+-]"));
+}
+
+#[test]
+fn check_unbalanced_loop_entry() {
+    assert_eq!(Err(ParseError::UnbalancedLoopEntry { line: 1, column: 2 }),
+               check("+[[\n]"));
+}
+
+#[test]
+fn check_error_message() {
+    assert_eq!("3:1: unbalanced ']'",
+               check("\n\n]").unwrap_err().to_string());
+}
+
+#[test]
+fn skip_debug_char() {
+    assert_eq!(Program { instructions: vec![Inc(1u8), Output] },
+               parse("+#.".to_string()));
+}
+
+#[test]
+fn parse_debug_char() {
+    let options = Options {
+        debug: true,
+        ..Options::default()
+    };
+    let (p, _) = parse_with("+#.", &options);
+    assert_eq!(Program { instructions: vec![Inc(1u8), Breakpoint, Output] },
+               p);
+}
+
+#[test]
+fn parse_with_spans() {
+    let (p, source_map) = parse_with("+ x\n [\n\t-]", &Options::default());
+    assert_eq!(parse("+[-]".to_string()), p);
+    let at = |line, column| Span::at(Position { line, column });
+    assert_eq!(&[at(1, 1), at(2, 2), at(3, 2), at(3, 3)],
+               source_map.spans());
+}
+
+#[test]
+#[should_panic(expected = "Unbalanced ']' at 2:3")]
+fn parse_unbalanced_loop_exit_location() {
+    let _ = parse("[]\n++]".to_string());
+}
+
+#[test]
+#[should_panic(expected = "Unbalanced '[' at 1:3")]
+fn parse_unbalanced_loop_entry_location() {
+    let _ = parse("+ [[\n]".to_string());
+}
+
+#[test]
+fn skip_pbrain_chars() {
+    assert_eq!(Program { instructions: vec![Inc(1u8), Output] },
+               parse("(+:Y).".to_string()));
+}
+
+#[test]
+fn parse_extended_chars() {
+    let text = "+@$!}{~^&|.";
+    assert_eq!(Program { instructions: vec![Inc(1u8), Output] },
+               parse(text.to_string()));
+    let options = Options {
+        extended: true,
+        ..Options::default()
+    };
+    assert_eq!(Program {
+                   instructions: vec![Inc(1u8), End, Store, Load, ShiftRight, ShiftLeft, Not,
+                                      Xor, And, Or, Output],
+               },
+               parse_with(text, &options).0);
+}
+
+#[test]
+fn split_inline_input() {
+    assert_eq!((&b",[.,]"[..], Some(&b"a!b\n"[..])), split_input(b",[.,]!a!b\n"));
+    assert_eq!((&b"+."[..], None), split_input(b"+."));
+}
+
+#[test]
+fn parse_inline_input() {
+    let options = Options {
+        inline_input: true,
+        ..Options::default()
+    };
+    let (p, source_map, input) = parse_with_input("+\n.!+-", &options);
+    assert_eq!(Program { instructions: vec![Inc(1u8), Output] }, p);
+    assert_eq!(2, source_map.spans().len());
+    assert_eq!(Some(b"+-".to_vec()), input);
+    assert_eq!(None, parse_with_input("+.", &options).2);
+    // without the option `!` is a comment
+    let (p, _, input) = parse_with_input("+.!+", &Options::default());
+    assert_eq!((3, None), (p.instructions.len(), input));
+}
+
+#[test]
+fn parse_pbrain_procedures() {
+    let options = Options {
+        pbrain: true,
+        ..Options::default()
+    };
+    let (p, _) = parse_with("+([-]):", &options);
+    assert_eq!(Program {
+                   instructions: vec![Inc(1u8),
+                                      ProcedureStart(5),
+                                      LoopEntry(4),
+                                      Dec(1),
+                                      LoopExit(2),
+                                      ProcedureEnd(1),
+                                      Call],
+               },
+               p);
+}
+
+#[test]
+#[should_panic(expected = "Unbalanced ')' at 1:3")]
+fn parse_procedure_across_loop() {
+    let options = Options {
+        pbrain: true,
+        ..Options::default()
+    };
+    let _ = parse_with("([)]", &options);
+}
+
+#[test]
+fn check_unbalanced_procedures() {
+    let pbrain = Brainfuck {
+        pbrain: true,
+        ..Brainfuck::default()
+    };
+    assert_eq!(Ok(()), check_dialect("(:[()])", &pbrain));
+    assert_eq!(Err(ParseError::UnbalancedProcedureStart { line: 1, column: 1 }),
+               check_dialect("(()", &pbrain));
+    assert_eq!(Err(ParseError::UnbalancedProcedureEnd { line: 2, column: 1 }),
+               check_dialect("[\n)]", &pbrain));
+    assert_eq!(Err(ParseError::UnbalancedLoopExit { line: 1, column: 2 }),
+               check_dialect("(])", &pbrain));
+    assert_eq!("1:1: unbalanced '('",
+               check_dialect("(", &pbrain).unwrap_err().to_string());
+}
+
+#[test]
+fn parse_bytes_like_text() {
+    let text = "++[>+<-]>.\n,[.,] some comment";
+    assert_eq!(parse(text.to_string()), parse_bytes(text.as_bytes()));
+    assert_eq!(Program { instructions: vec![] }, parse_bytes(b""));
+    // the commands of the other dialects are comments
+    assert_eq!(parse("+.".to_string()), parse_bytes(b"#(+:)Y@$!{.}~^&|"));
+}
+
+#[test]
+fn parse_bytes_skips_non_ascii() {
+    // Latin-1 comments are not valid UTF-8
+    let bytes = b"\xe9t\xe9 +\xff[\x80-\xbf]\xab.";
+    assert_eq!(parse("+[-].".to_string()), parse_bytes(bytes));
+}
+
+#[test]
+#[should_panic(expected = "Unbalanced ']' at 2:4")]
+fn parse_bytes_unbalanced_location() {
+    // each of the two bytes of \u{e9} counts as a column
+    let _ = parse_bytes("[]\n\u{e9}+]".as_bytes());
+}
+
+#[test]
+fn parse_reader_in_chunks() {
+    let text = "+".repeat(10_000) + "[>,.<-]";
+    let reader = TrickleReader {
+        bytes: text.as_bytes(),
+        interrupt: false,
+        error: None,
+    };
+    assert_eq!(parse(text.clone()), parse_reader(reader).unwrap());
+    assert_eq!(parse(text.clone()), parse_reader(io::Cursor::new(text.as_bytes())).unwrap());
+}
+
+#[test]
+fn parse_reader_fails() {
+    let reader = TrickleReader {
+        bytes: b"+-",
+        interrupt: false,
+        error: Some(io::ErrorKind::InvalidData),
+    };
+    assert_eq!(io::ErrorKind::InvalidData, parse_reader(reader).unwrap_err().kind());
+}
@@ -0,0 +1,415 @@
+//! Macro preprocessor `preprocess::expand` for writing larger programs.
+//!
+//! The preprocessor expands a source text to plain brainfuck before parsing:
+//! ```text
+//! #include "lib.bf"
+//! #define LETTER 8
+//! #define CLEAR [-]
+//! #define ADD(n) +*n
+//! #macro MOVE(distance)
+//! [->*distance+<*distance]
+//! #end
+//! ADD(LETTER) [>ADD(LETTER)<-] MOVE(2) >>+. CLEAR
+//! ```
+//! * `#include "FILE"` expands FILE, relative to the directory of the
+//!   including file, at its place.
+//! * `#define NAME TEXT` defines a constant or a macro, `#define NAME(A, B)
+//!   TEXT` a macro with the parameters `A` and `B`.
+//! * `#macro NAME(A, B)` defines a macro whose body are the following lines
+//!   up to `#end`.
+//! * A defined `NAME` is replaced by its expanded text, a macro with
+//!   parameters only if it is followed by `(ARG, ...)`. Each parameter in
+//!   the text is replaced by its argument first.
+//! * `C*N` repeats the character or macro `C` `N` times, `N` is a number or
+//!   a constant.
+//!
+//! Directives take whole lines, anything else is kept, so comments stay
+//! comments. `Expansion` maps each position of the expanded text back to the
+//! source text: text from macros and included files maps to the position of
+//! the macro or the `#include` in the source text.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use ast::Program;
+use dialect::Dialect;
+use parser::{self, ParseError};
+use span::{Position, SourceMap, Span};
+
+/// Maximum nesting depth of macros and includes.
+/// Deeper nesting fails instead of expanding recursive macros forever.
+pub const MAX_DEPTH: usize = 64;
+
+/// Maximum length in bytes of the expanded text.
+/// A longer text fails instead of nesting repetitions to gigabytes.
+pub const MAX_LEN: usize = 1 << 24;
+
+/// A macro defined by `#define` or `#macro`.
+#[derive(Debug, Clone)]
+struct Macro {
+    params: Vec<String>,
+    body: String,
+}
+
+/// A preprocessed text and the source position of each of its characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    text: String,
+    /// The source position of each character of each line of `text`.
+    positions: Vec<Vec<Position>>,
+}
+
+impl Expansion {
+    /// Returns the expanded text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the source position of `position` in the expanded text.
+    pub fn position(&self, position: Position) -> Position {
+        self.positions
+            .get(position.line.wrapping_sub(1))
+            .and_then(|line| line.get(position.column.wrapping_sub(1)))
+            .cloned()
+            .unwrap_or(position)
+    }
+
+    /// Returns the source span of `span` in the expanded text.
+    pub fn span(&self, span: Span) -> Span {
+        Span {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+
+    /// Maps the spans of `source_map` of the expanded text to the source text.
+    pub fn source_map(&self, source_map: &SourceMap) -> SourceMap {
+        SourceMap::new(source_map.spans().iter().map(|span| self.span(*span)).collect())
+    }
+
+    /// Checks and parses the expanded text written in `dialect` like
+    /// `parser::check_dialect` and `parser::parse_dialect`.
+    /// Errors and source positions refer to the source text.
+    pub fn parse_dialect(&self, dialect: &dyn Dialect) -> Result<(Program, SourceMap), ParseError> {
+        parser::check_dialect(&self.text, dialect)
+            .map_err(|err| err.moved_to(self.position(err.position())))?;
+        let (program, source_map) = parser::parse_dialect(&self.text, dialect);
+        Ok((program, self.source_map(&source_map)))
+    }
+}
+
+/// Expands the file `path`, see `expand`.
+pub fn expand_file(path: &Path) -> Result<Expansion, String> {
+    expand(&read(path)?, path)
+}
+
+/// Expands the source `text` of the file `path`, see the module
+/// documentation. `path` locates the included files and the errors.
+///
+/// Fails on invalid directives, unreadable includes, wrong numbers of macro
+/// arguments, macros or includes nested deeper than `MAX_DEPTH` and
+/// expanded texts longer than `MAX_LEN`.
+/// Errors start with `FILE:LINE:COLUMN`.
+pub fn expand(text: &str, path: &Path) -> Result<Expansion, String> {
+    let mut expander = Expander {
+        macros: HashMap::new(),
+        text: String::new(),
+        positions: vec![Vec::new()],
+    };
+    expander.expand_source(text, path, None, 0)?;
+    Ok(Expansion {
+           text: expander.text,
+           positions: expander.positions,
+       })
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|err| format!("Cannot read {:} because {:}", path.display(), err))?;
+    Ok(text)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns the directive and its arguments if `line` is a directive line.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if !line.starts_with('#') {
+        return None;
+    }
+    let end = line.find(char::is_whitespace).unwrap_or(line.len());
+    match &line[1..end] {
+        name @ "include" | name @ "define" | name @ "macro" | name @ "end" => {
+            Some((name, line[end..].trim()))
+        }
+        _ => None,
+    }
+}
+
+/// Parses the head `NAME` or `NAME(A, B)` of a macro definition.
+/// Returns the name, the parameters and the text behind the head.
+fn parse_head(text: &str) -> Result<(String, Vec<String>, &str), String> {
+    let end = text.find(|c| !is_ident(c)).unwrap_or(text.len());
+    let name = &text[..end];
+    if !name.starts_with(is_ident_start) {
+        return Err(format!("Invalid macro name {:?}", name));
+    }
+    let rest = &text[end..];
+    if !rest.starts_with('(') {
+        return Ok((name.to_string(), Vec::new(), rest.trim()));
+    }
+    let close = rest.find(')').ok_or_else(|| format!("Missing ')' of macro {:}", name))?;
+    let params: Vec<String> = rest[1..close]
+        .split(',')
+        .map(|param| param.trim().to_string())
+        .collect();
+    if let Some(param) = params.iter().find(|param| !param.starts_with(is_ident_start) ||
+                                               !param.chars().all(is_ident)) {
+        return Err(format!("Invalid parameter {:?} of macro {:}", param, name));
+    }
+    Ok((name.to_string(), params, rest[close + 1..].trim()))
+}
+
+/// Returns the characters of `text` with their positions.
+fn located(text: &str, line: usize) -> Vec<(Position, char)> {
+    text.chars()
+        .enumerate()
+        .map(|(idx, c)| (Position { line, column: idx + 1 }, c))
+        .collect()
+}
+
+/// Replaces each parameter `params[i]` in `body` by `args[i]`.
+fn substitute(body: &str, params: &[String], args: &[String]) -> String {
+    let mut text = String::new();
+    let mut word = String::new();
+    for c in body.chars().chain(Some('\n')) {
+        if is_ident(c) && (!word.is_empty() || is_ident_start(c)) {
+            word.push(c);
+            continue;
+        }
+        match params.iter().position(|param| *param == word) {
+            Some(idx) => text.push_str(&args[idx]),
+            None => text.push_str(&word),
+        }
+        word.clear();
+        text.push(c);
+    }
+    text.pop();
+    text
+}
+
+/// Parses the arguments `(ARG, ...)` starting at `chars[start]`.
+/// Returns the arguments and the index behind the closing `)`,
+/// `None` if the `)` is missing.
+fn parse_args(chars: &[(Position, char)], start: usize) -> Option<(Vec<String>, usize)> {
+    let mut args = vec![String::new()];
+    let mut depth = 0;
+    for (idx, &(_, c)) in chars.iter().enumerate().skip(start + 1) {
+        match c {
+            ')' if depth == 0 => {
+                let args = args.iter().map(|arg| arg.trim().to_string()).collect();
+                return Some((args, idx + 1));
+            }
+            ',' if depth == 0 => {
+                args.push(String::new());
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        args.last_mut().expect("An argument").push(c);
+    }
+    None
+}
+
+struct Expander {
+    macros: HashMap<String, Macro>,
+    text: String,
+    positions: Vec<Vec<Position>>,
+}
+
+impl Expander {
+    fn push(&mut self, c: char, position: Position) {
+        self.text.push(c);
+        self.positions.last_mut().expect("A line").push(position);
+        if c == '\n' {
+            self.positions.push(Vec::new());
+        }
+    }
+
+    /// Expands the `text` of the file `path`. Without `origin` the expanded
+    /// text maps to its own positions, otherwise to `origin`.
+    fn expand_source(&mut self,
+                     text: &str,
+                     path: &Path,
+                     origin: Option<Position>,
+                     depth: usize)
+                     -> Result<(), String> {
+        let mut lines = text.split_inclusive('\n').enumerate();
+        while let Some((idx, line)) = lines.next() {
+            let position = Position {
+                line: idx + 1,
+                column: 1,
+            };
+            let error = |msg: String| format!("{:}:{:}: {:}", path.display(), position, msg);
+            match directive(line) {
+                Some(("include", name)) => {
+                    if !(name.len() >= 2 && name.starts_with('"') && name.ends_with('"')) {
+                        return Err(error(format!("Expected #include \"FILE\", got {:?}", name)));
+                    }
+                    if depth >= MAX_DEPTH {
+                        return Err(error(format!("Includes nested deeper than {:}", MAX_DEPTH)));
+                    }
+                    let included = path.parent()
+                        .unwrap_or_else(|| Path::new(""))
+                        .join(&name[1..name.len() - 1]);
+                    let text = read(&included).map_err(&error)?;
+                    let origin = Some(origin.unwrap_or(position));
+                    self.expand_source(&text, &included, origin, depth + 1)?;
+                }
+                Some(("define", head)) => {
+                    let (name, params, body) = parse_head(head).map_err(&error)?;
+                    self.macros.insert(name, Macro { params, body: body.to_string() });
+                }
+                Some(("macro", head)) => {
+                    let (name, params, rest) = parse_head(head).map_err(&error)?;
+                    if !rest.is_empty() {
+                        return Err(error(format!("Unexpected {:?} behind #macro {:}", rest, name)));
+                    }
+                    let mut body = String::new();
+                    loop {
+                        match lines.next() {
+                            Some((_, line)) if directive(line).is_some_and(|d| d.0 == "end") => {
+                                break
+                            }
+                            Some((_, line)) => body.push_str(line),
+                            None => return Err(error(format!("Missing #end of macro {:}", name))),
+                        }
+                    }
+                    if body.ends_with('\n') {
+                        body.pop();
+                    }
+                    self.macros.insert(name, Macro { params, body });
+                }
+                Some(_) => return Err(error("#end without #macro".to_string())),
+                None => self.expand_text(&located(line, position.line), path, origin, depth)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a repetition `*N` at `chars[*idx]` and moves `idx` behind it.
+    /// Returns `1` without a repetition.
+    fn count(&self, chars: &[(Position, char)], idx: &mut usize) -> Result<usize, String> {
+        if chars.get(*idx).is_none_or(|&(_, c)| c != '*') {
+            return Ok(1);
+        }
+        let start = *idx + 1;
+        let end = chars[start..]
+            .iter()
+            .position(|&(_, c)| !is_ident(c))
+            .map_or(chars.len(), |len| start + len);
+        let word: String = chars[start..end].iter().map(|&(_, c)| c).collect();
+        let number = if word.starts_with(|c: char| c.is_ascii_digit()) {
+            word.parse().map_err(|_| format!("Invalid count {:?}", word))?
+        } else {
+            match self.macros.get(&word) {
+                Some(constant) if constant.params.is_empty() => {
+                    constant
+                        .body
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Constant {:} is not a count", word))?
+                }
+                _ => return Ok(1),
+            }
+        };
+        *idx = end;
+        Ok(number)
+    }
+
+    /// Expands the characters `chars` of one line or macro body of the file
+    /// `path`, see `expand_source` for `origin`.
+    fn expand_text(&mut self,
+                   chars: &[(Position, char)],
+                   path: &Path,
+                   origin: Option<Position>,
+                   depth: usize)
+                   -> Result<(), String> {
+        let mut idx = 0;
+        while idx < chars.len() {
+            let (position, c) = chars[idx];
+            let position = origin.unwrap_or(position);
+            let error = |msg: String| format!("{:}:{:}: {:}", path.display(), position, msg);
+            if !is_ident_start(c) {
+                idx += 1;
+                let count = self.count(chars, &mut idx).map_err(&error)?;
+                if count.saturating_mul(c.len_utf8()) > MAX_LEN - self.text.len() {
+                    return Err(error(format!("Expansion longer than {:} bytes", MAX_LEN)));
+                }
+                for _ in 0..count {
+                    self.push(c, position);
+                }
+                continue;
+            }
+            let start = idx;
+            while idx < chars.len() && is_ident(chars[idx].1) {
+                idx += 1;
+            }
+            let name: String = chars[start..idx].iter().map(|&(_, c)| c).collect();
+            let definition = match self.macros.get(&name) {
+                Some(definition) if definition.params.is_empty() ||
+                                    chars.get(idx).is_some_and(|&(_, c)| c == '(') => {
+                    definition.clone()
+                }
+                _ => {
+                    if idx - start > MAX_LEN - self.text.len() {
+                        return Err(error(format!("Expansion longer than {:} bytes", MAX_LEN)));
+                    }
+                    for &(position, c) in &chars[start..idx] {
+                        self.push(c, origin.unwrap_or(position));
+                    }
+                    continue;
+                }
+            };
+            let body = if definition.params.is_empty() {
+                definition.body
+            } else {
+                let (args, next) = parse_args(chars, idx)
+                    .ok_or_else(|| error(format!("Missing ')' of {:}", name)))?;
+                if args.len() != definition.params.len() {
+                    return Err(error(format!("{:} expects {:} arguments, got {:}",
+                                             name,
+                                             definition.params.len(),
+                                             args.len())));
+                }
+                idx = next;
+                substitute(&definition.body, &definition.params, &args)
+            };
+            if depth >= MAX_DEPTH {
+                return Err(error(format!("Macros nested deeper than {:}", MAX_DEPTH)));
+            }
+            let body = located(&body, 1);
+            for _ in 0..self.count(chars, &mut idx).map_err(&error)? {
+                let len = self.text.len();
+                self.expand_text(&body, path, Some(position), depth + 1)?;
+                if self.text.len() == len {
+                    // the repetitions of an empty expansion are empty too
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
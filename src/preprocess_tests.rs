@@ -0,0 +1,137 @@
+//! Tests for the macro preprocessor `preprocess::expand`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ast::Instruction::*;
+use dialect::Brainfuck;
+use parser::ParseError;
+use preprocess::*;
+use span::{Position, Span};
+
+fn expand_text(text: &str) -> String {
+    expand(text, Path::new("main.bf")).unwrap().text().to_string()
+}
+
+fn expand_error(text: &str) -> String {
+    expand(text, Path::new("main.bf")).unwrap_err()
+}
+
+fn position(line: usize, column: usize) -> Position {
+    Position { line, column }
+}
+
+/// Creates the directory `name` in the temp directory with `files`.
+fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = env::temp_dir().join(name);
+    fs::create_dir_all(&dir).unwrap();
+    for &(fname, text) in files {
+        File::create(dir.join(fname))
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+    dir
+}
+
+#[test]
+fn plain_text_unchanged() {
+    let text = "+[->+<] a comment # with a breakpoint\n.";
+    assert_eq!(text, expand_text(text));
+}
+
+#[test]
+fn repetitions() {
+    assert_eq!("+++>>.", expand_text("+*3>*2."));
+    assert_eq!("+ * 3 a*b", expand_text("+ * 3 a*b"));
+    assert_eq!("--\n", expand_text("#define N 2\n-*N\n"));
+}
+
+#[test]
+fn constants_and_macros() {
+    let text = "#define CLEAR [-]\n#define ADD(n, right) >*right+*n<*right\nADD(3, 2) CLEAR\n";
+    assert_eq!(">>+++<< [-]\n", expand_text(text));
+    // macros with parameters are only expanded with arguments
+    assert_eq!("ADD a\n", expand_text("#define ADD(n) +*n\nADD a\n"));
+}
+
+#[test]
+fn multi_line_macro() {
+    let text = "#macro MOVE(distance)\n[->*distance+\n<*distance]\n#end\nMOVE(2)*2.";
+    assert_eq!("[->>+\n<<][->>+\n<<].", expand_text(text));
+}
+
+#[test]
+fn nested_macros() {
+    let text = "#define LETTER 8\n#define ADD(n) +*n\n#define TWICE(x) x x\nTWICE(ADD(LETTER))";
+    assert_eq!("++++++++ ++++++++", expand_text(text));
+}
+
+#[test]
+fn include_files() {
+    let dir = temp_dir("bf_interp_preprocess_include",
+                       &[("lib.bf", "#define CLEAR [-]\n+*2\n"),
+                         ("main.bf", "#include \"lib.bf\"\nCLEAR.\n")]);
+    let expansion = expand_file(&dir.join("main.bf")).unwrap();
+    assert_eq!("++\n[-].\n", expansion.text());
+    // the included text maps to the `#include`
+    assert_eq!(position(1, 1), expansion.position(position(1, 2)));
+    assert_eq!(position(2, 1), expansion.position(position(2, 2)));
+}
+
+#[test]
+fn source_positions() {
+    let text = "#define CLEAR [-]\n+\n CLEAR .";
+    let expansion = expand(text, Path::new("main.bf")).unwrap();
+    let (program, source_map) = expansion.parse_dialect(&Brainfuck::default()).unwrap();
+    assert_eq!(vec![Inc(1), LoopEntry(3), Dec(1), LoopExit(1), Output],
+               program.instructions);
+    assert_eq!(&[Span::at(position(2, 1)),
+                 Span::at(position(3, 2)),
+                 Span::at(position(3, 2)),
+                 Span::at(position(3, 2)),
+                 Span::at(position(3, 8))],
+               source_map.spans());
+}
+
+#[test]
+fn parse_errors_in_source() {
+    let expansion = expand("#define OPEN [\n\n+ OPEN", Path::new("main.bf")).unwrap();
+    assert_eq!(Err(ParseError::UnbalancedLoopEntry { line: 3, column: 3 }),
+               expansion.parse_dialect(&Brainfuck::default()));
+}
+
+#[test]
+fn preprocessor_errors() {
+    assert_eq!("main.bf:2:3: ADD expects 2 arguments, got 1",
+               expand_error("#define ADD(n, m) +*n\n+ ADD(1)"));
+    assert_eq!("main.bf:2:1: Missing ')' of ADD", expand_error("#define ADD(n) +\nADD(1"));
+    assert_eq!("main.bf:1:1: Missing #end of macro M", expand_error("#macro M\n+"));
+    assert_eq!("main.bf:1:1: #end without #macro", expand_error("#end"));
+    assert_eq!("main.bf:1:1: Invalid parameter \"1\" of macro M",
+               expand_error("#define M(1) +"));
+    assert_eq!("main.bf:2:1: Constant N is not a count", expand_error("#define N +\n+*N"));
+    assert!(expand_error("#include \"no such file.bf\"").starts_with("main.bf:1:1: Cannot read"));
+}
+
+#[test]
+fn recursion_fails() {
+    assert_eq!(format!("main.bf:2:1: Macros nested deeper than {:}", MAX_DEPTH),
+               expand_error("#define LOOP +LOOP\nLOOP"));
+    let dir = temp_dir("bf_interp_preprocess_recursion",
+                       &[("self.bf", "#include \"self.bf\"\n")]);
+    let err = expand_file(&dir.join("self.bf")).unwrap_err();
+    assert!(err.ends_with(&format!("Includes nested deeper than {:}", MAX_DEPTH)), "{:}", err);
+}
+
+#[test]
+fn long_expansion_fails() {
+    let msg = format!("main.bf:3:1: Expansion longer than {:} bytes", MAX_LEN);
+    assert_eq!(msg, expand_error("#define A +*1000\n#define B A*1000\nB*1000"));
+    assert_eq!(msg, expand_error("#define A add\n#define B A*10000\nB*10000"));
+    assert_eq!("main.bf:1:1: Expansion longer than 16777216 bytes",
+               expand_error(&format!("+*{:}", MAX_LEN + 1)));
+    assert_eq!("", expand_text("#define E\n#define F E*1000000\nF*1000000"));
+}
@@ -0,0 +1,185 @@
+//! Printer `printer::to_bf_source` that turns a `Program` back into brainfuck source.
+//!
+//! Optimized instructions are expanded into canonical brainfuck:
+//! `Inc(3)` is printed as `+++`, `MoveLeft(2)` as `<<`.
+//! Thus, parsing the printed source yields the unoptimized program again
+//! and optimizing that yields the original optimized program.
+
+use std::fmt;
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+
+/// Layout options for `to_bf_source`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    /// Maximum number of characters per line including indentation.
+    /// `0` does not wrap lines.
+    pub width: usize,
+    /// Number of spaces to indent per loop or procedure depth.
+    /// If not `0`, each `[` and `]` is put on a line of its own and
+    /// the loop body is indented by one level.
+    /// `0` prints loops inline.
+    pub indent: usize,
+}
+
+impl Default for Options {
+    /// Prints everything on one line.
+    fn default() -> Options {
+        Options {
+            width: 0,
+            indent: 0,
+        }
+    }
+}
+
+/// Returns the brainfuck command of `instr` and how often it is repeated.
+pub(crate) fn command(instr: Instruction) -> (char, usize) {
+    match instr {
+        MoveLeft(offset) => ('<', offset),
+        MoveRight(offset) => ('>', offset),
+        Inc(val) => ('+', val as usize),
+        Dec(val) => ('-', val as usize),
+        Input => (',', 1),
+        Output => ('.', 1),
+        LoopEntry(_) => ('[', 1),
+        LoopExit(_) => (']', 1),
+        Breakpoint => ('#', 1),
+        ProcedureStart(_) => ('(', 1),
+        ProcedureEnd(_) => (')', 1),
+        Call => (':', 1),
+        Fork => ('Y', 1),
+        End => ('@', 1),
+        Store => ('$', 1),
+        Load => ('!', 1),
+        ShiftRight => ('}', 1),
+        ShiftLeft => ('{', 1),
+        Not => ('~', 1),
+        Xor => ('^', 1),
+        And => ('&', 1),
+        Or => ('|', 1),
+    }
+}
+
+/// Keeps track of the current line while printing.
+pub(crate) struct Printer {
+    options: Options,
+    out: String,
+    /// number of characters in the current line
+    column: usize,
+    /// loop and procedure nesting depth
+    depth: usize,
+}
+
+impl Printer {
+    pub(crate) fn new(options: Options) -> Printer {
+        Printer {
+            options,
+            out: String::new(),
+            column: 0,
+            depth: 0,
+        }
+    }
+
+    fn newline(&mut self) {
+        if self.column > 0 {
+            self.out.push('\n');
+        }
+        self.column = 0;
+    }
+
+    fn push(&mut self, c: char) {
+        if self.column == 0 {
+            let prefix = self.depth * self.options.indent;
+            self.out.push_str(&" ".repeat(prefix));
+            self.column = prefix;
+        }
+        self.out.push(c);
+        self.column += 1;
+        if self.options.width > 0 && self.column >= self.options.width {
+            self.newline();
+        }
+    }
+
+    pub(crate) fn print(&mut self, instr: Instruction) {
+        let (c, count) = command(instr);
+        if self.options.indent > 0 {
+            match instr {
+                LoopEntry(_) | ProcedureStart(_) => {
+                    self.newline();
+                    self.push(c);
+                    self.newline();
+                    self.depth += 1;
+                    return;
+                }
+                LoopExit(_) | ProcedureEnd(_) => {
+                    self.newline();
+                    self.depth = self.depth.saturating_sub(1);
+                    self.push(c);
+                    self.newline();
+                    return;
+                }
+                _ => (),
+            }
+        }
+        for _ in 0..count {
+            self.push(c);
+        }
+    }
+
+    /// Prints each line of the comment `text` trimmed on a line of its own,
+    /// indented like the surrounding commands.
+    /// Comment lines are never wrapped.
+    pub(crate) fn comment(&mut self, text: &str) {
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            self.newline();
+            let prefix = self.depth * self.options.indent;
+            self.out.push_str(&" ".repeat(prefix));
+            self.out.push_str(line);
+            self.column = prefix + line.len();
+            self.newline();
+        }
+    }
+
+    /// Returns the printed text.
+    /// Multi-line layouts are terminated with a newline.
+    pub(crate) fn finish(mut self) -> String {
+        if self.column > 0 && (self.options.width > 0 || self.options.indent > 0) {
+            self.out.push('\n');
+        }
+        self.out
+    }
+}
+
+/// Renders `program` as brainfuck source with the given layout `options`.
+/// The source consists only of the 8 brainfuck commands, spaces and newlines
+/// (and the debug character `#` for `Breakpoint`).
+pub fn to_bf_source(program: &Program, options: &Options) -> String {
+    let mut printer = Printer::new(*options);
+    for instr in &program.instructions {
+        printer.print(*instr);
+    }
+    printer.finish()
+}
+
+impl fmt::Display for Instruction {
+    /// Prints the instruction as brainfuck commands, e.g. `Inc(3)` as `+++`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (c, count) = command(*self);
+        for _ in 0..count {
+            write!(f, "{:}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Program {
+    /// Prints the program as brainfuck source on one line
+    /// (see `to_bf_source` for more layout options).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for instr in &self.instructions {
+            write!(f, "{:}", instr)?;
+        }
+        Ok(())
+    }
+}
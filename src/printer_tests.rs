@@ -0,0 +1,71 @@
+//! Tests for the printer `printer::to_bf_source`.
+
+use ast::Program;
+use ast::Instruction::*;
+use opt::optimize;
+use parser::parse;
+use printer::{to_bf_source, Options};
+
+const HELLO: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.\
+                   +++.------.--------.>>+.>++.";
+
+#[test]
+fn display_instruction() {
+    assert_eq!("+++", Inc(3).to_string());
+    assert_eq!("--", Dec(2).to_string());
+    assert_eq!("<<<<", MoveLeft(4).to_string());
+    assert_eq!(">", MoveRight(1).to_string());
+    assert_eq!(",", Input.to_string());
+    assert_eq!(".", Output.to_string());
+    assert_eq!("[", LoopEntry(7).to_string());
+    assert_eq!("]", LoopExit(3).to_string());
+    assert_eq!("#", Breakpoint.to_string());
+}
+
+#[test]
+fn display_optimized_program() {
+    let p = Program { instructions: vec![Inc(3), LoopEntry(3), Dec(1), LoopExit(1), MoveRight(2), Output] };
+    assert_eq!("+++[-]>>.", p.to_string());
+}
+
+#[test]
+fn round_trip_unoptimized() {
+    let p = parse(HELLO.to_string());
+    assert_eq!(HELLO, p.to_string());
+    assert_eq!(p, parse(p.to_string()));
+}
+
+#[test]
+fn round_trip_optimized() {
+    let p = optimize(&parse(HELLO.to_string()));
+    assert_eq!(p, optimize(&parse(p.to_string())));
+}
+
+#[test]
+fn wrap_lines() {
+    let p = parse("++++++++++>>.".to_string());
+    let options = Options { width: 4, indent: 0 };
+    assert_eq!("++++\n++++\n++>>\n.\n", to_bf_source(&p, &options));
+}
+
+#[test]
+fn indent_loops() {
+    let p = optimize(&parse("++[->+[-]<],.".to_string()));
+    let options = Options { width: 0, indent: 2 };
+    assert_eq!("++\n[\n  ->+\n  [\n    -\n  ]\n  <\n]\n,.\n",
+               to_bf_source(&p, &options));
+}
+
+#[test]
+fn indent_and_wrap() {
+    let p = parse("[++++++]".to_string());
+    let options = Options { width: 5, indent: 2 };
+    assert_eq!("[\n  +++\n  +++\n]\n", to_bf_source(&p, &options));
+}
+
+#[test]
+fn round_trip_formatted() {
+    let p = parse(HELLO.to_string());
+    let options = Options { width: 20, indent: 4 };
+    assert_eq!(p, parse(to_bf_source(&p, &options)));
+}
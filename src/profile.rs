@@ -0,0 +1,192 @@
+//! Execution profiler `profile::profile`.
+//!
+//! The profiler runs a program like `interp::interp` and counts how often
+//! each instruction executes. From the counts it derives per-loop statistics
+//! and a hot-spot report against the source lines (`Profile::report`).
+//! The counts refer to the instructions of the profiled program, so an
+//! optimized `Inc(3)` counts once per execution, not three times.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::io::{Read, Write};
+
+use ast::Program;
+use ast::Instruction::*;
+use interp;
+use span::SourceMap;
+use tape::{Config, Tape};
+
+/// Maximum number of characters of a source line shown in the report.
+const SOURCE_WIDTH: usize = 60;
+
+/// Execution statistics of one loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopProfile {
+    /// Index of the `LoopEntry` instruction.
+    pub entry: usize,
+    /// Index of the `LoopExit` instruction.
+    pub exit: usize,
+    /// How often the loop was reached, including skipped loops.
+    pub entries: u64,
+    /// How often the loop body ran.
+    pub iterations: u64,
+    /// Executed instructions from `entry` to `exit`, including nested loops.
+    pub executed: u64,
+}
+
+impl LoopProfile {
+    /// Returns the average number of iterations per entry.
+    pub fn average_iterations(&self) -> f64 {
+        if self.entries == 0 {
+            0.0
+        } else {
+            self.iterations as f64 / self.entries as f64
+        }
+    }
+}
+
+/// Execution counts of a program run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    counts: Vec<u64>,
+    loops: Vec<LoopProfile>,
+}
+
+/// Runs `program` like `interp::interp` and returns its profile.
+pub fn profile(program: &Program, input: &mut dyn Read, output: &mut dyn Write) -> Profile {
+    profile_with(program,
+                 &mut Tape::new(&Config::default()),
+                 input,
+                 output)
+}
+
+/// Runs `program` like `interp::interp_with` on `tape` and returns its profile.
+///
+/// # Panics
+///
+/// Panics like `interp::interp_with`, the profile is lost then.
+pub fn profile_with(program: &Program,
+                    tape: &mut Tape,
+                    input: &mut dyn Read,
+                    output: &mut dyn Write)
+                    -> Profile {
+    let mut counts = vec![0; program.instructions.len()];
+    let mut pc: usize = 0;
+
+    while pc < program.instructions.len() {
+        counts[pc] += 1;
+        pc = interp::step(program, pc, tape, input, output);
+    }
+    Profile::new(program, counts)
+}
+
+impl Profile {
+    /// Creates the profile of `program` from the execution `counts`
+    /// of its instructions.
+    fn new(program: &Program, counts: Vec<u64>) -> Profile {
+        let loops = program
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(entry, instr)| match *instr {
+                            LoopEntry(exit) => {
+                                Some(LoopProfile {
+                                         entry,
+                                         exit,
+                                         entries: counts[entry],
+                                         // each iteration ends at the loop-exit
+                                         iterations: counts[exit],
+                                         executed: counts[entry..exit + 1].iter().sum(),
+                                     })
+                            }
+                            _ => None,
+                        })
+            .collect();
+        Profile { counts, loops }
+    }
+
+    /// Returns how often each instruction executed,
+    /// the count of instruction `pc` is at index `pc`.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the total number of executed instructions.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the statistics of all loops ordered by their `LoopEntry`.
+    pub fn loops(&self) -> &[LoopProfile] {
+        &self.loops
+    }
+
+    /// Returns the hot-spot report: the `top` loops and source lines
+    /// executing the most instructions.
+    /// `source_map` maps the profiled program to the source `text`.
+    pub fn report(&self, source_map: &SourceMap, text: &str, top: usize) -> String {
+        let total = self.total();
+        let percent = |count: u64| if total == 0 {
+            0.0
+        } else {
+            100.0 * count as f64 / total as f64
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        let source_line = |line: usize| {
+            let text = lines.get(line - 1).map_or("", |text| text.trim());
+            if text.chars().count() > SOURCE_WIDTH {
+                format!("{:}...", text.chars().take(SOURCE_WIDTH).collect::<String>())
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut report = format!("{:} instructions executed\n", total);
+
+        let mut loops = self.loops.clone();
+        loops.sort_by(|a, b| b.executed.cmp(&a.executed).then(a.entry.cmp(&b.entry)));
+        let _ = writeln!(report, "\nHot loops:");
+        let _ = writeln!(report,
+                         "{:>12} {:>6} {:>10} {:>12} {:>10}  source",
+                         "executed",
+                         "%",
+                         "entries",
+                         "iterations",
+                         "average");
+        for l in loops.iter().take(top).filter(|l| l.executed > 0) {
+            let location = match (source_map.span(l.entry), source_map.span(l.exit)) {
+                (Some(entry), Some(exit)) => format!("{:}", entry.merge(&exit)),
+                _ => format!("pc={:}-{:}", l.entry, l.exit),
+            };
+            let _ = writeln!(report,
+                             "{:>12} {:>5.1}% {:>10} {:>12} {:>10.1}  {:}",
+                             l.executed,
+                             percent(l.executed),
+                             l.entries,
+                             l.iterations,
+                             l.average_iterations(),
+                             location);
+        }
+
+        // the executed instructions starting on each source line
+        let mut line_counts = BTreeMap::new();
+        for (pc, count) in self.counts.iter().enumerate() {
+            if let Some(span) = source_map.span(pc) {
+                *line_counts.entry(span.start.line).or_insert(0) += *count;
+            }
+        }
+        let mut line_counts: Vec<(usize, u64)> = line_counts.into_iter().collect();
+        line_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let _ = writeln!(report, "\nHot lines:");
+        let _ = writeln!(report, "{:>12} {:>6} {:>6}  source", "executed", "%", "line");
+        for &(line, count) in line_counts.iter().take(top).filter(|&&(_, c)| c > 0) {
+            let _ = writeln!(report,
+                             "{:>12} {:>5.1}% {:>6}  {:}",
+                             count,
+                             percent(count),
+                             line,
+                             source_line(line));
+        }
+        report
+    }
+}
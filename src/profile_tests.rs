@@ -0,0 +1,76 @@
+//! Tests for the profiler `profile::profile`.
+
+use buffer::*;
+use opt::optimize_with;
+use parser::{parse_with, Options};
+use profile::{profile, LoopProfile};
+
+#[test]
+fn count_instructions() {
+    let (program, _) = parse_with("++>+.", &Options::default());
+    let mut output = Vec::new();
+    let p = profile(&program, &mut IBuffer::from_str(""), &mut output);
+    assert_eq!(&[1, 1, 1, 1, 1], p.counts());
+    assert_eq!(5, p.total());
+    assert_eq!(&[1], output.as_slice());
+}
+
+#[test]
+fn count_loops() {
+    // the inner loop runs 2 times per outer iteration, it is skipped once
+    let (program, _) = parse_with("+++[>++[-]<-]>[]", &Options::default());
+    let p = profile(&program, &mut IBuffer::from_str(""), &mut Vec::new());
+    assert_eq!(&[LoopProfile {
+                     entry: 3,
+                     exit: 12,
+                     entries: 1,
+                     iterations: 3,
+                     executed: 34,
+                 },
+                 LoopProfile {
+                     entry: 7,
+                     exit: 9,
+                     entries: 3,
+                     iterations: 6,
+                     executed: 15,
+                 },
+                 LoopProfile {
+                     entry: 14,
+                     exit: 15,
+                     entries: 1,
+                     iterations: 0,
+                     executed: 1,
+                 }],
+               p.loops());
+    assert_eq!(2.0, p.loops()[1].average_iterations());
+    assert_eq!(0.0, p.loops()[2].average_iterations());
+}
+
+#[test]
+fn count_optimized_instructions() {
+    let (program, source_map) = parse_with("+++[-]", &Options::default());
+    let (program, _) = optimize_with(&program, &source_map);
+    let p = profile(&program, &mut IBuffer::from_str(""), &mut Vec::new());
+    assert_eq!(&[1, 1, 3, 3], p.counts());
+}
+
+#[test]
+fn report_hot_spots() {
+    let text = "+++\n[>++++[-]<-]\n";
+    let (program, source_map) = parse_with(text, &Options::default());
+    let (program, source_map) = optimize_with(&program, &source_map);
+    let p = profile(&program, &mut IBuffer::from_str(""), &mut Vec::new());
+    let report = p.report(&source_map, text, 1);
+    assert_eq!("\
+44 instructions executed
+
+Hot loops:
+    executed      %    entries   iterations    average  source
+          43  97.7%          1            3        3.0  2:1-2:12
+
+Hot lines:
+    executed      %   line  source
+          43  97.7%      2  [>++++[-]<-]
+",
+               report);
+}
@@ -0,0 +1,145 @@
+//! Interactive read-eval-print loop `repl::Repl` with a persistent tape.
+//!
+//! Each line is either brainfuck code, which runs on the tape left behind
+//! by the previous lines, or one of the commands:
+//! * `:tape` shows the cells around the data-pointer,
+//! * `:ptr` shows the data-pointer,
+//! * `:reset` clears the tape and the data-pointer,
+//! * `:load FILE` runs the brainfuck program in `FILE` on the tape,
+//! * `:help` lists the commands and
+//! * `:quit` ends the REPL.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+use interp::{self, Engine};
+use opt;
+use parser;
+use tape::{Config, Tape};
+
+/// Number of cells shown left and right of the data-pointer by `:tape`.
+pub const TAPE_VIEW_RADIUS: usize = 8;
+
+const HELP: &str = "\
+Enter brainfuck code or one of the commands:
+  :tape        shows the cells around the data-pointer
+  :ptr         shows the data-pointer
+  :reset       clears the tape and the data-pointer
+  :load FILE   runs the brainfuck program in FILE
+  :help        shows this help
+  :quit        ends the REPL";
+
+/// Renders the cells within `radius` around the data-pointer of `tape`:
+/// a row of cell indices, a row of cell values and a `^` below the
+/// cell at the data-pointer.
+pub fn tape_view(tape: &Tape, radius: usize) -> String {
+    let cells = tape.cells();
+    let dataptr = tape.dataptr();
+    let begin = dataptr.saturating_sub(radius).min(cells.len());
+    let end = dataptr.saturating_add(radius + 1).min(cells.len());
+
+    let mut indices = String::new();
+    let mut values = String::new();
+    let mut marker = String::new();
+    for (idx, value) in cells[begin..end].iter().enumerate().map(|(i, v)| (begin + i, v)) {
+        let (idx_text, value_text) = (idx.to_string(), value.to_string());
+        let width = idx_text.len().max(value_text.len()) + 1;
+        indices.push_str(&format!("{:>w$}", idx_text, w = width));
+        values.push_str(&format!("{:>w$}", value_text, w = width));
+        let mark = if idx == dataptr { "^" } else { "" };
+        marker.push_str(&format!("{:>w$}", mark, w = width));
+    }
+    format!("{:}\n{:}\n{:}\n",
+            indices,
+            values,
+            marker.trim_end())
+}
+
+/// The state of the REPL: the tape persists between evaluated lines.
+pub struct Repl {
+    config: Config,
+    engine: Engine,
+    tape: Tape,
+}
+
+impl Repl {
+    /// Creates a REPL with an empty tape of configuration `config`,
+    /// running code with `engine`.
+    pub fn new(config: Config, engine: Engine) -> Repl {
+        Repl {
+            config,
+            engine,
+            tape: Tape::new(&config),
+        }
+    }
+
+    /// Returns the tape.
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    /// Checks, parses, optimizes and runs `text` on the tape.
+    fn execute(&mut self,
+               text: &str,
+               input: &mut dyn Read,
+               output: &mut dyn Write)
+               -> Result<(), String> {
+        parser::check(text).map_err(|err| err.to_string())?;
+        let program = opt::optimize(&parser::parse(text.to_string()));
+        let engine = self.engine;
+        let tape = &mut self.tape;
+        panic::catch_unwind(AssertUnwindSafe(|| {
+                                                 interp::run_with(engine,
+                                                                  &program,
+                                                                  tape,
+                                                                  input,
+                                                                  output)
+                                             }))
+                .map_err(|_| "Program aborted, the tape is kept (see :reset)".to_string())
+    }
+
+    /// Evaluates one `line` of input.
+    /// Brainfuck code reads from `input` and writes to `output`,
+    /// so do the commands.
+    ///
+    /// Returns `Ok(false)` if the REPL shall end.
+    pub fn eval(&mut self,
+                line: &str,
+                input: &mut dyn Read,
+                output: &mut dyn Write)
+                -> Result<bool, String> {
+        let line = line.trim();
+        let (command, arg) = match line.find(char::is_whitespace) {
+            Some(idx) => (&line[..idx], line[idx..].trim()),
+            None => (line, ""),
+        };
+        let written = match command {
+            ":quit" | ":q" => return Ok(false),
+            ":help" => writeln!(output, "{:}", HELP),
+            ":tape" => write!(output, "{:}", tape_view(&self.tape, TAPE_VIEW_RADIUS)),
+            ":ptr" => writeln!(output, "{:}", self.tape.dataptr()),
+            ":reset" => {
+                self.tape = Tape::new(&self.config);
+                Ok(())
+            }
+            ":load" => {
+                let mut contents = Vec::new();
+                File::open(arg)
+                    .and_then(|mut file| file.read_to_end(&mut contents))
+                    .map_err(|err| format!("Cannot read {:?} because {:}", arg, err))?;
+                self.execute(&String::from_utf8_lossy(&contents), input, output)?;
+                Ok(())
+            }
+            _ if command.starts_with(':') => {
+                return Err(format!("Unknown command {:?}, see :help", command))
+            }
+            _ => {
+                self.execute(line, input, output)?;
+                Ok(())
+            }
+        };
+        written.map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,128 @@
+//! Tests for the REPL `repl::Repl`.
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+use buffer::*;
+use interp::Engine;
+use repl::{tape_view, Repl};
+use tape::{Config, Tape};
+
+/// Evaluates `lines` and returns the output of the last line.
+fn eval_lines(repl: &mut Repl, lines: &[&str]) -> String {
+    let mut output = Vec::new();
+    for line in lines {
+        output.clear();
+        let res = repl.eval(line, &mut IBuffer::from_str("x"), &mut output);
+        assert_eq!(Ok(true), res, "line {:?}", line);
+    }
+    String::from_utf8(output).expect("output must be utf-8")
+}
+
+fn repl() -> Repl {
+    Repl::new(Config::default(), Engine::Match)
+}
+
+#[test]
+fn tape_persists_between_lines() {
+    let mut repl = repl();
+    assert_eq!("B", eval_lines(&mut repl, &["++++++++[>++++++++<-]", ">++", "."]));
+    assert_eq!(1, repl.tape().dataptr());
+    assert_eq!(66, repl.tape().get());
+}
+
+#[test]
+fn input_from_reader() {
+    let mut repl = repl();
+    assert_eq!("y", eval_lines(&mut repl, &[",+."]));
+}
+
+#[test]
+fn show_ptr() {
+    let mut repl = repl();
+    assert_eq!("3\n", eval_lines(&mut repl, &[">>>", ":ptr"]));
+}
+
+#[test]
+fn reset_tape() {
+    let mut repl = repl();
+    assert_eq!("0\n", eval_lines(&mut repl, &[">>+++", ":reset", ":ptr"]));
+    assert_eq!(0, repl.tape().get());
+}
+
+#[test]
+fn show_tape() {
+    let mut repl = repl();
+    assert_eq!(" 0 1  2 3 4 5 6 7 8 9 10\n 0 0 12 0 0 0 0 0 0 0  0\n      ^\n",
+               eval_lines(&mut repl, &[">>++++++++++++", ":tape"]));
+}
+
+#[test]
+fn tape_view_around_dataptr() {
+    let mut tape = Tape::new(&Config::default());
+    tape.move_right(20);
+    tape.inc(200);
+    assert_eq!(" 18 19  20 21 22\n  0  0 200  0  0\n         ^\n",
+               tape_view(&tape, 2));
+}
+
+#[test]
+fn tape_view_at_end_of_tape() {
+    let mut tape = Tape::new(&Config {
+                                 size: 3,
+                                 ..Config::default()
+                             });
+    tape.move_right(2);
+    assert_eq!(" 1 2\n 0 0\n   ^\n", tape_view(&tape, 1));
+}
+
+#[test]
+fn load_file() {
+    let fname = env::temp_dir().join("bf_interp_repl_load_file.bf");
+    File::create(&fname)
+        .and_then(|mut file| file.write_all(b"+++ three\n[>+<-]>"))
+        .expect("write must be ok");
+    let mut repl = repl();
+    eval_lines(&mut repl, &[&format!(":load {:}", fname.display())]);
+    assert_eq!(1, repl.tape().dataptr());
+    assert_eq!(3, repl.tape().get());
+}
+
+#[test]
+fn load_missing_file() {
+    let mut repl = repl();
+    let res = repl.eval(":load /does/not/exist.bf", &mut IBuffer::from_str(""), &mut Vec::new());
+    assert!(res.unwrap_err().starts_with("Cannot read"));
+}
+
+#[test]
+fn unbalanced_code() {
+    let mut repl = repl();
+    let res = repl.eval("+[", &mut IBuffer::from_str(""), &mut Vec::new());
+    assert_eq!(Err("1:2: unbalanced '['".to_string()), res);
+    assert_eq!(0, repl.tape().get());
+}
+
+#[test]
+fn unknown_command() {
+    let mut repl = repl();
+    assert!(repl.eval(":foo", &mut IBuffer::from_str(""), &mut Vec::new())
+                .is_err());
+}
+
+#[test]
+fn aborted_program_keeps_tape() {
+    let mut repl = repl();
+    eval_lines(&mut repl, &["+++"]);
+    let res = repl.eval("<", &mut IBuffer::from_str(""), &mut Vec::new());
+    assert!(res.is_err());
+    assert_eq!(3, repl.tape().get());
+}
+
+#[test]
+fn quit() {
+    let mut repl = repl();
+    assert_eq!(Ok(false),
+               repl.eval(":quit", &mut IBuffer::from_str(""), &mut Vec::new()));
+}
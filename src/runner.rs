@@ -0,0 +1,454 @@
+//! Test runner `runner::run_case` for brainfuck programs with specs.
+//!
+//! A test is a `NAME.bf` file with a spec: either a sidecar file `NAME.spec`
+//! in the same directory or header lines `# KEY: VALUE` at the top of
+//! `NAME.bf`. Header lines are removed before the program is parsed.
+//! `NAME.bfl` files are tests as well, their program is compiled from the
+//! structured language of `lang`.
+//! A spec consists of `KEY: VALUE` lines, empty lines are ignored:
+//! * `input: TEXT` the input of the program (default: empty),
+//! * `output: TEXT` the expected output (default: not checked),
+//! * `output-file: PATH` reads the expected output from `PATH`,
+//!   relative to the directory of the spec,
+//! * `exit: ok` or `exit: error` whether the program finishes or aborts,
+//!   e.g. by moving left of the tape (default: `ok`),
+//! * `engines: NAME, ...` the engines to run (default: all),
+//! * `dialect: NAME` the dialect of the program, e.g. `pbrain`
+//!   (default: `brainfuck`, see `dialect::lookup`),
+//! * `scheduler: NAME` the scheduler of Brainfork programs, `round-robin`
+//!   or `random:SEED` (default: `round-robin`, see `fork::Scheduler`),
+//! * `macros: true` expands the macros of the program before parsing it
+//!   (default: `false`, see `preprocess`),
+//! * `opt-level: N`, `tape-size: N`, `cell-width: BITS` and `eof: MODE`
+//!   like the options of the `bf-interp` binary.
+//!
+//! `TEXT` supports the escapes `\n`, `\r`, `\t`, `\0`, `\\` and `\xHH`.
+//! Leading and trailing whitespace of values is ignored, so use `\x20`
+//! for significant spaces at the ends.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use ast::Program;
+use boolfuck;
+use dialect::{self, Machine};
+use fork::{self, Scheduler};
+use interp::{self, Engine};
+use lang;
+use opt;
+use parser;
+use preprocess;
+use tape::{Config, Tape};
+
+/// All engines, the default of `Spec::engines`.
+pub const ENGINES: [Engine; 3] = [Engine::Match, Engine::Threaded, Engine::Bytecode];
+
+/// Number of unchanged lines shown around changes by `diff`.
+const DIFF_CONTEXT: usize = 2;
+
+/// How a test program shall end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    /// The program finishes normally.
+    Ok,
+    /// The program aborts.
+    Error,
+}
+
+/// The expectations and options of a test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub input: Vec<u8>,
+    /// The expected output, `None` if it is not checked.
+    pub output: Option<Vec<u8>>,
+    pub exit: Exit,
+    pub engines: Vec<Engine>,
+    /// The name of the dialect, see `dialect::lookup`.
+    pub dialect: String,
+    /// The scheduler of programs, which fork.
+    pub scheduler: Scheduler,
+    /// Whether to expand the macros of the program.
+    pub macros: bool,
+    pub opt_level: u32,
+    pub tape: Config,
+}
+
+impl Default for Spec {
+    fn default() -> Spec {
+        Spec {
+            input: Vec::new(),
+            output: None,
+            exit: Exit::Ok,
+            engines: ENGINES.to_vec(),
+            dialect: "brainfuck".to_string(),
+            scheduler: Scheduler::default(),
+            macros: false,
+            opt_level: 1,
+            tape: Config::default(),
+        }
+    }
+}
+
+/// Replaces the escapes of `text`, see the module documentation.
+pub fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| format!("Invalid escape \\x{:}", hex))?;
+                bytes.push(byte);
+            }
+            Some(c) => return Err(format!("Invalid escape \\{:}", c)),
+            None => return Err("Escape \\ at the end".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+impl Spec {
+    /// Parses the spec `text`.
+    /// `dir` is the directory relative paths are resolved against.
+    pub fn parse(text: &str, dir: &Path) -> Result<Spec, String> {
+        fn number<T: ::std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid {:} {:?}", key, value))
+        }
+
+        let mut spec = Spec::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let colon = line.find(':')
+                .ok_or_else(|| format!("{:}: expected KEY: VALUE", idx + 1))?;
+            let (key, value) = (line[..colon].trim(), line[colon + 1..].trim());
+            let parsed = match key {
+                "input" => unescape(value).map(|input| spec.input = input),
+                "output" => unescape(value).map(|output| spec.output = Some(output)),
+                "output-file" => {
+                    let mut output = Vec::new();
+                    File::open(dir.join(value))
+                        .and_then(|mut file| file.read_to_end(&mut output))
+                        .map(|_| spec.output = Some(output))
+                        .map_err(|err| format!("Cannot read {:} because {:}", value, err))
+                }
+                "exit" => {
+                    match value {
+                        "ok" => Ok(Exit::Ok),
+                        "error" => Ok(Exit::Error),
+                        _ => Err(format!("Invalid exit {:?}, expected ok or error", value)),
+                    }
+                    .map(|exit| spec.exit = exit)
+                }
+                "engines" => {
+                    value
+                        .split(',')
+                        .map(|engine| engine.trim().parse())
+                        .collect::<Result<Vec<Engine>, String>>()
+                        .map(|engines| spec.engines = engines)
+                }
+                "dialect" => {
+                    spec.dialect = value.to_string();
+                    Ok(())
+                }
+                "scheduler" => value.parse().map(|scheduler| spec.scheduler = scheduler),
+                "macros" => {
+                    value
+                        .parse()
+                        .map(|macros| spec.macros = macros)
+                        .map_err(|_| format!("Invalid macros {:?}, expected true or false", value))
+                }
+                "opt-level" => number(key, value).map(|level| spec.opt_level = level),
+                "tape-size" => number(key, value).map(|size| spec.tape.size = size),
+                "cell-width" => value.parse().map(|width| spec.tape.cell_width = width),
+                "eof" => value.parse().map(|eof| spec.tape.eof = eof),
+                _ => Err(format!("Unknown key {:?}", key)),
+            };
+            parsed.map_err(|err| format!("{:}: {:}", idx + 1, err))?;
+        }
+        Ok(spec)
+    }
+}
+
+/// A brainfuck program with its spec.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    /// The path of the `.bf` file.
+    pub path: PathBuf,
+    /// The program without the spec header.
+    /// Header lines are replaced by empty lines to keep the line numbers.
+    pub source: String,
+    pub spec: Spec,
+}
+
+/// Splits `text` into the spec header (the leading lines starting with `#`,
+/// without the `#`) and the rest with empty lines instead of the header.
+fn split_header(text: &str) -> (String, String) {
+    let mut header = String::new();
+    let mut source = String::new();
+    let mut in_header = true;
+    for line in text.split_inclusive('\n') {
+        in_header = in_header && line.starts_with('#');
+        if in_header {
+            header.push_str(&line[1..]);
+            source.push('\n');
+        } else {
+            source.push_str(line);
+        }
+    }
+    (header, source)
+}
+
+/// Loads the test `path`, a `.bf` or `.bfl` file.
+/// Returns `Ok(None)` if the file has neither a sidecar spec nor a header.
+pub fn load(path: &Path) -> Result<Option<TestCase>, String> {
+    let read = |path: &Path| {
+        let mut contents = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map(|_| String::from_utf8_lossy(&contents).into_owned())
+            .map_err(|err| format!("Cannot read {:} because {:}", path.display(), err))
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let text = read(path)?;
+    let sidecar = path.with_extension("spec");
+    let (spec_text, source) = if sidecar.is_file() {
+        (read(&sidecar)?, text)
+    } else {
+        let (header, source) = split_header(&text);
+        if header.is_empty() {
+            return Ok(None);
+        }
+        (header, source)
+    };
+    let spec = Spec::parse(&spec_text, dir)
+        .map_err(|err| format!("{:}:{:}", path.display(), err))?;
+    Ok(Some(TestCase {
+                path: path.to_path_buf(),
+                source,
+                spec,
+            }))
+}
+
+/// Returns the `.bf` and `.bfl` files in `path` and its subdirectories, sorted,
+/// or `path` itself if it is a file.
+/// Hidden directories and `target` directories are skipped.
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        if entry.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                files.extend(discover(&entry)?);
+            }
+        } else if entry
+                      .extension()
+                      .is_some_and(|ext| ext == "bf" || ext == lang::EXTENSION) {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+/// Why a test failed on an engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    /// The program does not parse.
+    Syntax(String),
+    /// The program aborted with the message, but should have finished.
+    Aborted(String),
+    /// The program finished, but should have aborted.
+    Finished,
+    /// The output differs from the expected output.
+    Output { expected: Vec<u8>, actual: Vec<u8> },
+}
+
+impl Failure {
+    /// Describes the failure, with a diff for differing outputs.
+    pub fn describe(&self) -> String {
+        match *self {
+            Failure::Syntax(ref msg) => format!("syntax error {:}\n", msg),
+            Failure::Aborted(ref msg) => format!("aborted: {:}\n", msg),
+            Failure::Finished => "finished, but should abort\n".to_string(),
+            Failure::Output {
+                ref expected,
+                ref actual,
+            } => format!("output differs\n{:}", diff(expected, actual)),
+        }
+    }
+}
+
+impl TestCase {
+    /// Parses the program in the dialect of its spec, after expanding its
+    /// macros if the spec says so, and optimizes it for `opt_level`, `0`
+    /// disables the optimizer. `.bfl` programs are compiled by `lang::compile`.
+    /// Fails with the syntax error, a preprocessor error or an unknown dialect.
+    pub fn compile(&self, opt_level: u32) -> Result<Program, String> {
+        let dialect = dialect::lookup(&self.spec.dialect)?;
+        let program = if self.path.extension().is_some_and(|ext| ext == lang::EXTENSION) {
+            lang::compile(&self.source)?.0
+        } else if self.spec.macros {
+            preprocess::expand(&self.source, &self.path)?
+                .parse_dialect(&*dialect)
+                .map_err(|err| err.to_string())?
+                .0
+        } else {
+            parser::check_dialect(&self.source, &*dialect).map_err(|err| err.to_string())?;
+            parser::parse_dialect(&self.source, &*dialect).0
+        };
+        if opt_level > 0 {
+            Ok(opt::optimize(&program))
+        } else {
+            Ok(program)
+        }
+    }
+}
+
+/// Runs `case` on `engine`.
+/// Programs which fork run on the scheduler of the spec instead,
+/// Boolfuck programs on a tape of `tape-size` bits.
+pub fn run_engine(case: &TestCase, engine: Engine) -> Result<(), Failure> {
+    let spec = &case.spec;
+    let program = case.compile(spec.opt_level).map_err(Failure::Syntax)?;
+    let machine = dialect::lookup(&spec.dialect).map_err(Failure::Syntax)?.machine();
+    let mut tape = Tape::new(&spec.tape);
+    let mut input = spec.input.as_slice();
+    let mut output = Vec::new();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        if machine == Machine::Bits {
+            boolfuck::run(&program, spec.tape.size, &mut input, &mut output)
+        } else if fork::forks(&program) {
+            fork::run_with(&program, &mut tape, &mut input, &mut output, spec.scheduler)
+        } else {
+            interp::run_with(engine, &program, &mut tape, &mut input, &mut output)
+        }
+    }));
+    match (result, spec.exit) {
+        (Ok(()), Exit::Error) => return Err(Failure::Finished),
+        (Err(payload), Exit::Ok) => {
+            let msg = payload
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+            return Err(Failure::Aborted(msg));
+        }
+        _ => (),
+    }
+    match spec.output {
+        Some(ref expected) if *expected != output => {
+            Err(Failure::Output {
+                    expected: expected.clone(),
+                    actual: output,
+                })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs `case` on all engines of its spec.
+pub fn run_case(case: &TestCase) -> Vec<(Engine, Result<(), Failure>)> {
+    case.spec
+        .engines
+        .iter()
+        .map(|engine| (*engine, run_engine(case, *engine)))
+        .collect()
+}
+
+/// Returns `line` with control characters escaped.
+fn printable(line: &[u8]) -> String {
+    let mut text = String::new();
+    for c in String::from_utf8_lossy(line).chars() {
+        if c.is_control() && c != '\t' {
+            text.extend(c.escape_default());
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+/// Returns a line diff from `expected` to `actual`: removed lines start
+/// with `-`, added lines with `+` and unchanged lines with a space.
+/// Only the unchanged lines around changes are shown.
+pub fn diff(expected: &[u8], actual: &[u8]) -> String {
+    let old: Vec<&[u8]> = expected.split(|b| *b == b'\n').collect();
+    let new: Vec<&[u8]> = actual.split(|b| *b == b'\n').collect();
+
+    // longest common subsequence of lines, lcs[i][j] for old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|&(_, line)| line.0 != ' ')
+        .map(|(idx, _)| idx)
+        .collect();
+    let near_change = |idx: usize| {
+        changed
+            .iter()
+            .any(|c| idx + DIFF_CONTEXT >= *c && idx <= *c + DIFF_CONTEXT)
+    };
+    let mut text = String::from("--- expected\n+++ actual\n");
+    let mut skipped = false;
+    for (idx, &(tag, line)) in lines.iter().enumerate() {
+        if near_change(idx) {
+            text.push_str(&format!("{:}{:}\n", tag, printable(line)));
+            skipped = false;
+        } else if !skipped {
+            text.push_str("...\n");
+            skipped = true;
+        }
+    }
+    text
+}
@@ -0,0 +1,178 @@
+//! Tests for the test runner `runner::run_case`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use interp::Engine;
+use runner::*;
+use tape::{CellWidth, Eof};
+
+fn case(source: &str, spec: &str) -> TestCase {
+    TestCase {
+        path: PathBuf::from("test.bf"),
+        source: source.to_string(),
+        spec: Spec::parse(spec, Path::new("")).unwrap(),
+    }
+}
+
+fn failures(case: &TestCase) -> Vec<(Engine, Failure)> {
+    run_case(case)
+        .into_iter()
+        .filter_map(|(engine, result)| result.err().map(|failure| (engine, failure)))
+        .collect()
+}
+
+#[test]
+fn unescape_text() {
+    assert_eq!(Ok(b"a\nb\t\\\0\x7f\r".to_vec()), unescape(r"a\nb\t\\\0\x7f\r"));
+    assert!(unescape(r"\q").is_err());
+    assert!(unescape(r"\xg0").is_err());
+    assert!(unescape("\\").is_err());
+}
+
+#[test]
+fn parse_spec() {
+    let spec = Spec::parse("input: ab\\n\n\noutput:  x \nexit: error\nengines: match, bytecode\n\
+                            macros: true\nopt-level: 0\ntape-size: 10\ncell-width: 16\n\
+                            eof: unchanged",
+                           Path::new(""))
+            .unwrap();
+    assert_eq!(b"ab\n".to_vec(), spec.input);
+    assert_eq!(Some(b"x".to_vec()), spec.output);
+    assert_eq!(Exit::Error, spec.exit);
+    assert_eq!(vec![Engine::Match, Engine::Bytecode], spec.engines);
+    assert!(spec.macros);
+    assert_eq!(0, spec.opt_level);
+    assert_eq!(10, spec.tape.size);
+    assert_eq!(CellWidth::Bits16, spec.tape.cell_width);
+    assert_eq!(Eof::Unchanged, spec.tape.eof);
+    assert_eq!(Spec::default(), Spec::parse("", Path::new("")).unwrap());
+}
+
+#[test]
+fn parse_invalid_spec() {
+    assert_eq!(Err("2: Unknown key \"outptu\"".to_string()),
+               Spec::parse("input: a\noutptu: b", Path::new("")));
+    assert!(Spec::parse("input", Path::new("")).is_err());
+    assert!(Spec::parse("exit: maybe", Path::new("")).is_err());
+    assert!(Spec::parse("engines: match, jit", Path::new("")).is_err());
+    assert!(Spec::parse("macros: yes", Path::new("")).is_err());
+}
+
+#[test]
+fn expand_macros() {
+    let results = run_case(&case("#define ECHO ,[.,]\nECHO",
+                                 "input: abc\noutput: abc\nmacros: true"));
+    assert!(results.iter().all(|r| r.1.is_ok()));
+}
+
+#[test]
+fn compile_lang() {
+    let mut case = case("var c;\nread c;\nwhile c { print c + 1; read c; }",
+                        "input: HAL\noutput: IBM");
+    case.path = PathBuf::from("test.bfl");
+    assert!(failures(&case).is_empty());
+    let lang = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/lang");
+    let files = discover(&lang).unwrap();
+    assert_eq!(vec![lang.join("fizzbuzz.bfl")], files);
+    let case = load(&files[0]).unwrap().expect("example has a header");
+    assert_eq!(Vec::<(Engine, Failure)>::new(), failures(&case));
+}
+
+#[test]
+fn pass_on_all_engines() {
+    let results = run_case(&case(",[.,]", "input: abc\noutput: abc"));
+    assert_eq!(vec![Engine::Match, Engine::Threaded, Engine::Bytecode],
+               results.iter().map(|r| r.0).collect::<Vec<_>>());
+    assert!(results.iter().all(|r| r.1.is_ok()));
+}
+
+#[test]
+fn fail_on_output() {
+    let failures = failures(&case("++++++++[>++++++++<-]>+.+.", "output: AC\nengines: threaded"));
+    assert_eq!(vec![(Engine::Threaded,
+                     Failure::Output {
+                         expected: b"AC".to_vec(),
+                         actual: b"AB".to_vec(),
+                     })],
+               failures);
+}
+
+#[test]
+fn fail_on_exit() {
+    let aborted = failures(&case("<", "engines: match"));
+    assert_eq!(1, aborted.len());
+    match aborted[0].1 {
+        Failure::Aborted(_) => (),
+        ref failure => panic!("Unexpected failure {:?}", failure),
+    }
+    assert_eq!(vec![(Engine::Match, Failure::Finished)],
+               failures(&case("+", "exit: error\nengines: match")));
+    assert!(failures(&case("+.<", "exit: error\noutput: \\x01")).is_empty());
+}
+
+#[test]
+fn fail_on_syntax_error() {
+    assert_eq!(vec![(Engine::Bytecode, Failure::Syntax("1:2: unbalanced ']'".to_string()))],
+               failures(&case("+]", "engines: bytecode")));
+}
+
+#[test]
+fn diff_lines() {
+    assert_eq!("--- expected\n+++ actual\n a\n-b\n+x\n c\n",
+               diff(b"a\nb\nc", b"a\nx\nc"));
+    assert_eq!("--- expected\n+++ actual\n...\n 3\n 4\n-5\n 6\n 7\n...\n",
+               diff(b"1\n2\n3\n4\n5\n6\n7\n8\n9", b"1\n2\n3\n4\n6\n7\n8\n9"));
+    assert_eq!("--- expected\n+++ actual\n-a\\u{7}\n+b\n", diff(b"a\x07", b"b"));
+}
+
+#[test]
+fn load_header_and_sidecar() {
+    let dir = env::temp_dir().join("bf_interp_runner_load");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    let write = |name: &str, contents: &str| {
+        File::create(dir.join(name))
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+    };
+    write("header.bf", "# output: A\n#input: A\n,.\n# not a header\n");
+    write("sub/sidecar.bf", ",.");
+    write("sub/sidecar.spec", "output-file: sidecar.out");
+    write("sub/sidecar.out", "X");
+    write("plain.bf", "+");
+    write("notes.txt", "+");
+
+    let files = discover(&dir).unwrap();
+    assert_eq!(vec![dir.join("header.bf"), dir.join("plain.bf"), dir.join("sub/sidecar.bf")],
+               files);
+
+    let header = load(&files[0]).unwrap().unwrap();
+    assert_eq!("\n\n,.\n# not a header\n", header.source);
+    assert_eq!(b"A".to_vec(), header.spec.input);
+    assert_eq!(Some(b"A".to_vec()), header.spec.output);
+    assert!(failures(&header).is_empty());
+
+    assert_eq!(None, load(&files[1]).unwrap());
+
+    let sidecar = load(&files[2]).unwrap().unwrap();
+    assert_eq!(",.", sidecar.source);
+    assert_eq!(Some(b"X".to_vec()), sidecar.spec.output);
+    assert_eq!(3, failures(&sidecar).len());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn example_tests_pass() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/tests");
+    let files = discover(&dir).unwrap();
+    assert!(files.len() >= 4);
+    for file in files {
+        let case = load(&file).unwrap().expect("example has a spec");
+        assert_eq!(Vec::<(Engine, Failure)>::new(), failures(&case), "{:?}", file);
+    }
+}
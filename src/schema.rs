@@ -0,0 +1,136 @@
+//! Versioned serialization schema for `ast::Program` (feature `serde`).
+//!
+//! A `Program` serializes as
+//! `{"version": 5, "instructions": [{"Inc": 3}, {"LoopEntry": 3}, "Output", ...]}`.
+//! New `Instruction` variants only ever get added, so a dump of an older
+//! version is always loadable. A dump of a newer version is rejected,
+//! because it may contain variants this version does not know, and so is
+//! a dump containing variants newer than its version.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+
+/// The schema version written by this version of bf-interp.
+///
+/// History:
+/// * `1`: `MoveLeft`, `MoveRight`, `Inc`, `Dec`, `Input`, `Output`,
+///   `LoopEntry` and `LoopExit`.
+/// * `2`: `Breakpoint`.
+/// * `3`: `ProcedureStart`, `ProcedureEnd` and `Call`.
+/// * `4`: `Fork`.
+/// * `5`: `End`, `Store`, `Load`, `ShiftRight`, `ShiftLeft`, `Not`, `Xor`,
+///   `And` and `Or`.
+pub const SCHEMA_VERSION: u32 = 5;
+
+/// Returns the schema version which added `instr`, see `SCHEMA_VERSION`.
+pub fn version(instr: Instruction) -> u32 {
+    match instr {
+        MoveLeft(_) | MoveRight(_) | Inc(_) | Dec(_) | Input | Output | LoopEntry(_) |
+        LoopExit(_) => 1,
+        Breakpoint => 2,
+        ProcedureStart(_) | ProcedureEnd(_) | Call => 3,
+        Fork => 4,
+        End | Store | Load | ShiftRight | ShiftLeft | Not | Xor | And | Or => 5,
+    }
+}
+
+/// The serialized form of a `Program`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramSchema {
+    /// Schema version of the dump (see `SCHEMA_VERSION`).
+    pub version: u32,
+    /// The instructions of the program.
+    pub instructions: Vec<Instruction>,
+}
+
+/// Errors loading a `ProgramSchema` as `Program`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaError {
+    /// The dump was written by a newer version of bf-interp.
+    UnsupportedVersion(u32),
+    /// The instruction at the given index was added by the given schema
+    /// version, which is newer than the version of the dump.
+    NewerInstruction(usize, u32),
+    /// The loop instruction at the given index does not point to its
+    /// corresponding loop instruction.
+    InvalidLoop(usize),
+    /// The procedure instruction at the given index does not point to its
+    /// corresponding procedure instruction.
+    InvalidProcedure(usize),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaError::UnsupportedVersion(version) => {
+                write!(f,
+                       "unsupported schema version {:} (supported up to {:})",
+                       version,
+                       SCHEMA_VERSION)
+            }
+            SchemaError::NewerInstruction(idx, version) => {
+                write!(f, "instruction at pc={:} needs schema version {:}", idx, version)
+            }
+            SchemaError::InvalidLoop(idx) => write!(f, "invalid loop target at pc={:}", idx),
+            SchemaError::InvalidProcedure(idx) => {
+                write!(f, "invalid procedure target at pc={:}", idx)
+            }
+        }
+    }
+}
+
+impl From<Program> for ProgramSchema {
+    fn from(program: Program) -> ProgramSchema {
+        ProgramSchema {
+            version: SCHEMA_VERSION,
+            instructions: program.instructions,
+        }
+    }
+}
+
+impl TryFrom<ProgramSchema> for Program {
+    type Error = SchemaError;
+
+    /// Checks the version, the versions of the instructions and the loop and
+    /// procedure targets of `schema`.
+    fn try_from(schema: ProgramSchema) -> Result<Program, SchemaError> {
+        if schema.version > SCHEMA_VERSION {
+            return Err(SchemaError::UnsupportedVersion(schema.version));
+        }
+        let instructions = schema.instructions;
+        let mut loop_stack = Vec::new();
+        for (idx, instr) in instructions.iter().enumerate() {
+            if version(*instr) > schema.version {
+                return Err(SchemaError::NewerInstruction(idx, version(*instr)));
+            }
+            let (target, entry_instr, error): (_, _, fn(usize) -> SchemaError) = match *instr {
+                LoopEntry(_) | ProcedureStart(_) => {
+                    loop_stack.push(idx);
+                    continue;
+                }
+                LoopExit(target) => (target, LoopEntry(idx), SchemaError::InvalidLoop),
+                ProcedureEnd(target) => {
+                    (target, ProcedureStart(idx), SchemaError::InvalidProcedure)
+                }
+                _ => continue,
+            };
+            let entry = loop_stack.pop().ok_or_else(|| error(idx))?;
+            if target != entry {
+                return Err(error(idx));
+            }
+            if instructions[entry] != entry_instr {
+                return Err(error(entry));
+            }
+        }
+        if let Some(entry) = loop_stack.pop() {
+            return Err(match instructions[entry] {
+                           ProcedureStart(_) => SchemaError::InvalidProcedure(entry),
+                           _ => SchemaError::InvalidLoop(entry),
+                       });
+        }
+        Ok(Program { instructions })
+    }
+}
@@ -0,0 +1,82 @@
+//! Tests for the serde schema of `ast::Program`.
+
+use serde_json;
+
+use ast::Program;
+use ast::Instruction::*;
+use opt::optimize;
+use parser::parse;
+
+#[test]
+fn serialize_instructions() {
+    let p = Program { instructions: vec![Inc(3), LoopEntry(3), MoveRight(1), LoopExit(1), Output] };
+    assert_eq!(r#"{"version":5,"instructions":[{"Inc":3},{"LoopEntry":3},{"MoveRight":1},{"LoopExit":1},"Output"]}"#,
+               serde_json::to_string(&p).expect("serialize must be ok"));
+}
+
+#[test]
+fn round_trip_optimized_program() {
+    let p = optimize(&parse(",++>+++[-<+>]<.".to_string()));
+    let json = serde_json::to_string(&p).expect("serialize must be ok");
+    let loaded: Program = serde_json::from_str(&json).expect("deserialize must be ok");
+    assert_eq!(p, loaded);
+}
+
+#[test]
+fn load_version_1_dump() {
+    let json = r#"{"version":1,"instructions":["Input",{"Dec":2},{"MoveLeft":4}]}"#;
+    let loaded: Program = serde_json::from_str(json).expect("deserialize must be ok");
+    assert_eq!(Program { instructions: vec![Input, Dec(2), MoveLeft(4)] },
+               loaded);
+}
+
+#[test]
+fn reject_newer_version() {
+    let json = r#"{"version":99,"instructions":[]}"#;
+    let err = serde_json::from_str::<Program>(json).expect_err("deserialize must fail");
+    assert!(err.to_string().contains("unsupported schema version 99"));
+}
+
+#[test]
+fn reject_instruction_newer_than_version() {
+    let json = r#"{"version":2,"instructions":["Breakpoint",{"Inc":1},"Xor"]}"#;
+    let err = serde_json::from_str::<Program>(json).expect_err("deserialize must fail");
+    assert!(err.to_string().contains("instruction at pc=2 needs schema version 5"),
+            "{:}",
+            err);
+}
+
+#[test]
+fn reject_unknown_instruction() {
+    let json = r#"{"version":1,"instructions":["Teleport"]}"#;
+    assert!(serde_json::from_str::<Program>(json).is_err());
+}
+
+#[test]
+fn reject_invalid_loop_target() {
+    let json = r#"{"version":1,"instructions":[{"LoopEntry":1},{"LoopExit":1}]}"#;
+    let err = serde_json::from_str::<Program>(json).expect_err("deserialize must fail");
+    assert!(err.to_string().contains("invalid loop target at pc=1"));
+}
+
+#[test]
+fn reject_unbalanced_loop() {
+    let json = r#"{"version":1,"instructions":[{"LoopEntry":1}]}"#;
+    assert!(serde_json::from_str::<Program>(json).is_err());
+}
+
+#[test]
+fn round_trip_procedures() {
+    let p = Program { instructions: vec![ProcedureStart(2), Inc(1), ProcedureEnd(0), Call] };
+    let json = serde_json::to_string(&p).expect("serialize must be ok");
+    assert!(json.contains(r#"{"ProcedureStart":2}"#));
+    let loaded: Program = serde_json::from_str(&json).expect("deserialize must be ok");
+    assert_eq!(p, loaded);
+}
+
+#[test]
+fn reject_mismatched_procedure() {
+    let json = r#"{"version":3,"instructions":[{"LoopEntry":1},{"ProcedureEnd":0}]}"#;
+    let err = serde_json::from_str::<Program>(json).expect_err("deserialize must fail");
+    assert!(err.to_string().contains("invalid procedure target at pc=0"));
+}
@@ -0,0 +1,87 @@
+//! Source positions of instructions, see `parser::parse_with`
+//! and `opt::optimize_with`.
+
+use std::fmt;
+
+/// A position in the source text.
+/// Lines and columns start at `1`, columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    /// Prints `line:column`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:}:{:}", self.line, self.column)
+    }
+}
+
+/// The range of source text an instruction was parsed from.
+/// Both `start` and `end` are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Returns the span of a single character at `position`.
+    pub fn at(position: Position) -> Span {
+        Span {
+            start: position,
+            end: position,
+        }
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn merge(&self, other: &Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    /// Prints `line:column` for single characters and
+    /// `line:column-line:column` otherwise.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{:}", self.start)
+        } else {
+            write!(f, "{:}-{:}", self.start, self.end)
+        }
+    }
+}
+
+/// Maps each instruction of a `Program` to its `Span` in the source text.
+/// The span of the instruction at index `pc` is at index `pc`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    spans: Vec<Span>,
+}
+
+impl SourceMap {
+    /// Creates a source map from the spans of all instructions.
+    pub fn new(spans: Vec<Span>) -> SourceMap {
+        SourceMap { spans }
+    }
+
+    /// Returns the spans of all instructions.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Returns the span of the instruction at `pc`.
+    pub fn span(&self, pc: usize) -> Option<Span> {
+        self.spans.get(pc).cloned()
+    }
+
+    /// Returns the first instruction at or behind `position`,
+    /// i.e. the first instruction whose span does not end before `position`.
+    pub fn find(&self, position: Position) -> Option<usize> {
+        self.spans.iter().position(|span| span.end >= position)
+    }
+}
@@ -0,0 +1,340 @@
+//! The tape (aka band) of memory cells the engines operate on.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+/// Maximum nesting depth of pbrain procedure calls.
+/// Deeper calls panic instead of exhausting the memory or the stack.
+pub const MAX_CALL_DEPTH: usize = 1 << 12;
+
+/// Width of a single memory cell.
+/// Incrementing and decrementing wraps at this width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellWidth {
+    /// Cells are bytes (`0` to `255`), the classical brainfuck.
+    Bits8,
+    /// Cells range from `0` to `65535`.
+    Bits16,
+    /// Cells range from `0` to `4294967295`.
+    Bits32,
+}
+
+impl FromStr for CellWidth {
+    type Err = String;
+
+    /// Parses the number of bits: `8`, `16` or `32`.
+    fn from_str(s: &str) -> Result<CellWidth, String> {
+        match s {
+            "8" => Ok(CellWidth::Bits8),
+            "16" => Ok(CellWidth::Bits16),
+            "32" => Ok(CellWidth::Bits32),
+            _ => Err(format!("Unknown cell width {:?}", s)),
+        }
+    }
+}
+
+/// What `Input` (`,`) writes to the current cell at the end of input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eof {
+    /// Sets the cell to `0`.
+    Zero,
+    /// Sets the cell to `-1`, i.e. the maximum value of the cell width.
+    MinusOne,
+    /// Leaves the cell unchanged.
+    Unchanged,
+}
+
+impl FromStr for Eof {
+    type Err = String;
+
+    /// Parses `zero`, `minus-one` or `unchanged`.
+    fn from_str(s: &str) -> Result<Eof, String> {
+        match s {
+            "zero" => Ok(Eof::Zero),
+            "minus-one" => Ok(Eof::MinusOne),
+            "unchanged" => Ok(Eof::Unchanged),
+            _ => Err(format!("Unknown EOF mode {:?}", s)),
+        }
+    }
+}
+
+/// Configuration of a `Tape`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// Number of cells.
+    pub size: usize,
+    /// Width of each cell.
+    pub cell_width: CellWidth,
+    /// Behavior of `Input` at the end of input.
+    pub eof: Eof,
+}
+
+impl Default for Config {
+    /// 30000 byte cells, `0` at the end of input.
+    fn default() -> Config {
+        Config {
+            size: 30000,
+            cell_width: CellWidth::Bits8,
+            eof: Eof::Zero,
+        }
+    }
+}
+
+/// The memory cells and the data-pointer.
+/// All cells are stored as `u32` and truncated to the configured cell width.
+///
+/// The tape also holds the state of pbrain procedures: the defined
+/// procedures and the return addresses of the active calls. Addresses are
+/// up to the engine, e.g. instruction indices or bytecode offsets.
+/// Extended Brainfuck's storage register is kept on the tape as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tape {
+    cells: Vec<u32>,
+    dataptr: usize,
+    mask: u32,
+    eof: Eof,
+    procedures: HashMap<u32, usize>,
+    calls: Vec<usize>,
+    storage: u32,
+}
+
+impl Tape {
+    /// Creates a tape with all cells `0` and the data-pointer at `0`.
+    pub fn new(config: &Config) -> Tape {
+        Tape {
+            cells: vec![0; config.size],
+            dataptr: 0,
+            mask: match config.cell_width {
+                CellWidth::Bits8 => 0xff,
+                CellWidth::Bits16 => 0xffff,
+                CellWidth::Bits32 => 0xffff_ffff,
+            },
+            eof: config.eof,
+            procedures: HashMap::new(),
+            calls: Vec::new(),
+            storage: 0,
+        }
+    }
+
+    /// Returns all cells.
+    pub fn cells(&self) -> &[u32] {
+        &self.cells
+    }
+
+    /// Returns the data-pointer.
+    pub fn dataptr(&self) -> usize {
+        self.dataptr
+    }
+
+    /// Moves the data-pointer to `dataptr`.
+    /// Moving behind the end of the tape panics on the next cell access.
+    pub fn set_dataptr(&mut self, dataptr: usize) {
+        self.dataptr = dataptr;
+    }
+
+    /// Returns the value of the cell at the data-pointer.
+    #[inline]
+    pub fn get(&self) -> u32 {
+        self.cells[self.dataptr]
+    }
+
+    /// Sets the cell at the data-pointer to `value` truncated to the cell width.
+    #[inline]
+    pub fn set(&mut self, value: u32) {
+        self.cells[self.dataptr] = value & self.mask;
+    }
+
+    /// Moves the data-pointer left by `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data-pointer moves left of `0`.
+    #[inline]
+    pub fn move_left(&mut self, offset: usize) {
+        self.dataptr = self.dataptr
+            .checked_sub(offset)
+            .expect("Data-pointer moved left of the tape");
+    }
+
+    /// Moves the data-pointer right by `offset`.
+    /// Moving behind the end of the tape panics on the next cell access.
+    #[inline]
+    pub fn move_right(&mut self, offset: usize) {
+        self.dataptr += offset;
+    }
+
+    /// Increments the cell at the data-pointer by `increment`.
+    #[inline]
+    pub fn inc(&mut self, increment: u8) {
+        let cell = &mut self.cells[self.dataptr];
+        *cell = cell.wrapping_add(increment as u32) & self.mask;
+    }
+
+    /// Decrements the cell at the data-pointer by `decrement`.
+    #[inline]
+    pub fn dec(&mut self, decrement: u8) {
+        let cell = &mut self.cells[self.dataptr];
+        *cell = cell.wrapping_sub(decrement as u32) & self.mask;
+    }
+
+    /// Returns the storage register of Extended Brainfuck.
+    pub fn storage(&self) -> u32 {
+        self.storage
+    }
+
+    /// Sets the storage register to `value`.
+    pub fn set_storage(&mut self, value: u32) {
+        self.storage = value;
+    }
+
+    /// Copies the cell at the data-pointer into the storage register.
+    #[inline]
+    pub fn store(&mut self) {
+        self.storage = self.get();
+    }
+
+    /// Copies the storage register into the cell at the data-pointer.
+    #[inline]
+    pub fn load(&mut self) {
+        let value = self.storage;
+        self.set(value);
+    }
+
+    /// Shifts the cell at the data-pointer one bit to the right.
+    #[inline]
+    pub fn shift_right(&mut self) {
+        let value = self.get() >> 1;
+        self.set(value);
+    }
+
+    /// Shifts the cell at the data-pointer one bit to the left,
+    /// truncated to the cell width.
+    #[inline]
+    pub fn shift_left(&mut self) {
+        let value = self.get() << 1;
+        self.set(value);
+    }
+
+    /// Inverts the bits of the cell at the data-pointer.
+    #[inline]
+    pub fn not(&mut self) {
+        let value = !self.get();
+        self.set(value);
+    }
+
+    /// Sets the cell at the data-pointer to its xor with the storage register.
+    #[inline]
+    pub fn xor(&mut self) {
+        let value = self.get() ^ self.storage;
+        self.set(value);
+    }
+
+    /// Sets the cell at the data-pointer to its and with the storage register.
+    #[inline]
+    pub fn and(&mut self) {
+        let value = self.get() & self.storage;
+        self.set(value);
+    }
+
+    /// Sets the cell at the data-pointer to its or with the storage register.
+    #[inline]
+    pub fn or(&mut self) {
+        let value = self.get() | self.storage;
+        self.set(value);
+    }
+
+    /// Defines the procedure numbered by the cell at the data-pointer
+    /// with its body at `address`.
+    /// Returns the address of the previous definition of the procedure.
+    pub fn define(&mut self, address: usize) -> Option<usize> {
+        let number = self.get();
+        self.procedures.insert(number, address)
+    }
+
+    /// Returns the address of the procedure `number`, if defined.
+    pub fn procedure(&self, number: u32) -> Option<usize> {
+        self.procedures.get(&number).cloned()
+    }
+
+    /// Sets the address of the procedure `number`, `None` undefines it.
+    pub fn set_procedure(&mut self, number: u32, address: Option<usize>) {
+        match address {
+            Some(address) => self.procedures.insert(number, address),
+            None => self.procedures.remove(&number),
+        };
+    }
+
+    /// Calls the procedure numbered by the cell at the data-pointer:
+    /// pushes `return_address` and returns the address of the procedure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the procedure is not defined or if the calls nest deeper
+    /// than `MAX_CALL_DEPTH`.
+    pub fn call(&mut self, return_address: usize) -> usize {
+        let number = self.get();
+        let address = self.procedure(number)
+            .unwrap_or_else(|| panic!("Undefined procedure {:}", number));
+        if self.calls.len() >= MAX_CALL_DEPTH {
+            panic!("Procedure calls nested deeper than {:}", MAX_CALL_DEPTH);
+        }
+        self.calls.push(return_address);
+        address
+    }
+
+    /// Returns from the current procedure call: pops and returns its return address.
+    ///
+    /// # Panics
+    ///
+    /// Panics outside of a procedure call.
+    pub fn ret(&mut self) -> usize {
+        self.calls.pop().expect("Return outside of a procedure call")
+    }
+
+    /// Returns the return addresses of the active procedure calls, innermost last.
+    pub fn calls(&self) -> &[usize] {
+        &self.calls
+    }
+
+    /// Swaps the return addresses of the active procedure calls with `calls`,
+    /// e.g. to switch between the threads of `fork::run_with`.
+    pub fn swap_calls(&mut self, calls: &mut Vec<usize>) {
+        ::std::mem::swap(&mut self.calls, calls);
+    }
+
+    /// Pushes the return address of a procedure call without calling it.
+    pub fn push_call(&mut self, return_address: usize) {
+        self.calls.push(return_address);
+    }
+
+    /// Returns whether the cell at the data-pointer is `0`.
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        0 == self.get()
+    }
+
+    /// Reads one byte from `input` into the cell at the data-pointer.
+    /// At the end of input, the cell is set according to the `Eof` configuration.
+    pub fn read(&mut self, input: &mut dyn Read) {
+        let mut buf = [0u8; 1];
+        match input.read(&mut buf) {
+            Err(_) => panic!("Cannot read from stdin"),
+            Ok(0) => {
+                match self.eof {
+                    Eof::Zero => self.set(0),
+                    Eof::MinusOne => self.set(u32::MAX),
+                    Eof::Unchanged => (),
+                }
+            }
+            Ok(_) => self.set(buf[0] as u32),
+        }
+    }
+
+    /// Writes the lowest byte of the cell at the data-pointer to `output`.
+    pub fn write(&self, output: &mut dyn Write) {
+        output
+            .write_all(&[self.get() as u8])
+            .expect("Output error");
+    }
+}
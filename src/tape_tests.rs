@@ -0,0 +1,124 @@
+//! Tests for `tape::Tape` and its configurations.
+
+use ast::Program;
+use buffer::*;
+use interp::{self, Engine};
+use parser::parse;
+use tape::{CellWidth, Config, Eof, Tape};
+
+fn execute_and_check(config: Config, program: &str, input: &str, exp_output: &[u8]) {
+    let p = parse(program.to_string());
+    for engine in &[Engine::Match, Engine::Threaded, Engine::Bytecode] {
+        let mut input = IBuffer::from_str(input);
+        let mut output = Vec::new();
+        interp::run_with(*engine, &p, &mut Tape::new(&config), &mut input, &mut output);
+        assert_eq!(exp_output, output.as_slice(), "engine {:?}", engine);
+    }
+}
+
+fn config(cell_width: CellWidth, eof: Eof) -> Config {
+    Config {
+        size: 100,
+        cell_width,
+        eof,
+    }
+}
+
+#[test]
+fn default_config() {
+    let tape = Tape::new(&Config::default());
+    assert_eq!(30000, tape.cells().len());
+    assert_eq!(0, tape.dataptr());
+}
+
+#[test]
+fn output_raw_bytes() {
+    execute_and_check(Config::default(), "-.", "", &[255]);
+}
+
+#[test]
+fn wrap_8_bit() {
+    execute_and_check(config(CellWidth::Bits8, Eof::Zero), "-.+.", "", &[255, 0]);
+}
+
+#[test]
+fn wrap_16_bit() {
+    // 256 is not 0 with 16 bit, so the loop runs
+    let mut tape = Tape::new(&config(CellWidth::Bits16, Eof::Zero));
+    interp::interp_with(&parse("-".to_string()),
+                        &mut tape,
+                        &mut IBuffer::from_str(""),
+                        &mut Vec::new());
+    assert_eq!(0xffff, tape.get());
+    execute_and_check(config(CellWidth::Bits16, Eof::Zero),
+                      "++++++++++++++++[>++++++++++++++++<-]>[>+<[-]]>.",
+                      "",
+                      &[1]);
+}
+
+#[test]
+fn wrap_32_bit() {
+    let mut tape = Tape::new(&config(CellWidth::Bits32, Eof::Zero));
+    tape.dec(1);
+    assert_eq!(0xffff_ffff, tape.get());
+    tape.inc(2);
+    assert_eq!(1, tape.get());
+}
+
+#[test]
+fn eof_zero() {
+    execute_and_check(config(CellWidth::Bits8, Eof::Zero), ",.+,.", "a", &[b'a', 0]);
+}
+
+#[test]
+fn eof_minus_one() {
+    execute_and_check(config(CellWidth::Bits8, Eof::MinusOne), ",.,.", "a", &[b'a', 255]);
+    let mut tape = Tape::new(&config(CellWidth::Bits16, Eof::MinusOne));
+    tape.read(&mut IBuffer::from_str(""));
+    assert_eq!(0xffff, tape.get());
+}
+
+#[test]
+fn eof_unchanged() {
+    execute_and_check(config(CellWidth::Bits8, Eof::Unchanged), ",+,.", "a", b"b");
+}
+
+#[test]
+fn tape_keeps_state() {
+    let mut tape = Tape::new(&Config::default());
+    let p = parse("+++>++".to_string());
+    interp::interp_with(&p, &mut tape, &mut IBuffer::from_str(""), &mut Vec::new());
+    interp::interp_with(&Program { instructions: p.instructions.clone() },
+                        &mut tape,
+                        &mut IBuffer::from_str(""),
+                        &mut Vec::new());
+    assert_eq!(2, tape.dataptr());
+    assert_eq!(&[3, 5, 2], &tape.cells()[0..3]);
+}
+
+#[test]
+#[should_panic]
+fn move_left_of_tape() {
+    execute_and_check(Config::default(), "<", "", &[]);
+}
+
+#[test]
+#[should_panic]
+fn move_right_of_tape() {
+    execute_and_check(config(CellWidth::Bits8, Eof::Zero), "+[>+]", "", &[]);
+}
+
+#[test]
+fn bitwise_operations_wrap() {
+    let mut tape = Tape::new(&config(CellWidth::Bits8, Eof::Zero));
+    tape.set(0x81);
+    tape.shift_left();
+    assert_eq!(0x02, tape.get());
+    tape.not();
+    assert_eq!(0xfd, tape.get());
+    tape.store();
+    tape.shift_right();
+    tape.xor();
+    assert_eq!(0x7e ^ 0xfd, tape.get());
+    assert_eq!(0xfd, tape.storage());
+}
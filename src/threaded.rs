@@ -0,0 +1,226 @@
+//! Threaded-code interpreter of brainfuck ast.
+//!
+//! Instead of matching on every instruction at runtime (see `interp::interp`),
+//! the program is compiled once into a vector of boxed closures.
+//! Each closure executes one instruction, each loop is compiled into
+//! one closure running its compiled body, so there is no program counter
+//! and no loop target lookup left at runtime.
+//! Loops containing pbrain procedures or calls are the exception: they run
+//! as steps, closures returning the next program counter, so calls jump to
+//! the procedure body and return through the call stack of the tape instead
+//! of nesting native stack frames, which could overflow before
+//! `tape::MAX_CALL_DEPTH`.
+//! Extended Brainfuck's `End` sets a flag, which the compiled blocks check
+//! after each op, but only in programs containing `End`.
+
+use std::cell::Cell;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+use tape::{Config, Tape};
+
+/// A single compiled instruction or a whole compiled loop.
+type Op = Box<dyn Fn(&mut Tape, &mut dyn Read, &mut dyn Write)>;
+
+/// A compiled instruction returning the index of the next step to run.
+type Step = Box<dyn Fn(&mut Tape, &mut dyn Read, &mut dyn Write) -> usize>;
+
+/// The flag set by `End`, `None` if the program does not contain `End`.
+type Ended = Option<Rc<Cell<bool>>>;
+
+/// A brainfuck program compiled into closures (see `compile`).
+pub struct CompiledProgram {
+    /// The step of each instruction, the index of a step is the index of
+    /// its instruction. The steps of instructions inside a compiled loop
+    /// are never run.
+    steps: Vec<Step>,
+    ended: Ended,
+}
+
+/// Compiles `program` into a `CompiledProgram`.
+/// Compiling is only worth it, if the program is executed afterwards.
+pub fn compile(program: &Program) -> CompiledProgram {
+    let ended = if program.instructions.contains(&End) {
+        Some(Rc::new(Cell::new(false)))
+    } else {
+        None
+    };
+    CompiledProgram {
+        steps: compile_steps(&program.instructions, &ended),
+        ended,
+    }
+}
+
+/// Runs `ops`, returns `false` if they ended the program.
+#[inline]
+fn run_ops(ops: &[Op],
+           ended: &Ended,
+           t: &mut Tape,
+           input: &mut dyn Read,
+           output: &mut dyn Write)
+           -> bool {
+    for op in ops {
+        op(t, input, output);
+        if ended.as_ref().is_some_and(|ended| ended.get()) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Returns whether the instructions from `begin` up to (excluding) `end`
+/// can run as a compiled block, i.e. contain no pbrain procedure and call.
+fn compilable(instructions: &[Instruction], begin: usize, end: usize) -> bool {
+    !instructions[begin..end]
+         .iter()
+         .any(|instr| matches!(*instr, Call | ProcedureStart(_) | ProcedureEnd(_)))
+}
+
+/// Compiles the step of each instruction.
+/// Loops which are `compilable` are compiled into a single step running
+/// their compiled body, the other loops jump like `interp::step`.
+fn compile_steps(instructions: &[Instruction], ended: &Ended) -> Vec<Step> {
+    let mut steps: Vec<Step> = Vec::with_capacity(instructions.len());
+    let mut pc = 0;
+    while pc < instructions.len() {
+        let next = pc + 1;
+        let step: Step = match instructions[pc] {
+            LoopEntry(target) if compilable(instructions, next, target) => {
+                let body = compile_block(instructions, next, target, ended);
+                let ended = ended.clone();
+                let step: Step = Box::new(move |t, input, output| {
+                    while !t.is_zero() {
+                        if !run_ops(&body, &ended, t, input, output) {
+                            break;
+                        }
+                    }
+                    target + 1
+                });
+                steps.push(step);
+                // the steps inside the loop are never run
+                while steps.len() <= target {
+                    steps.push(Box::new(move |_, _, _| unreachable!("Step inside a loop")));
+                }
+                pc = target + 1;
+                continue;
+            }
+            LoopEntry(target) => {
+                Box::new(move |t, _, _| if t.is_zero() { target + 1 } else { next })
+            }
+            LoopExit(target) => {
+                Box::new(move |t, _, _| if t.is_zero() { next } else { target + 1 })
+            }
+            ProcedureStart(target) => {
+                Box::new(move |t, _, _| {
+                             t.define(next);
+                             target + 1
+                         })
+            }
+            ProcedureEnd(_) => Box::new(|t, _, _| t.ret()),
+            Call => Box::new(move |t, _, _| t.call(next)),
+            End => {
+                let ended = ended.clone().expect("Program contains End");
+                Box::new(move |_, _, _| {
+                             ended.set(true);
+                             next
+                         })
+            }
+            _ => {
+                let op = compile_block(instructions, pc, next, ended)
+                    .pop()
+                    .unwrap_or_else(|| Box::new(|_, _, _| ()));
+                Box::new(move |t, input, output| {
+                             op(t, input, output);
+                             next
+                         })
+            }
+        };
+        steps.push(step);
+        pc = next;
+    }
+    steps
+}
+
+/// Compiles the instructions from index `begin` up to (excluding) `end`,
+/// which are `compilable`.
+/// Loops are compiled into a single `Op` that runs its compiled body,
+/// using the loop targets already resolved by the parser.
+fn compile_block(instructions: &[Instruction], begin: usize, end: usize, ended: &Ended) -> Vec<Op> {
+    let mut ops: Vec<Op> = Vec::new();
+    let mut pc = begin;
+    while pc < end {
+        ops.push(match instructions[pc] {
+                     MoveLeft(offset) => Box::new(move |t, _, _| t.move_left(offset)),
+                     MoveRight(offset) => Box::new(move |t, _, _| t.move_right(offset)),
+                     Inc(increment) => Box::new(move |t, _, _| t.inc(increment)),
+                     Dec(decrement) => Box::new(move |t, _, _| t.dec(decrement)),
+                     Output => Box::new(|t, _, output| t.write(output)),
+                     Input => Box::new(|t, input, _| t.read(input)),
+                     LoopEntry(target) => {
+                         let body = compile_block(instructions, pc + 1, target, ended);
+                         let ended = ended.clone();
+                         pc = target;
+                         Box::new(move |t, input, output| while !t.is_zero() {
+                                      if !run_ops(&body, &ended, t, input, output) {
+                                          break;
+                                      }
+                                  })
+                     }
+                     Breakpoint => {
+                         pc += 1;
+                         continue;
+                     }
+                     Fork => {
+                         Box::new(move |_, _, _| {
+                             panic!("Fork at pc={:} needs the brainfork scheduler `fork::run_with`",
+                                    pc)
+                         })
+                     }
+                     End => {
+                         let ended = ended.clone().expect("Program contains End");
+                         Box::new(move |_, _, _| ended.set(true))
+                     }
+                     Store => Box::new(|t, _, _| t.store()),
+                     Load => Box::new(|t, _, _| t.load()),
+                     ShiftRight => Box::new(|t, _, _| t.shift_right()),
+                     ShiftLeft => Box::new(|t, _, _| t.shift_left()),
+                     Not => Box::new(|t, _, _| t.not()),
+                     Xor => Box::new(|t, _, _| t.xor()),
+                     And => Box::new(|t, _, _| t.and()),
+                     Or => Box::new(|t, _, _| t.or()),
+                     instr @ LoopExit(_) |
+                     instr @ ProcedureStart(_) |
+                     instr @ ProcedureEnd(_) |
+                     instr @ Call => panic!("Unexpected instruction {:?} at pc={:}", instr, pc),
+                 });
+        pc += 1;
+    }
+    ops
+}
+
+impl CompiledProgram {
+    /// Runs the compiled program.
+    /// Reads (instruction `Input` aka `,`) from input.
+    /// Writes (instruction `Output` aka `.`) to output.
+    /// The tape has the same default configuration as for `interp::interp`.
+    pub fn run(&self, input: &mut dyn Read, output: &mut dyn Write) {
+        self.run_with(&mut Tape::new(&Config::default()), input, output);
+    }
+
+    /// Runs the compiled program on the given `tape`
+    /// (see `interp::interp_with`).
+    pub fn run_with(&self, tape: &mut Tape, input: &mut dyn Read, output: &mut dyn Write) {
+        if let Some(ref ended) = self.ended {
+            ended.set(false);
+        }
+        let mut pc = 0;
+        while pc < self.steps.len() {
+            pc = self.steps[pc](tape, input, output);
+            if self.ended.as_ref().is_some_and(|ended| ended.get()) {
+                break;
+            }
+        }
+    }
+}
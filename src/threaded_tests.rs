@@ -0,0 +1,111 @@
+//! Tests for the threaded-code interpreter `threaded::compile`.
+
+use ast::{Program, Instruction};
+use self::Instruction::*;
+use buffer::*;
+use interp::{self, Engine};
+use parser::parse;
+use threaded::compile;
+
+fn execute_and_check(program: &[Instruction], input: &str, exp_output: &[u8]) {
+    let mut input = IBuffer::from_str(input);
+    let mut output = Vec::new();
+    let p = Program { instructions: program.to_vec() };
+    compile(&p).run(&mut input, &mut output);
+    assert_eq!(exp_output, output.as_slice());
+}
+
+#[test]
+fn output_zero() {
+    execute_and_check(&[Output], "", &[0]);
+}
+
+#[test]
+fn inc_dec_wrapping() {
+    execute_and_check(&[Inc(255), Inc(1), Output, Dec(1), Inc(100), Output], "", &[0, 99]);
+}
+
+#[test]
+fn move_right_and_left() {
+    execute_and_check(&[Inc(2), MoveRight(2), Inc(1), Output, MoveLeft(2), Output],
+                      "",
+                      &[1, 2]);
+}
+
+#[test]
+fn input() {
+    execute_and_check(&[Input, Output, Input, Output], "a1", b"a1");
+}
+
+#[test]
+fn skip_loop() {
+    execute_and_check(&[MoveRight(1),
+                        Inc(1),
+                        MoveLeft(1),
+                        LoopEntry(5),
+                        Inc(1),
+                        LoopExit(3),
+                        Output],
+                      "",
+                      &[0]);
+}
+
+#[test]
+fn loop_move_value() {
+    execute_and_check(&[Inc(100),
+                        LoopEntry(6),
+                        Dec(1),
+                        MoveRight(3),
+                        Inc(1),
+                        MoveLeft(3),
+                        LoopExit(1),
+                        Output,
+                        MoveRight(3),
+                        Output],
+                      "",
+                      &[0, 100]);
+}
+
+#[test]
+fn engines_agree_on_hello_world() {
+    let p = parse("++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.\
+                   +++.------.--------.>>+.>++."
+                          .to_string());
+    let mut match_output = Vec::new();
+    interp::run(Engine::Match, &p, &mut IBuffer::from_str(""), &mut match_output);
+    let mut threaded_output = Vec::new();
+    interp::run(Engine::Threaded,
+                &p,
+                &mut IBuffer::from_str(""),
+                &mut threaded_output);
+    assert_eq!(b"Hello World!\n".to_vec(), match_output);
+    assert_eq!(match_output, threaded_output);
+}
+
+#[test]
+fn skip_breakpoint() {
+    execute_and_check(&[Inc(1), Breakpoint, LoopEntry(5), Breakpoint, Dec(1), LoopExit(2), Output],
+                      "",
+                      &[0]);
+}
+
+#[test]
+fn call_procedure() {
+    execute_and_check(&[ProcedureStart(5),
+                        MoveRight(1),
+                        Inc(1),
+                        Output,
+                        MoveLeft(1),
+                        ProcedureEnd(0),
+                        Call,
+                        Call],
+                      "",
+                      &[1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "Procedure calls nested deeper than")]
+fn endless_recursion() {
+    // the call depth limit panics before the native stack overflows
+    execute_and_check(&[ProcedureStart(2), Call, ProcedureEnd(0), Call], "", &[]);
+}
@@ -0,0 +1,333 @@
+//! Execution traces `trace::run`.
+//!
+//! `trace::run` interprets a program like `interp::interp_with` and calls a
+//! hook with a `Record` for each executed instruction. A `Tracer` writes the
+//! records to a sink as JSON lines or in a compact binary format, optionally
+//! only those of a pc range. Traces of the same program and input are
+//! deterministic, so they can be diffed between versions.
+//!
+//! Traces only show the match engine: the threaded and bytecode engines
+//! run whole loops without a hook per instruction, so `bf-interp trace`
+//! rejects `--engine` for them. To compare them, diff the outputs of `run`.
+//!
+//! The binary format starts with the magic bytes `BFT\0` and the format
+//! version (2 bytes, little endian). Each record follows as
+//! * the opcode of the instruction (see `bytecode`), with the bit `0x80` set
+//!   if the record has an I/O byte,
+//! * the pc, the operand (offset, value or loop target), the data-pointer,
+//!   the cell value before and after as LEB128 varints and
+//! * the I/O byte, if any.
+
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::ops::Range;
+use std::str::FromStr;
+
+use ast::{Instruction, Program};
+use ast::Instruction::*;
+use bytecode;
+use interp;
+use tape::Tape;
+
+/// Magic bytes at the beginning of each binary trace.
+pub const MAGIC: [u8; 4] = *b"BFT\0";
+
+/// Version of the binary trace format.
+pub const VERSION: u16 = 1;
+
+/// Opcode bit marking a record with an I/O byte.
+const IO_FLAG: u8 = 0x80;
+
+/// The execution of one instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record {
+    /// Index of the executed instruction.
+    pub pc: usize,
+    /// The executed instruction.
+    pub instruction: Instruction,
+    /// The data-pointer before the execution.
+    pub dataptr: usize,
+    /// The value of the cell at `dataptr` before the execution,
+    /// `0` if `dataptr` is right of the tape.
+    pub before: u32,
+    /// The value of the cell at `dataptr` after the execution,
+    /// `0` if `dataptr` is right of the tape.
+    pub after: u32,
+    /// The byte read by `Input` or written by `Output`.
+    /// `None` for other instructions and for `Input` at the end of input.
+    pub io: Option<u8>,
+}
+
+impl Record {
+    /// Returns the record as one line of JSON (without line break), e.g.
+    /// `{"pc":0,"op":"Inc","arg":1,"dataptr":0,"before":0,"after":1}`.
+    /// `arg` is only present for instructions with operand, `io` only
+    /// for records with an I/O byte.
+    pub fn to_json(&self) -> String {
+        let (op, arg) = match self.instruction {
+            MoveLeft(offset) => ("MoveLeft", Some(offset)),
+            MoveRight(offset) => ("MoveRight", Some(offset)),
+            Inc(val) => ("Inc", Some(val as usize)),
+            Dec(val) => ("Dec", Some(val as usize)),
+            Output => ("Output", None),
+            Input => ("Input", None),
+            LoopEntry(target) => ("LoopEntry", Some(target)),
+            LoopExit(target) => ("LoopExit", Some(target)),
+            Breakpoint => ("Breakpoint", None),
+            ProcedureStart(target) => ("ProcedureStart", Some(target)),
+            ProcedureEnd(target) => ("ProcedureEnd", Some(target)),
+            Call => ("Call", None),
+            Fork => ("Fork", None),
+            End => ("End", None),
+            Store => ("Store", None),
+            Load => ("Load", None),
+            ShiftRight => ("ShiftRight", None),
+            ShiftLeft => ("ShiftLeft", None),
+            Not => ("Not", None),
+            Xor => ("Xor", None),
+            And => ("And", None),
+            Or => ("Or", None),
+        };
+        let mut json = format!(r#"{{"pc":{:},"op":"{:}""#, self.pc, op);
+        if let Some(arg) = arg {
+            json.push_str(&format!(r#","arg":{:}"#, arg));
+        }
+        json.push_str(&format!(r#","dataptr":{:},"before":{:},"after":{:}"#,
+                               self.dataptr,
+                               self.before,
+                               self.after));
+        if let Some(io) = self.io {
+            json.push_str(&format!(r#","io":{:}"#, io));
+        }
+        json.push('}');
+        json
+    }
+
+    /// Appends the record in the binary format to `buf`.
+    fn write_binary(&self, buf: &mut Vec<u8>) {
+        let (op, operand) = bytecode::opcode(self.instruction);
+        buf.push(if self.io.is_some() { op | IO_FLAG } else { op });
+        for value in &[self.pc,
+                       operand,
+                       self.dataptr,
+                       self.before as usize,
+                       self.after as usize] {
+            bytecode::write_varint(buf, *value);
+        }
+        buf.extend(self.io);
+    }
+}
+
+/// Reads a binary trace written by a `Tracer` with `Format::Binary`.
+///
+/// Fails with `ErrorKind::InvalidData` if the magic bytes or the version
+/// do not match or if a record is malformed.
+pub fn read_binary(input: &mut dyn Read) -> io::Result<Vec<Record>> {
+    fn invalid(msg: &str) -> Error {
+        Error::new(ErrorKind::InvalidData, msg)
+    }
+
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    if data.len() < 6 || data[0..4] != MAGIC {
+        return Err(invalid("not a binary trace"));
+    }
+    let version = u16::from_le_bytes([data[4], data[5]]);
+    if version == 0 || version > VERSION {
+        return Err(invalid(&format!("unsupported trace version {:}", version)));
+    }
+    let mut records = Vec::new();
+    let mut pos = 6;
+    while pos < data.len() {
+        let op = data[pos];
+        pos += 1;
+        let mut values = [0usize; 5];
+        for value in values.iter_mut() {
+            *value = bytecode::read_varint(&data, &mut pos)
+                .ok_or_else(|| invalid("truncated record"))?;
+        }
+        let instruction = bytecode::from_opcode(op & !IO_FLAG, values[1])
+            .ok_or_else(|| invalid(&format!("invalid opcode {:?}", op)))?;
+        let io = if op & IO_FLAG != 0 {
+            let byte = *data.get(pos).ok_or_else(|| invalid("truncated record"))?;
+            pos += 1;
+            Some(byte)
+        } else {
+            None
+        };
+        records.push(Record {
+                         pc: values[0],
+                         instruction,
+                         dataptr: values[2],
+                         before: values[3] as u32,
+                         after: values[4] as u32,
+                         io,
+                     });
+    }
+    Ok(records)
+}
+
+/// The output format of a `Tracer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// One JSON object per line, see `Record::to_json`.
+    Json,
+    /// The compact binary format, see the module documentation.
+    Binary,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    /// Parses `json` or `binary`.
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "json" => Ok(Format::Json),
+            "binary" => Ok(Format::Binary),
+            _ => Err(format!("Unknown trace format {:?}", s)),
+        }
+    }
+}
+
+/// Options of a `Tracer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// The output format.
+    pub format: Format,
+    /// Only records with a pc in this range are written, all if `None`.
+    pub pcs: Option<Range<usize>>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            format: Format::Json,
+            pcs: None,
+        }
+    }
+}
+
+/// Writes records to a sink.
+pub struct Tracer<W: Write> {
+    sink: W,
+    options: Options,
+    started: bool,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Tracer<W> {
+    /// Creates a tracer writing to `sink`.
+    pub fn new(sink: W, options: Options) -> Tracer<W> {
+        Tracer {
+            sink,
+            options,
+            started: false,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Writes `record` unless its pc is filtered out.
+    /// The binary header is written with the first record.
+    pub fn record(&mut self, record: &Record) -> io::Result<()> {
+        if let Some(ref pcs) = self.options.pcs {
+            if !pcs.contains(&record.pc) {
+                return Ok(());
+            }
+        }
+        self.start()?;
+        match self.options.format {
+            Format::Json => writeln!(self.sink, "{:}", record.to_json()),
+            Format::Binary => {
+                self.buf.clear();
+                record.write_binary(&mut self.buf);
+                self.sink.write_all(&self.buf)
+            }
+        }
+    }
+
+    /// Writes the binary header unless it was already written.
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started && self.options.format == Format::Binary {
+            self.sink.write_all(&MAGIC)?;
+            self.sink.write_all(&VERSION.to_le_bytes())?;
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    /// Flushes the sink and returns it.
+    /// A binary trace without records still gets its header.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.start()?;
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+}
+
+/// Remembers the last byte passing through a reader or writer.
+struct LastByte<T> {
+    inner: T,
+    last: Option<u8>,
+}
+
+impl Read for LastByte<&mut dyn Read> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if len > 0 {
+            self.last = Some(buf[len - 1]);
+        }
+        Ok(len)
+    }
+}
+
+impl Write for LastByte<&mut dyn Write> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        if len > 0 {
+            self.last = Some(buf[len - 1]);
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Interprets `program` like `interp::interp_with` on `tape` and calls
+/// `hook` with the record of each executed instruction.
+/// Stops at the first error returned by `hook`.
+pub fn run(program: &Program,
+           tape: &mut Tape,
+           input: &mut dyn Read,
+           output: &mut dyn Write,
+           hook: &mut dyn FnMut(&Record) -> io::Result<()>)
+           -> io::Result<()> {
+    let mut input = LastByte {
+        inner: input,
+        last: None,
+    };
+    let mut output = LastByte {
+        inner: output,
+        last: None,
+    };
+    let mut pc: usize = 0;
+
+    while pc < program.instructions.len() {
+        let dataptr = tape.dataptr();
+        // the data-pointer may be right of the tape until the next access
+        let before = tape.cells().get(dataptr).cloned().unwrap_or(0);
+        input.last = None;
+        output.last = None;
+        let next = interp::step(program, pc, tape, &mut input, &mut output);
+        hook(&Record {
+                  pc,
+                  instruction: program.instructions[pc],
+                  dataptr,
+                  before,
+                  after: tape.cells().get(dataptr).cloned().unwrap_or(0),
+                  io: input.last.or(output.last),
+              })?;
+        pc = next;
+    }
+    Ok(())
+}
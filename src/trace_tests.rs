@@ -0,0 +1,165 @@
+//! Tests for the tracer `trace::run`.
+
+use buffer::*;
+use ast::Instruction::*;
+use parser::parse;
+use tape::{Config, Tape};
+use trace::{self, Format, Options, Record, Tracer};
+
+/// Traces `text` with input `input` and returns the records.
+fn records(text: &str, input: &str) -> Vec<Record> {
+    let program = parse(text.to_string());
+    let mut records = Vec::new();
+    trace::run(&program,
+               &mut Tape::new(&Config::default()),
+               &mut IBuffer::from_str(input),
+               &mut Vec::new(),
+               &mut |record| {
+                   records.push(*record);
+                   Ok(())
+               })
+            .expect("trace must not fail");
+    records
+}
+
+/// Traces `text` with `options` and returns the written trace.
+fn trace_to_vec(text: &str, options: Options) -> Vec<u8> {
+    let program = parse(text.to_string());
+    let mut tracer = Tracer::new(Vec::new(), options);
+    trace::run(&program,
+               &mut Tape::new(&Config::default()),
+               &mut IBuffer::from_str("a"),
+               &mut Vec::new(),
+               &mut |record| tracer.record(record))
+            .expect("trace must not fail");
+    tracer.finish().expect("finish must not fail")
+}
+
+#[test]
+fn record_each_step() {
+    assert_eq!(vec![Record {
+                        pc: 0,
+                        instruction: Input,
+                        dataptr: 0,
+                        before: 0,
+                        after: 97,
+                        io: Some(97),
+                    },
+                    Record {
+                        pc: 1,
+                        instruction: MoveRight(1),
+                        dataptr: 0,
+                        before: 97,
+                        after: 97,
+                        io: None,
+                    },
+                    Record {
+                        pc: 2,
+                        instruction: Dec(1),
+                        dataptr: 1,
+                        before: 0,
+                        after: 255,
+                        io: None,
+                    },
+                    Record {
+                        pc: 3,
+                        instruction: Output,
+                        dataptr: 1,
+                        before: 255,
+                        after: 255,
+                        io: Some(255),
+                    },
+                    Record {
+                        pc: 4,
+                        instruction: Input,
+                        dataptr: 1,
+                        before: 255,
+                        after: 0,
+                        io: None,
+                    }],
+               records(",>-.,", "a"));
+}
+
+#[test]
+fn record_right_of_tape() {
+    let program = parse(">><".to_string());
+    let config = Config {
+        size: 1,
+        ..Config::default()
+    };
+    let mut steps = Vec::new();
+    trace::run(&program,
+               &mut Tape::new(&config),
+               &mut IBuffer::from_str(""),
+               &mut Vec::new(),
+               &mut |record| {
+                   steps.push((record.dataptr, record.before, record.after));
+                   Ok(())
+               })
+            .expect("trace must not fail");
+    assert_eq!(vec![(0, 0, 0), (1, 0, 0), (2, 0, 0)], steps);
+}
+
+#[test]
+fn record_loops() {
+    let pcs: Vec<usize> = records("+[-]", "").iter().map(|r| r.pc).collect();
+    assert_eq!(vec![0, 1, 2, 3], pcs);
+    let pcs: Vec<usize> = records("++[-]", "").iter().map(|r| r.pc).collect();
+    assert_eq!(vec![0, 1, 2, 3, 4, 3, 4], pcs);
+}
+
+#[test]
+fn json_lines() {
+    let trace = trace_to_vec(",+.", Options::default());
+    assert_eq!(r#"{"pc":0,"op":"Input","dataptr":0,"before":0,"after":97,"io":97}
+{"pc":1,"op":"Inc","arg":1,"dataptr":0,"before":97,"after":98}
+{"pc":2,"op":"Output","dataptr":0,"before":98,"after":98,"io":98}
+"#,
+               String::from_utf8(trace).expect("trace must be utf-8"));
+}
+
+#[test]
+fn filter_pc_range() {
+    let options = Options {
+        format: Format::Json,
+        pcs: Some(1..2),
+    };
+    let trace = trace_to_vec("+++", options);
+    assert_eq!("{\"pc\":1,\"op\":\"Inc\",\"arg\":1,\"dataptr\":0,\"before\":1,\"after\":2}\n",
+               String::from_utf8(trace).expect("trace must be utf-8"));
+}
+
+#[test]
+fn binary_round_trip() {
+    let options = Options {
+        format: Format::Binary,
+        pcs: None,
+    };
+    let text = ",[->+<]>>+++++[-<<+>>]<<.";
+    let trace = trace_to_vec(text, options);
+    assert_eq!(b"BFT\0\x01\0", &trace[..6]);
+    let loaded = trace::read_binary(&mut trace.as_slice()).expect("read must be ok");
+    assert_eq!(records(text, "a"), loaded);
+}
+
+#[test]
+fn binary_header_without_records() {
+    let options = Options {
+        format: Format::Binary,
+        pcs: Some(100..200),
+    };
+    let trace = trace_to_vec("+", options);
+    assert_eq!(b"BFT\0\x01\0", trace.as_slice());
+    assert_eq!(Vec::<Record>::new(),
+               trace::read_binary(&mut trace.as_slice()).expect("read must be ok"));
+}
+
+#[test]
+fn binary_rejects_malformed() {
+    assert!(trace::read_binary(&mut &b"BFC\0\x01\0"[..]).is_err());
+    assert!(trace::read_binary(&mut &b"BFT\0\x02\0"[..]).is_err());
+    // truncated record
+    assert!(trace::read_binary(&mut &b"BFT\0\x01\0\x03\x00\x01"[..]).is_err());
+    // unknown opcode
+    assert!(trace::read_binary(&mut &b"BFT\0\x01\0\x7f\0\0\0\0\0"[..]).is_err());
+}